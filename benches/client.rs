@@ -0,0 +1,67 @@
+use std::time::Duration;
+
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use reqwest::Client;
+use tokio::runtime::Runtime;
+use wiremock::matchers::path;
+use wiremock::Mock;
+use wiremock::MockServer;
+use wiremock::ResponseTemplate;
+
+/// How many pages each iteration fetches, one after another like a scrape does
+const BATCH: usize = 50;
+
+async fn fetch_batch(client: &Client, url: &str) {
+    for _ in 0..BATCH {
+        client.get(url).send().await.unwrap().bytes().await.unwrap();
+    }
+}
+
+/// Compares sequential fetches with and without keeping connections alive, against a local server
+/// serving a record page so the network itself doesn't drown out the difference
+fn sequential_fetches(c: &mut Criterion) {
+    let runtime = Runtime::new().unwrap();
+    let page = std::fs::read_to_string("knuth.html").unwrap();
+    let server = runtime.block_on(async {
+        let server = MockServer::start().await;
+        Mock::given(path("/id.php"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(page))
+            .mount(&server)
+            .await;
+        server
+    });
+    let url = format!("{}/id.php", server.uri());
+
+    let clients = [
+        ("default", Client::new()),
+        (
+            "tuned",
+            Client::builder()
+                .pool_idle_timeout(Duration::from_secs(90))
+                .pool_max_idle_per_host(8)
+                .tcp_keepalive(Duration::from_secs(60))
+                .build()
+                .unwrap(),
+        ),
+        (
+            "no reuse",
+            Client::builder().pool_max_idle_per_host(0).build().unwrap(),
+        ),
+    ];
+
+    let mut group = c.benchmark_group("sequential_fetches");
+    group.throughput(Throughput::Elements(BATCH as u64));
+    for (name, client) in &clients {
+        group.bench_with_input(BenchmarkId::from_parameter(name), client, |b, client| {
+            b.iter(|| runtime.block_on(fetch_batch(client, &url)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, sequential_fetches);
+criterion_main!(benches);
@@ -0,0 +1,30 @@
+use std::fmt::Write;
+
+/// Builds a page laid out like a mathgenealogy record with `rows` students
+pub fn students_page(rows: usize) -> String {
+    let mut page = String::from(
+        r#"<html><body><div id="mainContent"><div id="paddingWrapper">
+<h2 style="text-align: center">Synthetic  Advisor </h2>
+<div style="line-height: 30px; text-align: center; margin-bottom: 1ex">
+  <span style="margin-right: 0.5em">Ph.D. <span style="color: #006633; margin-left: 0.5em">University of Toronto</span> 2003</span>
+<img src="img/flags/Canada.gif" alt="Canada" />
+</div>
+<div style="text-align: center"><span style="color: #000066">Dissertation:</span> <span style="font-style:italic" id="thesisTitle">A Synthetic Dissertation</span></div>
+<table style="margin-left: auto; margin-right: auto">
+<tr><th>Name</th><th>School</th><th>Year</th><th>Descendants</th></tr>"#,
+    );
+
+    for i in 0..rows {
+        write!(
+            page,
+            r#"<tr><td><a href="id.php?id={}">Student, Number {i}</a></td><td>University of Guelph</td><td style="padding-left: 2px; padding-right: 2px">{}</td><td style="text-align: center"></td></tr>"#,
+            100_000 + i,
+            1900 + i % 120,
+        )
+        .unwrap();
+        page.push('\n');
+    }
+
+    page.push_str("</table></div></div></body></html>");
+    page
+}
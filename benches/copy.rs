@@ -0,0 +1,103 @@
+use std::path::Path;
+
+use combi::export::export_mathematicians;
+use combi::export::Format;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use criterion::Throughput;
+use sqlx::Executor;
+use sqlx::PgPool;
+use tokio::runtime::Runtime;
+
+/// How many mathematicians each iteration exports or imports
+const ROWS: i32 = 20_000;
+
+async fn insert_row_by_row(pool: &PgPool) {
+    let mut transaction = pool.begin().await.unwrap();
+    for id in 1..=ROWS {
+        sqlx::query("INSERT INTO mathematicians(id, name) VALUES ($1, $2);")
+            .bind(id)
+            .bind(format!("Mathematician {id}"))
+            .execute(&mut *transaction)
+            .await
+            .unwrap();
+    }
+    transaction.commit().await.unwrap();
+}
+
+async fn truncate(pool: &PgPool) {
+    pool.execute("TRUNCATE mathematicians CASCADE;")
+        .await
+        .unwrap();
+}
+
+/// Compares the row-by-row export and inserts with `COPY` on a table of mathematicians. Needs
+/// `BENCH_DATABASE_URL` pointing at a database the benchmark may migrate and empty, and is skipped
+/// without it
+fn mathematicians(c: &mut Criterion) {
+    let Ok(url) = std::env::var("BENCH_DATABASE_URL") else {
+        eprintln!("BENCH_DATABASE_URL is not set, skipping the COPY benchmarks");
+        return;
+    };
+    let runtime = Runtime::new().unwrap();
+    let pool = runtime.block_on(async {
+        let pool = PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        truncate(&pool).await;
+        insert_row_by_row(&pool).await;
+        pool
+    });
+    let dir = std::env::temp_dir().join(format!("combi-copy-bench-{}", std::process::id()));
+    let tables = ["mathematicians"];
+
+    let mut group = c.benchmark_group("export");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.sample_size(10);
+    group.bench_function("ndjson", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(export_mathematicians(
+                    &pool,
+                    Format::Ndjson,
+                    None,
+                    std::io::sink(),
+                ))
+                .unwrap()
+        })
+    });
+    group.bench_function("copy", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(combi::bulk::export(&pool, &tables, &dir))
+                .unwrap()
+        })
+    });
+    group.finish();
+
+    let mut group = c.benchmark_group("import");
+    group.throughput(Throughput::Elements(ROWS as u64));
+    group.sample_size(10);
+    group.bench_function("row by row", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                truncate(&pool).await;
+                insert_row_by_row(&pool).await;
+            })
+        })
+    });
+    group.bench_function("copy", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                truncate(&pool).await;
+                combi::bulk::import(&pool, &tables, &dir).await.unwrap();
+            })
+        })
+    });
+    group.finish();
+
+    let _ = std::fs::remove_dir_all(Path::new(&dir));
+}
+
+criterion_group!(benches, mathematicians);
+criterion_main!(benches);
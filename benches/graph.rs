@@ -0,0 +1,102 @@
+use combi::graph::descendant_count;
+use combi::graph::load_graph;
+use combi::graph::store_descendant_counts;
+use combi::graph::Graph;
+use combi::parser::Id;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use rand::rngs::StdRng;
+use rand::Rng;
+use rand::SeedableRng;
+use sqlx::Executor;
+use sqlx::PgPool;
+use tokio::runtime::Runtime;
+
+/// About as many mathematicians as the site lists
+const MATHEMATICIANS: i32 = 300_000;
+
+/// A genealogy shaped roughly like the site's: everyone after the first hundred is the student of
+/// someone recent, and one in ten has a second advisor
+fn genealogy() -> Vec<(i32, i32)> {
+    let mut rng = StdRng::seed_from_u64(1);
+    let mut relations = vec![];
+    for advisee in 101..=MATHEMATICIANS {
+        let advisor = |rng: &mut StdRng| advisee - rng.gen_range(1..=advisee.min(2_000) - 1);
+        relations.push((advisor(&mut rng), advisee));
+        if rng.gen_ratio(1, 10) {
+            relations.push((advisor(&mut rng), advisee));
+        }
+    }
+    relations.sort_unstable();
+    relations.dedup();
+    relations
+}
+
+/// The mathematician with the most descendants, where a recursive query has the most to do
+fn largest_subtree(graph: &Graph) -> Id {
+    (1..=100)
+        .map(Id)
+        .max_by_key(|&id| graph.count_descendants(id))
+        .unwrap()
+}
+
+/// The in-memory graph on a site-sized genealogy, and next to it the same questions asked of
+/// Postgres when `BENCH_DATABASE_URL` points at a database the benchmark may migrate and empty
+fn analysis(c: &mut Criterion) {
+    let relations = genealogy();
+    let graph = Graph::from_relations(&relations);
+    let root = largest_subtree(&graph);
+
+    let mut group = c.benchmark_group("in memory");
+    group.sample_size(10);
+    group.bench_function("build", |b| b.iter(|| Graph::from_relations(&relations)));
+    group.bench_function("descendants", |b| b.iter(|| graph.count_descendants(root)));
+    group.bench_function("cycles", |b| b.iter(|| graph.cycles()));
+    group.bench_function("all descendants", |b| {
+        b.iter(|| graph.all_descendant_counts())
+    });
+    group.finish();
+    let counts = graph.all_descendant_counts();
+
+    let Ok(url) = std::env::var("BENCH_DATABASE_URL") else {
+        eprintln!("BENCH_DATABASE_URL is not set, skipping the SQL benchmarks");
+        return;
+    };
+    let runtime = Runtime::new().unwrap();
+    let pool = runtime.block_on(async {
+        let pool = PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool.execute("TRUNCATE advisor_relations;").await.unwrap();
+        let (advisors, advisees): (Vec<i32>, Vec<i32>) = relations.iter().copied().unzip();
+        sqlx::query(
+            "INSERT INTO advisor_relations(advisor, advisee) SELECT * FROM UNNEST($1, $2);",
+        )
+        .bind(advisors)
+        .bind(advisees)
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool
+    });
+
+    let mut group = c.benchmark_group("sql");
+    group.sample_size(10);
+    group.bench_function("load", |b| {
+        b.iter(|| runtime.block_on(load_graph(&pool)).unwrap())
+    });
+    group.bench_function("descendants", |b| {
+        b.iter(|| runtime.block_on(descendant_count(&pool, root)).unwrap())
+    });
+    group.bench_function("store counts", |b| {
+        b.iter(|| {
+            runtime
+                .block_on(store_descendant_counts(&pool, &counts))
+                .unwrap()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, analysis);
+criterion_main!(benches);
@@ -0,0 +1,122 @@
+use std::collections::HashSet;
+
+use color_eyre::eyre::eyre;
+use combi::parser::Id;
+use combi::parser::ScrapeFields;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::Criterion;
+use criterion::Throughput;
+use futures::TryStreamExt;
+use sqlx::Executor;
+use sqlx::PgPool;
+use tokio::runtime::Runtime;
+
+/// About as many mathematicians as the site has
+const ROWS: i32 = 300_000;
+
+/// The query `known_ids` runs, the fields a full scan asks for written in
+fn query() -> String {
+    let fields = ScrapeFields::ALL.bits();
+    format!(
+        r"SELECT id FROM mathematicians
+        WHERE id NOT IN (SELECT advisor FROM scrape_progress)
+            AND (scraped_fields IS NULL OR scraped_fields & {fields} = {fields})
+        UNION ALL SELECT alias FROM canonical_id_aliases"
+    )
+}
+
+/// Each row put in the set as it arrives, the set growing as it goes
+async fn stream(pool: &PgPool) -> HashSet<Id> {
+    sqlx::query_scalar(&query())
+        .fetch(pool)
+        .map_ok(Id)
+        .try_collect()
+        .await
+        .unwrap()
+}
+
+/// `COPY` as text, a line per id parsed straight into the set
+async fn copy(pool: &PgPool) -> color_eyre::Result<HashSet<Id>> {
+    let mut connection = pool.acquire().await?;
+    let mut data = connection
+        .copy_out_raw(&format!("COPY ({}) TO STDOUT;", query()))
+        .await?;
+    let mut ids = HashSet::new();
+    // a line can be split across chunks
+    let mut id: i32 = 0;
+    while let Some(chunk) = data.try_next().await? {
+        for &byte in chunk.iter() {
+            match byte {
+                b'0'..=b'9' => {
+                    id = id
+                        .checked_mul(10)
+                        .and_then(|id| id.checked_add(i32::from(byte - b'0')))
+                        .ok_or(eyre!("A stored id is out of range"))?;
+                }
+                b'\n' => {
+                    ids.insert(Id(id));
+                    id = 0;
+                }
+                _ => return Err(eyre!("COPY sent {:?} in an id", char::from(byte))),
+            }
+        }
+    }
+    Ok(ids)
+}
+
+/// Compares the ways of loading the stored ids a scan skips on a table of 300000 mathematicians, a
+/// few of them only partly stored or redirected from an alias. Needs `BENCH_DATABASE_URL` pointing
+/// at a database the benchmark may migrate and empty, and is skipped without it
+fn known_ids(c: &mut Criterion) {
+    let Ok(url) = std::env::var("BENCH_DATABASE_URL") else {
+        eprintln!("BENCH_DATABASE_URL is not set, skipping the id loading benchmarks");
+        return;
+    };
+    let runtime = Runtime::new().unwrap();
+    let pool = runtime.block_on(async {
+        let pool = PgPool::connect(&url).await.unwrap();
+        sqlx::migrate!().run(&pool).await.unwrap();
+        pool.execute("TRUNCATE mathematicians, scrape_progress, canonical_id_aliases CASCADE;")
+            .await
+            .unwrap();
+        sqlx::query(
+            r"INSERT INTO mathematicians(id, name, scraped_fields)
+            SELECT id, 'Mathematician ' || id, CASE WHEN id % 100 = 0 THEN 1::SMALLINT END
+            FROM generate_series(1, $1) id;",
+        )
+        .bind(ROWS)
+        .execute(&pool)
+        .await
+        .unwrap();
+        pool.execute(
+            r"INSERT INTO canonical_id_aliases(alias, canonical)
+                SELECT 1000000 + id, id FROM generate_series(1, 3000) id;
+            INSERT INTO scrape_progress(advisor, students_committed, students_total)
+                SELECT id, 0, 10 FROM generate_series(1, 300000, 1000) id;",
+        )
+        .await
+        .unwrap();
+        pool
+    });
+    let fetch_all = || combi::query::known_ids(&pool, ScrapeFields::ALL);
+    // the same ids whichever way they're loaded
+    let known = runtime.block_on(fetch_all()).unwrap();
+    assert_eq!(runtime.block_on(stream(&pool)), known);
+    assert_eq!(runtime.block_on(copy(&pool)).unwrap(), known);
+
+    let mut group = c.benchmark_group("known ids");
+    group.throughput(Throughput::Elements(known.len() as u64));
+    group.sample_size(20);
+    group.bench_function("fetch all", |b| {
+        b.iter(|| runtime.block_on(fetch_all()).unwrap())
+    });
+    group.bench_function("stream", |b| b.iter(|| runtime.block_on(stream(&pool))));
+    group.bench_function("copy", |b| {
+        b.iter(|| runtime.block_on(copy(&pool)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, known_ids);
+criterion_main!(benches);
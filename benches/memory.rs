@@ -0,0 +1,104 @@
+//! Peak heap memory of scraping a record with 1000 students and of the set of stored ids a scan
+//! skips, which criterion doesn't measure. Run with `cargo bench --bench memory`
+
+use std::alloc::GlobalAlloc;
+use std::alloc::Layout;
+use std::alloc::System;
+use std::collections::HashSet;
+use std::fs::read_to_string;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+
+use combi::parser;
+use common::students_page;
+use scraper::Html;
+
+mod common;
+
+/// The system allocator, keeping count of the bytes allocated and the most there were at once
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+fn grew(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        grew(layout.size());
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        if new_size > layout.size() {
+            grew(new_size - layout.size());
+        } else {
+            ALLOCATED.fetch_sub(layout.size() - new_size, Ordering::Relaxed);
+        }
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// Runs `f`, returning what it returned along with the most memory it had allocated at once and
+/// how much of that was still allocated at the end
+fn measure<T>(f: impl FnOnce() -> T) -> (T, usize, usize) {
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let value = f();
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+    let retained = ALLOCATED.load(Ordering::Relaxed).saturating_sub(before);
+    (value, peak, retained)
+}
+
+fn kib(bytes: usize) -> String {
+    format!("{:>8.1} KiB", bytes as f64 / 1024.0)
+}
+
+fn main() {
+    const STUDENTS: usize = 1000;
+    let page = students_page(STUDENTS);
+    let student_page = read_to_string("Tai-Yih.html").unwrap();
+    // the parser's selectors are built on first use and kept, which isn't the record's memory
+    parser::scrape(&Html::parse_document(&student_page)).unwrap();
+
+    let (record, peak, retained) =
+        measure(|| parser::scrape(&Html::parse_document(&page)).unwrap());
+    assert_eq!(record.students.len(), STUDENTS);
+    println!("the advisor's page        {}", kib(page.len()));
+    println!("parsing it, at most       {}", kib(peak));
+    println!("the record it parses into {}", kib(retained));
+
+    // what fetching the students did before, every page parsed into a record kept until the end
+    let (kept, peak, _) = measure(|| {
+        (0..STUDENTS)
+            .map(|_| parser::scrape(&Html::parse_document(&student_page)).unwrap())
+            .collect::<Vec<_>>()
+    });
+    drop(kept);
+    println!("students kept, at most    {}", kib(peak));
+
+    // and what it does now, every page dropped once it's parsed
+    let ((), peak, _) = measure(|| {
+        for _ in 0..STUDENTS {
+            let _ = parser::scrape(&Html::parse_document(&student_page)).unwrap();
+        }
+    });
+    println!("students dropped, at most {}", kib(peak));
+
+    // the stored ids a scan skips, as many as the site has mathematicians
+    let (known, peak, retained) = measure(|| (1..=300_000).map(parser::Id).collect::<HashSet<_>>());
+    assert_eq!(known.len(), 300_000);
+    println!("300000 known ids          {}", kib(retained));
+    println!("loading them, at most     {}", kib(peak));
+}
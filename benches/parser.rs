@@ -0,0 +1,52 @@
+use std::fs::read_to_string;
+
+use combi::parser;
+use common::students_page;
+use criterion::criterion_group;
+use criterion::criterion_main;
+use criterion::BenchmarkId;
+use criterion::Criterion;
+use criterion::Throughput;
+use scraper::Html;
+
+mod common;
+
+const FIXTURES: [&str; 4] = ["knuth.html", "rajesh.html", "abu.html", "Tai-Yih.html"];
+
+fn scrape_fixtures(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scrape");
+
+    for fixture in FIXTURES {
+        let page = read_to_string(fixture).unwrap();
+        group.throughput(Throughput::Bytes(page.len() as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(fixture), &page, |b, page| {
+            b.iter(|| parser::scrape(&Html::parse_document(page)).unwrap())
+        });
+    }
+
+    for rows in [100, 1000] {
+        let page = students_page(rows);
+        group.throughput(Throughput::Bytes(page.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::new("synthetic", format!("{rows} students")),
+            &page,
+            |b, page| b.iter(|| parser::scrape(&Html::parse_document(page)).unwrap()),
+        );
+    }
+
+    group.finish();
+}
+
+fn scrape_students(c: &mut Criterion) {
+    let page = Html::parse_document(&students_page(100));
+
+    let mut group = c.benchmark_group("scrape_students");
+    group.throughput(Throughput::Elements(100));
+    group.bench_function("100 rows", |b| {
+        b.iter(|| parser::scrape_students(parser::main_content(&page)).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, scrape_fixtures, scrape_students);
+criterion_main!(benches);
@@ -0,0 +1,39 @@
+-- The schema the scraper has been running against, kept idempotent so existing databases can
+-- adopt the migrations as is
+CREATE TABLE IF NOT EXISTS mathematicians (
+    id   INTEGER PRIMARY KEY,
+    name TEXT NOT NULL
+);
+
+CREATE TABLE IF NOT EXISTS countries (
+    name TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS schools (
+    name TEXT PRIMARY KEY
+);
+
+CREATE TABLE IF NOT EXISTS school_locations (
+    school  TEXT NOT NULL,
+    country TEXT NOT NULL,
+    PRIMARY KEY (school, country)
+);
+
+CREATE TABLE IF NOT EXISTS dissertations (
+    title  TEXT NOT NULL,
+    author INTEGER NOT NULL,
+    PRIMARY KEY (title, author)
+);
+
+CREATE TABLE IF NOT EXISTS advisor_relations (
+    advisor INTEGER NOT NULL,
+    advisee INTEGER NOT NULL,
+    PRIMARY KEY (advisor, advisee)
+);
+
+CREATE TABLE IF NOT EXISTS graduation_records (
+    mathematician INTEGER NOT NULL,
+    school        TEXT NOT NULL,
+    year          INTEGER NOT NULL,
+    PRIMARY KEY (mathematician, school, year)
+);
@@ -0,0 +1,5 @@
+-- Ids that the site redirected to a different, canonical id
+CREATE TABLE canonical_id_aliases (
+    alias     INTEGER PRIMARY KEY,
+    canonical INTEGER NOT NULL
+);
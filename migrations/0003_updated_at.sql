@@ -0,0 +1,18 @@
+-- When each row was last written, so exports can pick up only what changed since a previous run.
+-- Rows that predate this migration are stamped with the time it ran.
+CREATE FUNCTION set_updated_at() RETURNS TRIGGER AS $$
+BEGIN
+    NEW.updated_at = now();
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+ALTER TABLE mathematicians ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+CREATE INDEX mathematicians_updated_at ON mathematicians (updated_at);
+CREATE TRIGGER mathematicians_updated_at BEFORE UPDATE ON mathematicians
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
+
+ALTER TABLE advisor_relations ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT now();
+CREATE INDEX advisor_relations_updated_at ON advisor_relations (updated_at);
+CREATE TRIGGER advisor_relations_updated_at BEFORE UPDATE ON advisor_relations
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
@@ -0,0 +1,2 @@
+-- Years are parsed as a validated i16 `Year`, store them with the same width
+ALTER TABLE graduation_records ALTER COLUMN year TYPE SMALLINT;
@@ -0,0 +1,2 @@
+-- The page a mathematician's record was scraped from, NULL for those only seen as someone's student
+ALTER TABLE mathematicians ADD COLUMN source_url TEXT;
@@ -0,0 +1,4 @@
+-- The position of the student in the advisor's students table, in whatever order the site
+-- displayed them (alphabetical by default, chronological with fChrono=1). NULL for relations
+-- stored before this was recorded
+ALTER TABLE advisor_relations ADD COLUMN rank INTEGER;
@@ -0,0 +1,12 @@
+-- The year a dissertation was written when the page gives one apart from the degree year, and who
+-- advised it
+ALTER TABLE dissertations ADD COLUMN year SMALLINT;
+
+CREATE TABLE dissertation_advisors (
+    title   TEXT NOT NULL,
+    author  INTEGER NOT NULL,
+    advisor INTEGER NOT NULL,
+    -- 1 for "Advisor 1", 2 for "Advisor 2" and so on
+    rank    INTEGER NOT NULL,
+    PRIMARY KEY (title, author, advisor)
+);
@@ -0,0 +1,2 @@
+-- SHA-256 of the page a mathematician was last scraped from, refreshes skip pages that hash the same
+ALTER TABLE mathematicians ADD COLUMN page_hash BYTEA;
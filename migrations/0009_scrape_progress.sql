@@ -0,0 +1,8 @@
+-- How far a chunked insert of a long students list got, so an interrupted one resumes after the
+-- last committed chunk. A row only exists while its insert is unfinished
+CREATE TABLE scrape_progress (
+    advisor            INTEGER PRIMARY KEY REFERENCES mathematicians(id),
+    students_committed INTEGER NOT NULL,
+    students_total     INTEGER NOT NULL,
+    updated_at         TIMESTAMPTZ NOT NULL DEFAULT now()
+);
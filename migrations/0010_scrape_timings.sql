@@ -0,0 +1,10 @@
+-- How long the last scrape of each id took per phase, only written with --record-timings, to find
+-- the pages that are slow to fetch, parse or store
+CREATE TABLE scrape_timings (
+    id         INTEGER PRIMARY KEY,
+    fetch_ms   INTEGER NOT NULL,
+    parse_ms   INTEGER NOT NULL,
+    insert_ms  INTEGER NOT NULL,
+    scraped_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX scrape_timings_total ON scrape_timings ((fetch_ms + parse_ms + insert_ms) DESC);
@@ -0,0 +1,3 @@
+-- A dissertation is stored once per title and author. The primary key already held them to that
+-- under its generated name, name it after what the insert's `ON CONFLICT (title, author)` targets
+ALTER TABLE dissertations RENAME CONSTRAINT dissertations_pkey TO dissertations_title_author_key;
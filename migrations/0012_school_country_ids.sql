@@ -0,0 +1,37 @@
+-- Schools and countries are referenced by a surrogate id rather than their name, so a name can be
+-- corrected or two spellings merged by changing one row. Names stay unique
+ALTER TABLE schools DROP CONSTRAINT schools_pkey;
+ALTER TABLE schools ADD COLUMN id SERIAL PRIMARY KEY;
+ALTER TABLE schools ADD CONSTRAINT schools_name_key UNIQUE (name);
+
+ALTER TABLE countries DROP CONSTRAINT countries_pkey;
+ALTER TABLE countries ADD COLUMN id SERIAL PRIMARY KEY;
+ALTER TABLE countries ADD CONSTRAINT countries_name_key UNIQUE (name);
+
+-- every name referenced needs a row to point at
+INSERT INTO schools(name)
+SELECT school FROM graduation_records UNION SELECT school FROM school_locations
+ON CONFLICT (name) DO NOTHING;
+INSERT INTO countries(name) SELECT country FROM school_locations
+ON CONFLICT (name) DO NOTHING;
+
+-- dropping the name columns drops the primary keys that include them
+ALTER TABLE graduation_records ADD COLUMN school_id INTEGER REFERENCES schools(id);
+UPDATE graduation_records g SET school_id = s.id FROM schools s WHERE s.name = g.school;
+ALTER TABLE graduation_records DROP COLUMN school;
+ALTER TABLE graduation_records RENAME COLUMN school_id TO school;
+ALTER TABLE graduation_records ALTER COLUMN school SET NOT NULL;
+ALTER TABLE graduation_records ADD PRIMARY KEY (mathematician, school, year);
+
+ALTER TABLE school_locations ADD COLUMN school_id INTEGER REFERENCES schools(id);
+ALTER TABLE school_locations ADD COLUMN country_id INTEGER REFERENCES countries(id);
+UPDATE school_locations l SET school_id = s.id, country_id = c.id
+FROM schools s, countries c
+WHERE s.name = l.school AND c.name = l.country;
+ALTER TABLE school_locations DROP COLUMN school;
+ALTER TABLE school_locations DROP COLUMN country;
+ALTER TABLE school_locations RENAME COLUMN school_id TO school;
+ALTER TABLE school_locations RENAME COLUMN country_id TO country;
+ALTER TABLE school_locations ALTER COLUMN school SET NOT NULL;
+ALTER TABLE school_locations ALTER COLUMN country SET NOT NULL;
+ALTER TABLE school_locations ADD PRIMARY KEY (school, country);
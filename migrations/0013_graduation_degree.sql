@@ -0,0 +1,2 @@
+-- The degree as the page words it, e.g. "Ph.D." or "M.A.", NULL when the page gives none
+ALTER TABLE graduation_records ADD COLUMN degree TEXT;
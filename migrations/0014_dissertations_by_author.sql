@@ -0,0 +1,3 @@
+-- A scrape replaces an author's dissertations with the ones their page lists, looking them up by
+-- author alone, which the key leading with the title can't serve
+CREATE INDEX dissertations_author ON dissertations (author);
@@ -0,0 +1,5 @@
+-- A mathematician can't advise themselves, such a row is a parsing or data error and makes every
+-- recursive query over the relations loop on it
+DELETE FROM advisor_relations WHERE advisor = advisee;
+
+ALTER TABLE advisor_relations ADD CONSTRAINT advisor_relations_not_self CHECK (advisor <> advisee);
@@ -0,0 +1,9 @@
+-- The students whose page was fetched for a record that isn't stored yet, so a scrape interrupted
+-- partway through a long students list doesn't fetch them again. The rows of an advisor are
+-- dropped in the transaction that stores the record
+CREATE TABLE fetched_students (
+    advisor    INTEGER NOT NULL,
+    student    INTEGER NOT NULL,
+    fetched_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (advisor, student)
+);
@@ -0,0 +1,9 @@
+-- The ids whose scrape failed, written as each failure happens so a run that crashes doesn't lose
+-- them, and read back by --retry-failed. A row goes away once its id is scraped successfully
+CREATE TABLE scrape_failures (
+    id           INTEGER PRIMARY KEY,
+    reason       TEXT NOT NULL,
+    error        TEXT NOT NULL,
+    attempts     INTEGER NOT NULL DEFAULT 1,
+    last_attempt TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,11 @@
+-- What a scrape of an already stored mathematician changed about them, a row per field with the
+-- values before and after, only written with --record-field-updates. `id` isn't a foreign key so
+-- the history outlives a mathematician that's deleted and scraped again
+CREATE TABLE field_updates (
+    id    INTEGER NOT NULL,
+    field TEXT NOT NULL,
+    old   TEXT,
+    new   TEXT,
+    at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+CREATE INDEX field_updates_id ON field_updates (id, at);
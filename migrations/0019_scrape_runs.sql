@@ -0,0 +1,15 @@
+-- Scrape runs started with --run-label, so what's stored can be attributed to a crawl and crawls
+-- compared. The counts are the run's summary, they and finished_at stay NULL for a run that never
+-- finished
+CREATE TABLE scrape_runs (
+    id          SERIAL PRIMARY KEY,
+    label       TEXT NOT NULL,
+    started_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    finished_at TIMESTAMPTZ,
+    scraped     BIGINT,
+    unchanged   BIGINT,
+    filtered    BIGINT,
+    failed      BIGINT,
+    missing     BIGINT,
+    downloaded  BIGINT
+);
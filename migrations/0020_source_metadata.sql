@@ -0,0 +1,4 @@
+-- Who the page credits with the record's data and when it says the site last updated it, NULL when
+-- the page doesn't say
+ALTER TABLE mathematicians ADD COLUMN contributor TEXT;
+ALTER TABLE mathematicians ADD COLUMN source_updated DATE;
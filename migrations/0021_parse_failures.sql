@@ -0,0 +1,9 @@
+-- The pages that didn't parse into a record, only written with --store-parse-failures. The snippet
+-- is the page's main content cut to 16 KiB, enough to make a fixture of without fetching the page
+-- again. Unlike scrape_failures a row stays after the id is scraped, until it fails again
+CREATE TABLE parse_failures (
+    id        INTEGER PRIMARY KEY,
+    error     TEXT NOT NULL,
+    snippet   TEXT NOT NULL,
+    failed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,9 @@
+-- Where schools are, only written by geocode-schools. A row without coordinates is a school the
+-- geocoder didn't know, kept so that it isn't asked again unless --retry-unresolved
+CREATE TABLE school_coordinates (
+    school      INTEGER PRIMARY KEY REFERENCES schools(id) ON DELETE CASCADE,
+    latitude    DOUBLE PRECISION,
+    longitude   DOUBLE PRECISION,
+    geocoded_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+    CHECK ((latitude IS NULL) = (longitude IS NULL))
+);
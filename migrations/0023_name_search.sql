@@ -0,0 +1,3 @@
+-- The words of names for search-names, which matches them in any order. The simple configuration
+-- only lowercases, names aren't words a dictionary should stem
+CREATE INDEX mathematicians_name_words ON mathematicians USING GIN (to_tsvector('simple', name));
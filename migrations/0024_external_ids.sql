@@ -0,0 +1,13 @@
+-- What other knowledge bases call a mathematician, e.g. a Wikidata QID, loaded with
+-- import-external-ids. A mathematician has one id per system and an id names one mathematician.
+-- There's no foreign key, a mapping can name mathematicians that aren't scraped yet
+CREATE TABLE external_ids (
+    mathematician INTEGER NOT NULL,
+    system        TEXT NOT NULL,
+    external_id   TEXT NOT NULL,
+    updated_at    TIMESTAMPTZ NOT NULL DEFAULT now(),
+    PRIMARY KEY (mathematician, system),
+    UNIQUE (system, external_id)
+);
+CREATE TRIGGER external_ids_updated_at BEFORE UPDATE ON external_ids
+    FOR EACH ROW EXECUTE FUNCTION set_updated_at();
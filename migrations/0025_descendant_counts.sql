@@ -0,0 +1,8 @@
+-- How many descendants everyone in advisor_relations has, counted over the whole graph by
+-- count-descendants and replaced as a whole each time. Ids only known from the relations are
+-- counted too, so there's no foreign key
+CREATE TABLE descendant_counts (
+    mathematician INTEGER PRIMARY KEY,
+    descendants   INTEGER NOT NULL,
+    counted_at    TIMESTAMPTZ NOT NULL DEFAULT now()
+);
@@ -0,0 +1,3 @@
+-- The last student with an id that a chunked insert committed, which the next attempt resumes
+-- after. A count of committed students is off as soon as the site's list changes in between
+ALTER TABLE scrape_progress ADD COLUMN last_student INTEGER;
@@ -0,0 +1,30 @@
+-- Countries used to be stored as the alt text of their flag, e.g. "UnitedStates", and are spelled
+-- out like the parser does now. An old spelling of a country that a later scrape stored spelled
+-- out is merged into it, along with its school locations
+CREATE FUNCTION spelled_out_country(alt TEXT) RETURNS TEXT AS $$
+    SELECT CASE alt
+        WHEN 'AntiguaandBarbuda' THEN 'Antigua and Barbuda'
+        WHEN 'BosniaandHerzegovina' THEN 'Bosnia and Herzegovina'
+        WHEN 'TrinidadandTobago' THEN 'Trinidad and Tobago'
+        WHEN 'SaoTomeandPrincipe' THEN 'Sao Tome and Principe'
+        WHEN 'IsleofMan' THEN 'Isle of Man'
+        ELSE regexp_replace(alt, '([[:lower:]])([[:upper:]])', '\1 \2', 'g')
+    END
+$$ LANGUAGE SQL IMMUTABLE;
+
+UPDATE school_locations l SET country = spelled.id
+FROM countries old, countries spelled
+WHERE l.country = old.id AND spelled.name = spelled_out_country(old.name) AND spelled.id <> old.id
+    AND NOT EXISTS (
+        SELECT 1 FROM school_locations d WHERE d.school = l.school AND d.country = spelled.id
+    );
+DELETE FROM school_locations l
+USING countries old, countries spelled
+WHERE l.country = old.id AND spelled.name = spelled_out_country(old.name) AND spelled.id <> old.id;
+DELETE FROM countries old
+USING countries spelled
+WHERE spelled.name = spelled_out_country(old.name) AND spelled.id <> old.id;
+
+UPDATE countries SET name = spelled_out_country(name) WHERE name <> spelled_out_country(name);
+
+DROP FUNCTION spelled_out_country(TEXT);
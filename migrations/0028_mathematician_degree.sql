@@ -0,0 +1,12 @@
+-- The title of the record's own degree, kept even when the page gives no school or year for it,
+-- which a graduation record can't be stored without
+ALTER TABLE mathematicians ADD COLUMN degree TEXT;
+
+UPDATE mathematicians m SET degree = g.degree
+FROM (
+    SELECT DISTINCT ON (g.mathematician) g.mathematician, g.degree
+    FROM graduation_records g
+    JOIN schools s ON s.id = g.school
+    ORDER BY g.mathematician, g.year, s.name
+) g
+WHERE g.mathematician = m.id;
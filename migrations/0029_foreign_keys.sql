@@ -0,0 +1,16 @@
+-- Graduation records and dissertations go along with their mathematician, and the advisors of a
+-- dissertation with it. The keys are checked at the end of a transaction, so a scrape can store a
+-- record's rows in any order. Rows that already dangle aren't checked, `combi prune` deletes them
+-- and then validates the keys. Advisor relations get none, either end may be someone who isn't
+-- scraped yet
+ALTER TABLE graduation_records ADD CONSTRAINT graduation_records_mathematician_fkey
+    FOREIGN KEY (mathematician) REFERENCES mathematicians(id)
+    ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED NOT VALID;
+
+ALTER TABLE dissertations ADD CONSTRAINT dissertations_author_fkey
+    FOREIGN KEY (author) REFERENCES mathematicians(id)
+    ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED NOT VALID;
+
+ALTER TABLE dissertation_advisors ADD CONSTRAINT dissertation_advisors_dissertation_fkey
+    FOREIGN KEY (title, author) REFERENCES dissertations(title, author)
+    ON DELETE CASCADE DEFERRABLE INITIALLY DEFERRED NOT VALID;
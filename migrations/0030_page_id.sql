@@ -0,0 +1,3 @@
+-- The id the page gives as its own in its note asking to quote the MGP ID, which differs from `id`
+-- when the site served another record's page for it, NULL when the page gives none
+ALTER TABLE mathematicians ADD COLUMN page_id INTEGER;
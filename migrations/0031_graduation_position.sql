@@ -0,0 +1,22 @@
+-- The place of a graduation record's degree line on the page, 1 for the record's own degree and
+-- counting down the further degrees. The page order wasn't kept before, so the records already
+-- stored are placed by year and then school name, the order they were read in until now, with
+-- the records of one degree title sharing the place of its earliest
+ALTER TABLE graduation_records ADD COLUMN position SMALLINT NOT NULL DEFAULT 1;
+
+UPDATE graduation_records g SET position = placed.position
+FROM (
+    SELECT mathematician, school, year,
+        dense_rank() OVER (
+            PARTITION BY mathematician ORDER BY first_year, first_school, degree
+        ) AS position
+    FROM (
+        SELECT g.mathematician, g.school, g.year, g.degree,
+            first_value(g.year) OVER degree_records AS first_year,
+            first_value(s.name) OVER degree_records AS first_school
+        FROM graduation_records g
+        JOIN schools s ON s.id = g.school
+        WINDOW degree_records AS (PARTITION BY g.mathematician, g.degree ORDER BY g.year, s.name)
+    ) degrees
+) placed
+WHERE (g.mathematician, g.school, g.year) = (placed.mathematician, placed.school, placed.year);
@@ -0,0 +1,4 @@
+-- The flags of the fields scrapes stored for the mathematician, as `--fields` selects them, so a
+-- scan for fields a partial crawl left out visits them again. NULL for rows stored without a record
+-- of their fields, which are taken as complete
+ALTER TABLE mathematicians ADD COLUMN scraped_fields SMALLINT;
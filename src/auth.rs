@@ -0,0 +1,128 @@
+use std::fmt;
+
+use color_eyre::eyre::eyre;
+use reqwest::header::COOKIE;
+use reqwest::RequestBuilder;
+
+/// What a request to the site is signed in with, for if the site ever requires it. Read from the
+/// environment so secrets stay off the command line
+#[derive(Clone, PartialEq, Eq)]
+pub enum Credentials {
+    /// HTTP basic auth from `SITE_USERNAME` and `SITE_PASSWORD`
+    Basic {
+        username: String,
+        password: Option<String>,
+    },
+    /// An `Authorization: Bearer` token from `SITE_TOKEN`
+    Bearer(String),
+    /// A session cookie from `SITE_COOKIE`, sent as is, e.g. `PHPSESSID=abc123`
+    Cookie(String),
+}
+
+impl Credentials {
+    /// The credentials in the environment, `None` when none are set and an error when more than
+    /// one kind is, since which to send would be a guess
+    pub fn from_env() -> color_eyre::Result<Option<Self>> {
+        Self::from_vars(|name| std::env::var(name).ok())
+    }
+
+    fn from_vars(var: impl Fn(&str) -> Option<String>) -> color_eyre::Result<Option<Self>> {
+        let var = |name| var(name).filter(|value| !value.is_empty());
+        let mut found = vec![];
+        if let Some(username) = var("SITE_USERNAME") {
+            found.push(Credentials::Basic {
+                username,
+                password: var("SITE_PASSWORD"),
+            });
+        } else if var("SITE_PASSWORD").is_some() {
+            return Err(eyre!("SITE_PASSWORD is set without SITE_USERNAME"));
+        }
+        if let Some(token) = var("SITE_TOKEN") {
+            found.push(Credentials::Bearer(token));
+        }
+        if let Some(cookie) = var("SITE_COOKIE") {
+            found.push(Credentials::Cookie(cookie));
+        }
+
+        match found.len() {
+            0 | 1 => Ok(found.pop()),
+            _ => Err(eyre!(
+                "only one of SITE_USERNAME, SITE_TOKEN and SITE_COOKIE can be set"
+            )),
+        }
+    }
+
+    /// Signs `request` in
+    pub fn apply(&self, request: RequestBuilder) -> RequestBuilder {
+        match self {
+            Credentials::Basic { username, password } => {
+                request.basic_auth(username, password.as_ref())
+            }
+            Credentials::Bearer(token) => request.bearer_auth(token),
+            Credentials::Cookie(cookie) => request.header(COOKIE, cookie),
+        }
+    }
+
+    /// What kind of credentials these are, for logs
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Credentials::Basic { .. } => "basic auth",
+            Credentials::Bearer(_) => "bearer token",
+            Credentials::Cookie(_) => "session cookie",
+        }
+    }
+}
+
+/// Only the kind and the username, never a secret
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Credentials::Basic { username, .. } => write!(f, "basic auth as {username}"),
+            other => f.write_str(other.kind()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn from(vars: &[(&str, &str)]) -> color_eyre::Result<Option<Credentials>> {
+        Credentials::from_vars(|name| {
+            vars.iter()
+                .find(|(var, _)| *var == name)
+                .map(|(_, value)| value.to_string())
+        })
+    }
+
+    #[test]
+    fn credentials_come_from_one_kind_of_variable() {
+        assert_eq!(from(&[]).unwrap(), None);
+        assert_eq!(from(&[("SITE_TOKEN", "")]).unwrap(), None);
+        assert_eq!(
+            from(&[("SITE_USERNAME", "ada"), ("SITE_PASSWORD", "secret")]).unwrap(),
+            Some(Credentials::Basic {
+                username: "ada".to_string(),
+                password: Some("secret".to_string()),
+            })
+        );
+        assert_eq!(
+            from(&[("SITE_COOKIE", "PHPSESSID=abc123")]).unwrap(),
+            Some(Credentials::Cookie("PHPSESSID=abc123".to_string()))
+        );
+
+        assert!(from(&[("SITE_PASSWORD", "secret")]).is_err());
+        assert!(from(&[("SITE_USERNAME", "ada"), ("SITE_TOKEN", "t")]).is_err());
+    }
+
+    #[test]
+    fn debug_output_never_shows_a_secret() {
+        let basic = Credentials::Basic {
+            username: "ada".to_string(),
+            password: Some("secret".to_string()),
+        };
+        assert_eq!(format!("{basic:?}"), "basic auth as ada");
+        let token = format!("{:?}", Credentials::Bearer("secret".to_string()));
+        assert!(!token.contains("secret"));
+    }
+}
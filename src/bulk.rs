@@ -0,0 +1,176 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use color_eyre::eyre::eyre;
+use futures::TryStreamExt;
+use sqlx::PgPool;
+use tokio::io::AsyncWriteExt;
+use tracing::info;
+
+/// The order tables are restored in, every table after the ones its foreign keys point at
+pub const RESTORE_ORDER: &[&str] = &[
+    "mathematicians",
+    "schools",
+    "countries",
+    "advisor_relations",
+    "dissertations",
+    "dissertation_advisors",
+    "graduation_records",
+    "school_locations",
+    "canonical_id_aliases",
+    "scrape_progress",
+    "scrape_timings",
+    "fetched_students",
+    "scrape_failures",
+    "field_updates",
+    "scrape_runs",
+    "parse_failures",
+    "school_coordinates",
+    "external_ids",
+    "descendant_counts",
+];
+
+/// Tables whose id comes from a sequence, which has to continue after the restored ids
+const SERIAL_TABLES: [&str; 3] = ["schools", "countries", "scrape_runs"];
+
+fn copy_file(dir: &Path, table: &str) -> PathBuf {
+    dir.join(format!("{table}.copy"))
+}
+
+/// Writes each of the `tables` to `<table>.copy` in `dir` with `COPY ... TO STDOUT` in Postgres'
+/// binary format, which streams the table without a query per row. The files are only meant for
+/// [`import`] into a database of the same schema
+pub async fn export(pool: &PgPool, tables: &[&str], dir: &Path) -> color_eyre::Result<()> {
+    tokio::fs::create_dir_all(dir).await?;
+
+    for &table in tables {
+        let mut file =
+            tokio::io::BufWriter::new(tokio::fs::File::create(copy_file(dir, table)).await?);
+        let mut connection = pool.acquire().await?;
+        let mut data = connection
+            .copy_out_raw(&format!("COPY {table} TO STDOUT (FORMAT binary);"))
+            .await?;
+
+        let mut bytes = 0;
+        while let Some(chunk) = data.try_next().await? {
+            file.write_all(&chunk).await?;
+            bytes += chunk.len();
+        }
+        file.flush().await?;
+        info!("Exported {table}, {bytes} bytes");
+    }
+
+    Ok(())
+}
+
+/// Restores the `tables` from the files [`export`] wrote into `dir`, with `COPY ... FROM STDIN`.
+/// Everything happens in one transaction and only into empty tables, a partial or duplicate
+/// restore leaves the database as it was
+pub async fn import(pool: &PgPool, tables: &[&str], dir: &Path) -> color_eyre::Result<()> {
+    for &table in tables {
+        let path = copy_file(dir, table);
+        if !path.exists() {
+            return Err(eyre!("{} doesn't exist", path.display()));
+        }
+    }
+
+    let mut transaction = pool.begin().await?;
+    for &table in RESTORE_ORDER.iter().filter(|t| tables.contains(t)) {
+        let filled: bool = sqlx::query_scalar(&format!("SELECT EXISTS(SELECT 1 FROM {table});"))
+            .fetch_one(&mut *transaction)
+            .await?;
+        if filled {
+            return Err(eyre!(
+                "{table} already has rows, import only fills empty tables"
+            ));
+        }
+
+        let file = tokio::fs::File::open(copy_file(dir, table)).await?;
+        let mut copy = transaction
+            .copy_in_raw(&format!("COPY {table} FROM STDIN (FORMAT binary);"))
+            .await?;
+        if let Err(e) = copy.read_from(file).await {
+            copy.abort(e.to_string()).await?;
+            return Err(e.into());
+        }
+        let rows = copy.finish().await?;
+        info!("Imported {rows} rows of {table}");
+
+        if SERIAL_TABLES.contains(&table) {
+            sqlx::query(&format!(
+                r"SELECT setval(pg_get_serial_sequence('{table}', 'id'), MAX(id)) FROM {table}
+                HAVING COUNT(*) > 0;"
+            ))
+            .execute(&mut *transaction)
+            .await?;
+        }
+    }
+    transaction.commit().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::info::TABLES;
+    use sqlx::Executor;
+
+    #[test]
+    fn every_table_is_restored() {
+        let mut restored = RESTORE_ORDER.to_vec();
+        restored.sort_unstable();
+        let mut tables = TABLES.to_vec();
+        tables.sort_unstable();
+        assert_eq!(restored, tables);
+    }
+
+    #[sqlx::test]
+    async fn tables_round_trip(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name, page_hash) VALUES
+                (1, 'Advisor', '\x0102'), (2, 'Student', NULL);
+            INSERT INTO advisor_relations(advisor, advisee, rank) VALUES (1, 2, 1);
+            INSERT INTO schools(name) VALUES ('University of Toronto');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES (1, 1, 2003);",
+        )
+        .await
+        .unwrap();
+        let dir = std::env::temp_dir().join(format!("combi-copy-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        export(&pool, TABLES, &dir).await.unwrap();
+        let error = import(&pool, TABLES, &dir).await.unwrap_err();
+        assert!(error.to_string().contains("already has rows"), "{error}");
+
+        pool.execute(&*format!(
+            "TRUNCATE {} RESTART IDENTITY CASCADE;",
+            TABLES.join(", ")
+        ))
+        .await
+        .unwrap();
+        import(&pool, TABLES, &dir).await.unwrap();
+
+        let names: Vec<String> = sqlx::query_scalar("SELECT name FROM mathematicians ORDER BY id;")
+            .fetch_all(&pool)
+            .await
+            .unwrap();
+        assert_eq!(names, ["Advisor", "Student"]);
+        let hash: Vec<u8> =
+            sqlx::query_scalar("SELECT page_hash FROM mathematicians WHERE id = 1;")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(hash, [1, 2]);
+        // new schools continue after the restored ids
+        let id: i32 =
+            sqlx::query_scalar("INSERT INTO schools(name) VALUES ('McGill') RETURNING id;")
+                .fetch_one(&pool)
+                .await
+                .unwrap();
+        assert_eq!(id, 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
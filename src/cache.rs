@@ -0,0 +1,219 @@
+use std::path::Path;
+use std::path::PathBuf;
+
+use reqwest::Url;
+use sha2::Digest;
+use sha2::Sha256;
+
+/// How the scraper uses the page cache
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum CacheMode {
+    /// Serve pages from the cache when they're in it, fetch and keep the rest
+    #[default]
+    Use,
+    /// Always fetch, the fetched pages still replace the cached ones
+    Refresh,
+    /// Never fetch, a page that isn't cached is an error
+    Only,
+}
+
+/// What the site said identifies the version of a page it served, sent back when the page is
+/// requested again so that the site can answer it's not modified instead of sending it
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none()
+    }
+}
+
+/// A page read from the cache
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CachedPage {
+    pub body: String,
+    /// The url the page was finally served from
+    pub final_url: Url,
+    pub validators: Validators,
+}
+
+const ETAG: &str = "ETag: ";
+const LAST_MODIFIED: &str = "Last-Modified: ";
+
+/// Pages fetched from the site, kept on disk as a file per requested url so a later run can read
+/// them again without the network. A file holds the url the page was finally served from on its
+/// first line, then a line for each validator the site sent as `ETag: ...` or `Last-Modified: ...`,
+/// and the body after them. Files kept before validators were have none, their body starts on the
+/// second line, which a page never starts with either
+#[derive(Debug, Clone)]
+pub struct PageCache {
+    dir: PathBuf,
+}
+
+impl PageCache {
+    /// A cache in `dir`, which is created on the first page kept
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Named after the hash of the url, which can't be a file name as it is
+    fn path(&self, url: &str) -> PathBuf {
+        let hash = Sha256::digest(url.as_bytes());
+        let name: String = hash.iter().map(|byte| format!("{byte:02x}")).collect();
+        self.dir.join(format!("{name}.html"))
+    }
+
+    /// The cached page of `url`, `None` when it isn't cached
+    pub async fn get(&self, url: &str) -> color_eyre::Result<Option<CachedPage>> {
+        let contents = match tokio::fs::read_to_string(self.path(url)).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let (final_url, mut rest) = contents.split_once('\n').unwrap_or((&contents, ""));
+
+        let mut validators = Validators::default();
+        loop {
+            let (line, after) = rest.split_once('\n').unwrap_or((rest, ""));
+            if let Some(etag) = line.strip_prefix(ETAG) {
+                validators.etag = Some(etag.to_string());
+            } else if let Some(last_modified) = line.strip_prefix(LAST_MODIFIED) {
+                validators.last_modified = Some(last_modified.to_string());
+            } else {
+                break;
+            }
+            rest = after;
+        }
+
+        Ok(Some(CachedPage {
+            body: rest.to_string(),
+            final_url: Url::parse(final_url)?,
+            validators,
+        }))
+    }
+
+    /// Keeps the body of `url`, served from `final_url` with `validators`, replacing what was
+    /// cached for it. The file is written next to its place and renamed, so a reader never sees
+    /// half a page
+    pub async fn put(
+        &self,
+        url: &str,
+        body: &str,
+        final_url: &Url,
+        validators: &Validators,
+    ) -> color_eyre::Result<()> {
+        let mut contents = format!("{final_url}\n");
+        if let Some(etag) = &validators.etag {
+            contents.push_str(&format!("{ETAG}{etag}\n"));
+        }
+        if let Some(last_modified) = &validators.last_modified {
+            contents.push_str(&format!("{LAST_MODIFIED}{last_modified}\n"));
+        }
+        contents.push_str(body);
+
+        tokio::fs::create_dir_all(&self.dir).await?;
+        let path = self.path(url);
+        let partial = path.with_extension(format!("{}.partial", std::process::id()));
+        tokio::fs::write(&partial, contents).await?;
+        tokio::fs::rename(&partial, &path).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn temp_cache(name: &str) -> PageCache {
+        let dir = std::env::temp_dir().join(format!("combi-{name}-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        PageCache::new(dir)
+    }
+
+    #[tokio::test]
+    async fn pages_round_trip() {
+        let cache = temp_cache("cache-round-trip");
+        let url = "https://www.mathgenealogy.org/id.php?id=92443";
+        assert_eq!(cache.get(url).await.unwrap(), None);
+
+        let served = Url::parse("https://www.mathgenealogy.org/id.php?id=92443&fChrono=1").unwrap();
+        cache
+            .put(
+                url,
+                "<html>\nKnuth\n</html>",
+                &served,
+                &Validators::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            cache.get(url).await.unwrap(),
+            Some(CachedPage {
+                body: "<html>\nKnuth\n</html>".to_string(),
+                final_url: served.clone(),
+                validators: Validators::default(),
+            })
+        );
+        assert_eq!(
+            cache
+                .get("https://www.mathgenealogy.org/id.php?id=1")
+                .await
+                .unwrap(),
+            None
+        );
+
+        // a refetched page replaces the cached one
+        cache
+            .put(
+                url,
+                "<html>Knuth, D.</html>",
+                &served,
+                &Validators::default(),
+            )
+            .await
+            .unwrap();
+        let cached = cache.get(url).await.unwrap().unwrap();
+        assert_eq!(cached.body, "<html>Knuth, D.</html>");
+
+        let _ = std::fs::remove_dir_all(cache.dir());
+    }
+
+    #[tokio::test]
+    async fn validators_are_kept_with_the_page() {
+        let cache = temp_cache("cache-validators");
+        let url = "https://www.mathgenealogy.org/id.php?id=92443";
+        let served = Url::parse(url).unwrap();
+        let validators = Validators {
+            etag: Some("\"5f2a-1c\"".to_string()),
+            last_modified: Some("Thu, 14 Mar 2019 09:00:00 GMT".to_string()),
+        };
+        cache
+            .put(url, "<html>Rajesh</html>", &served, &validators)
+            .await
+            .unwrap();
+        let cached = cache.get(url).await.unwrap().unwrap();
+        assert_eq!(cached.body, "<html>Rajesh</html>");
+        assert_eq!(cached.validators, validators);
+
+        // a page kept before validators were has its body right after the url
+        let only_etag = Validators {
+            etag: Some("W/\"1\"".to_string()),
+            last_modified: None,
+        };
+        cache.put(url, "<html>", &served, &only_etag).await.unwrap();
+        assert_eq!(cache.get(url).await.unwrap().unwrap().validators, only_etag);
+        std::fs::write(cache.path(url), format!("{served}\n<html>old</html>")).unwrap();
+        let cached = cache.get(url).await.unwrap().unwrap();
+        assert_eq!(cached.body, "<html>old</html>");
+        assert!(cached.validators.is_empty());
+
+        let _ = std::fs::remove_dir_all(cache.dir());
+    }
+}
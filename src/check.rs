@@ -0,0 +1,264 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use combi::filter::RecordFilter;
+use combi::scrape::redact;
+use combi::scrape::ScraperConfig;
+use combi::source::MathGenealogy;
+use sqlx::PgPool;
+
+/// What kind of check failed, each exits with its own code so scripts can tell them apart
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Failure {
+    /// The configuration itself is unusable, e.g. a missing variable or malformed proxy url
+    Config = 3,
+    /// Postgres can't be connected to
+    Database = 4,
+    /// The schema is missing migrations
+    Migrations = 5,
+    /// The site can't be reached, directly or through the proxy
+    Network = 6,
+    /// The page cache directory can't be written to
+    Cache = 7,
+}
+
+impl Failure {
+    pub fn exit_code(self) -> i32 {
+        self as i32
+    }
+}
+
+fn report<T, E: Display>(
+    check: &str,
+    result: Result<T, E>,
+    failure: Failure,
+) -> Result<T, Failure> {
+    match result {
+        Ok(value) => {
+            println!("ok      {check}");
+            Ok(value)
+        }
+        Err(e) => {
+            println!("FAILED  {check}: {e}");
+            Err(failure)
+        }
+    }
+}
+
+fn print_config(config: &ScraperConfig, postgres_url: Option<&str>) {
+    println!(
+        "POSTGRES_URL:     {}",
+        postgres_url.map(redact).unwrap_or_default()
+    );
+    if let Ok(read_url) = std::env::var("POSTGRES_READ_URL") {
+        println!("POSTGRES_READ_URL: {}", redact(&read_url));
+    }
+    println!("retries:          {}", config.max_retries);
+    println!("min fields:       {}", config.min_fields);
+    println!(
+        "scrape gap:       {:?}, backing off by {}",
+        config.scrape_gap, config.gap_backoff
+    );
+    if let Some(throttle) = config.throttle {
+        println!(
+            "throttle:         x{} per failed request, -{} per request that went through",
+            throttle.step, throttle.recovery
+        );
+    }
+    println!("student delay:    {:?}", config.student_fetch_delay);
+    if let Some((count, size)) = config.inserters {
+        println!(
+            "inserters:        {count}, queueing up to {size} records, storing {} at a time",
+            config.insert_batch
+        );
+    }
+    println!("on conflict:      {:?}", config.on_conflict);
+    println!("fields:           {}", config.fields);
+    if config.filter != RecordFilter::default() {
+        println!("filter:           {:?}", config.filter);
+    }
+    println!(
+        "connection pool:  {} idle for {:?}{}",
+        config.pool_max_idle,
+        config.pool_idle_timeout,
+        if config.http1_only {
+            ", HTTP/1.1 only"
+        } else {
+            ""
+        }
+    );
+    println!(
+        "proxy:            {}",
+        config.proxy.as_deref().map(redact).unwrap_or_default()
+    );
+    match combi::auth::Credentials::from_env() {
+        Ok(Some(credentials)) => println!("credentials:      {credentials:?}"),
+        Ok(None) => {}
+        Err(e) => println!("credentials:      {e}"),
+    }
+    for user_agent in &config.user_agents {
+        println!("user agent:       {user_agent}");
+    }
+    for mirror in &config.mirrors {
+        println!("mirror:           {}", redact(mirror.as_str()));
+    }
+    if let Some((cache, mode)) = &config.page_cache {
+        println!("page cache:       {} ({mode:?})", cache.dir().display());
+    }
+
+    #[cfg(feature = "tor")]
+    if let Some((control, rotate_every)) = config.tor {
+        let password = match std::env::var("TOR_CONTROL_PASSWORD") {
+            Ok(_) => "set",
+            Err(_) => "unset",
+        };
+        println!("tor control:      {control}, rotating every {rotate_every} requests");
+        println!("tor password:     {password}");
+    }
+
+    #[cfg(feature = "nats")]
+    if let Ok(nats_url) = std::env::var("NATS_URL") {
+        let subject = std::env::var("NATS_SUBJECT").unwrap_or_else(|_| "combi.records".to_string());
+        println!("nats:             {} -> {subject}.<id>", redact(&nats_url));
+    }
+    println!();
+}
+
+/// The embedded migrations that haven't been applied to the database yet
+async fn pending_migrations(pool: &PgPool) -> sqlx::Result<Vec<String>> {
+    let applied: Vec<i64> =
+        sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success;")
+            .fetch_all(pool)
+            .await
+            .or_else(|e| match &e {
+                // the migrations table only exists once something was applied
+                sqlx::Error::Database(db) if db.code().as_deref() == Some("42P01") => Ok(vec![]),
+                _ => Err(e),
+            })?;
+
+    Ok(sqlx::migrate!()
+        .iter()
+        .filter(|m| !applied.contains(&m.version))
+        .map(|m| format!("{}_{}", m.version, m.description))
+        .collect())
+}
+
+/// Creates `dir` like the cache does on the first page kept, then writes and removes a file in it
+fn probe_dir(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let probe = dir.join(format!(".config-check.{}", std::process::id()));
+    std::fs::write(&probe, "")?;
+    std::fs::remove_file(probe)
+}
+
+/// Prints the effective configuration and checks that the database, schema and site are all there
+/// and the page cache can be written, returning the first kind of check that failed. Every check runs regardless, so a single run
+/// shows everything that needs fixing
+pub async fn run(config: &ScraperConfig) -> Result<(), Failure> {
+    let postgres_url = std::env::var("POSTGRES_URL").ok();
+    print_config(config, postgres_url.as_deref());
+
+    let mut failures = vec![];
+
+    match report(
+        "POSTGRES_URL is set",
+        postgres_url.ok_or("not set"),
+        Failure::Config,
+    ) {
+        Ok(postgres_url) => {
+            let pool = sqlx::postgres::PgPoolOptions::new()
+                .max_connections(1)
+                .connect(&postgres_url)
+                .await;
+            match report("database connection", pool, Failure::Database) {
+                Ok(pool) => {
+                    let pending = pending_migrations(&pool).await.map_err(|e| e.to_string());
+                    let pending = pending.and_then(|pending| match pending.as_slice() {
+                        [] => Ok(()),
+                        _ => Err(format!("pending {}", pending.join(", "))),
+                    });
+                    failures.extend(report("migrations", pending, Failure::Migrations).err());
+
+                    let missing = combi::info::missing_tables(&pool).await;
+                    let tables =
+                        missing.map_err(|e| e.to_string()).and_then(|missing| {
+                            match missing.as_slice() {
+                                [] => Ok(()),
+                                _ => Err(format!("missing {}", missing.join(", "))),
+                            }
+                        });
+                    failures.extend(report("tables", tables, Failure::Migrations).err());
+                }
+                Err(failure) => failures.push(failure),
+            }
+        }
+        Err(failure) => failures.push(failure),
+    }
+
+    if let Ok(read_url) = std::env::var("POSTGRES_READ_URL") {
+        let pool = sqlx::postgres::PgPoolOptions::new()
+            .max_connections(1)
+            .connect(&read_url)
+            .await;
+        failures.extend(report("read replica connection", pool, Failure::Database).err());
+    }
+
+    if let Some((cache, _)) = &config.page_cache {
+        let check = format!("page cache {}", cache.dir().display());
+        failures.extend(report(&check, probe_dir(cache.dir()), Failure::Cache).err());
+    }
+
+    match report("http client", config.build_client(), Failure::Config) {
+        Ok(client) => {
+            let response = client
+                .get(MathGenealogy::BASE_URL)
+                .send()
+                .await
+                .and_then(|r| r.error_for_status());
+            failures.extend(report(MathGenealogy::BASE_URL, response, Failure::Network).err());
+        }
+        Err(failure) => failures.push(failure),
+    }
+
+    match failures.first() {
+        Some(&failure) => Err(failure),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn failures_have_distinct_exit_codes() {
+        let codes = [
+            Failure::Config,
+            Failure::Database,
+            Failure::Migrations,
+            Failure::Network,
+            Failure::Cache,
+        ]
+        .map(Failure::exit_code);
+
+        for (i, code) in codes.iter().enumerate() {
+            // 1 is any other error and 2 a usage error from clap
+            assert!(*code > 2);
+            assert!(!codes[i + 1..].contains(code));
+        }
+    }
+
+    #[test]
+    fn the_cache_dir_is_created_and_left_empty() {
+        let dir = std::env::temp_dir().join(format!("combi-check-{}", std::process::id()));
+        let cache = dir.join("pages");
+        probe_dir(&cache).unwrap();
+        assert_eq!(std::fs::read_dir(&cache).unwrap().count(), 0);
+
+        // a file where the directory should be
+        let file = dir.join("file");
+        std::fs::write(&file, "").unwrap();
+        assert!(probe_dir(&file).is_err());
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
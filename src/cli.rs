@@ -0,0 +1,1386 @@
+use std::num::NonZeroUsize;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+use clap::ArgAction;
+use clap::Args;
+use clap::Parser;
+use clap::Subcommand;
+use combi::cache::CacheMode;
+use combi::cache::PageCache;
+use combi::export::Format;
+use combi::filter::NameList;
+use combi::filter::RecordFilter;
+use combi::graph::MAX_SUBTREE_DEPTH;
+use combi::parser::Id;
+use combi::parser::ScrapeFields;
+use combi::query::MissingField;
+use combi::sanitize::TextLimits;
+use combi::scrape::MissingIds;
+use combi::scrape::ScraperConfig;
+use combi::scrape::ThrottleConfig;
+use combi::store::ConflictStrategy;
+use tracing::level_filters::LevelFilter;
+
+/// Scrapes the Mathematics Genealogy Project into Postgres
+///
+/// Without a command, every id not in the database yet is scraped
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    #[command(flatten)]
+    pub scrape: ScrapeArgs,
+
+    /// Log more, can be repeated: -v = info, -vv = debug, -vvv = trace. Overrides RUST_LOG
+    #[arg(short, long, action = ArgAction::Count, global = true)]
+    pub verbose: u8,
+
+    /// Only log errors. Overrides RUST_LOG
+    #[arg(short, long, global = true, conflicts_with = "verbose")]
+    pub quiet: bool,
+
+    /// Abort database statements running longer than this, e.g. 30s or 10m, 0s never does
+    #[arg(long, global = true, value_parser = humantime::parse_duration, default_value = "5m")]
+    pub statement_timeout: Duration,
+
+    /// Log how many database connections are in use and idle this often, e.g. 30s, to tell
+    /// whether the pool is what holds a run back. Off unless given
+    #[arg(long, global = true, value_parser = humantime::parse_duration)]
+    pub pool_stats_every: Option<Duration>,
+}
+
+/// Options of the scrape that runs when no command is given
+#[derive(Debug, Args)]
+pub struct ScrapeArgs {
+    /// How many times a page is requested before giving up on it
+    #[arg(long, default_value_t = 3, value_parser = clap::value_parser!(u32).range(1..))]
+    pub retries: u32,
+
+    /// Warn about records with fewer than this many of school, year, country, dissertation and
+    /// students, which usually means the parser missed something
+    #[arg(long, default_value_t = 1)]
+    pub min_fields: usize,
+
+    /// Instead of scraping new ids, revisit every stored mathematician and only store their
+    /// advisor relations, e.g. to rebuild the graph
+    #[arg(long)]
+    pub relations_only: bool,
+
+    /// Stop starting new scrapes after this long, e.g. 2h or 30m, and exit once the ones in flight
+    /// finish. The next run picks up where this one stopped since stored ids are skipped
+    #[arg(long, value_parser = humantime::parse_duration)]
+    pub max_runtime: Option<Duration>,
+
+    /// Stop starting new scrapes once this much has been downloaded, e.g. 500MB or 2GiB, and exit
+    /// once the ones in flight finish. Counts the pages after they're decompressed
+    #[arg(long, value_parser = parse_bytes)]
+    pub max_bytes: Option<u64>,
+
+    /// Gap between starting scrapes, e.g. 700ms, which is what bounds the overall request rate.
+    /// It's only waited after scrapes that sent a request, ids already stored or read from the
+    /// page cache don't wait for it
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "700ms")]
+    pub scrape_gap: Duration,
+
+    /// How much the gap between scrapes grows while requests fail: it's multiplied by one plus
+    /// this times the share of the latest 50 requests that failed with a server error, a timeout,
+    /// a block or no answer. 4 makes it five times as long when they all fail, 0 keeps it fixed
+    #[arg(long, value_parser = parse_backoff, default_value_t = 4.0)]
+    pub gap_backoff: f64,
+
+    /// Adapt to a struggling site: every request that fails with a server error, a timeout, a
+    /// block or no answer multiplies the gap between scrapes by --throttle-step and halves how
+    /// many requests may be in flight, from 8, and every one that goes through takes
+    /// --throttle-recovery off the multiplier and lets one more be in flight
+    #[arg(long)]
+    pub throttle_on_error: bool,
+
+    /// What each failed request multiplies the gap between scrapes by, up to 32 times as long
+    #[arg(long, value_parser = parse_step, default_value_t = 2.0, requires = "throttle_on_error")]
+    pub throttle_step: f64,
+
+    /// What each request that went through takes off the gap's multiplier again
+    #[arg(long, value_parser = parse_backoff, default_value_t = 0.1, requires = "throttle_on_error")]
+    pub throttle_recovery: f64,
+
+    /// Pause between fetching the students of one record, e.g. 200ms. It adds to the gap between
+    /// starting scrapes, which is what bounds the overall request rate, so it only slows down how
+    /// fast a single record with many students hits the site
+    #[arg(long, value_parser = humantime::parse_duration, default_value = "200ms")]
+    pub student_fetch_delay: Duration,
+
+    /// Seconds an idle connection to the site is kept open for reuse
+    #[arg(long, default_value_t = 90)]
+    pub pool_idle_timeout: u64,
+
+    /// How many idle connections to the site are kept open for reuse
+    #[arg(long, default_value_t = 8)]
+    pub pool_max_idle: usize,
+
+    /// Commit the students of records with more than this many in chunks of this size, each in its
+    /// own transaction. A failure part way keeps the chunks already committed and the next run
+    /// resumes after them, at the cost of the record being partially stored in the meantime
+    #[arg(long)]
+    pub student_chunk_size: Option<NonZeroUsize>,
+
+    /// Hand scraped records to this many tasks that store them, rather than storing each in the
+    /// task that scraped it. Database load then no longer grows with the number of scrapes in
+    /// flight. Tree scrapes and records stored in chunks still store their own
+    #[arg(long)]
+    pub inserters: Option<NonZeroUsize>,
+
+    /// How many scraped records can wait for an inserter before scraping holds off until one is
+    /// stored
+    #[arg(long, default_value = "32", requires = "inserters")]
+    pub insert_queue: NonZeroUsize,
+
+    /// Store up to this many queued records in one transaction, saving a commit per record. A
+    /// batch that fails is stored again a record at a time, so one bad record only fails itself
+    #[arg(long, default_value = "1", requires = "inserters")]
+    pub insert_batch: NonZeroUsize,
+
+    /// Remember the ids of this many school and country names each once they're stored, so
+    /// records naming them don't store them again. 0 turns it off
+    #[arg(long, default_value = "4096")]
+    pub name_cache: usize,
+
+    /// Only store records from these schools, can be repeated. Compared ignoring case
+    #[arg(long = "allow-school", value_name = "SCHOOL")]
+    pub allow_schools: Vec<String>,
+
+    /// Never store records from these schools, can be repeated
+    #[arg(long = "deny-school", value_name = "SCHOOL")]
+    pub deny_schools: Vec<String>,
+
+    /// Only store records from these countries, e.g. "United States", can be repeated
+    #[arg(long = "allow-country", value_name = "COUNTRY")]
+    pub allow_countries: Vec<String>,
+
+    /// Never store records from these countries, can be repeated
+    #[arg(long = "deny-country", value_name = "COUNTRY")]
+    pub deny_countries: Vec<String>,
+
+    /// Store records without the school or country a filter needs, rather than dropping them
+    #[arg(long)]
+    pub keep_unknown: bool,
+
+    /// Cut names longer than this many characters before storing them, the record's, its
+    /// students' and its advisors'. Every stored text has its control characters removed and its
+    /// line breaks and runs of spaces made single spaces either way
+    #[arg(long, value_name = "CHARS")]
+    pub max_name_length: Option<NonZeroUsize>,
+
+    /// Cut dissertation titles longer than this many characters before storing them
+    #[arg(long, value_name = "CHARS")]
+    pub max_title_length: Option<NonZeroUsize>,
+
+    /// Cut school names longer than this many characters before storing them
+    #[arg(long, value_name = "CHARS")]
+    pub max_school_length: Option<NonZeroUsize>,
+
+    /// Store how long fetching, parsing and inserting took for every scraped id, see
+    /// slowest-scrapes
+    #[arg(long)]
+    pub record_timings: bool,
+
+    /// Store the fields a scrape changed about an already stored mathematician, e.g. a student
+    /// filled in from their own page, with the values before and after
+    #[arg(long)]
+    pub record_field_updates: bool,
+
+    /// Store the main content of every page that didn't parse, cut to 16 KiB, in parse_failures
+    /// along with the error, to make fixtures of without fetching the page again
+    #[arg(long)]
+    pub store_parse_failures: bool,
+
+    /// Record the run in scrape_runs under this label, with when it started and finished and
+    /// its summary, see runs
+    #[arg(long, value_name = "LABEL")]
+    pub run_label: Option<String>,
+
+    /// Write the ids whose scrape failed to this CSV file at the end of the run, with whether the
+    /// id doesn't exist, its page didn't parse or something else went wrong
+    #[arg(long, value_name = "PATH")]
+    pub failed_ids: Option<PathBuf>,
+
+    /// Never scrape the ids in this file, one per line with `#` starting a comment. The file
+    /// --failed-ids writes can be given as it is
+    #[arg(long, value_name = "PATH")]
+    pub exclude_ids: Option<PathBuf>,
+
+    /// Only scrape again the ids whose scrape failed in earlier runs, as recorded in the database
+    #[arg(long, conflicts_with = "relations_only")]
+    pub retry_failed: bool,
+
+    /// How ids the site has no record for are logged, a full scan meets thousands of them
+    #[arg(long, value_enum, default_value_t = MissingIds::Summary)]
+    pub missing_ids: MissingIds,
+
+    /// What storing an advisor relation that's already stored does: skip it for a cheap scan,
+    /// update its rank for a refresh, or fail the record for a strict import
+    #[arg(long, value_enum, default_value_t = ConflictStrategy::Update)]
+    pub on_conflict: ConflictStrategy,
+
+    /// Only parse and store these fields of a record, comma separated, e.g. advisors,students
+    /// for just the graph. The name is always stored, and countries only along with the
+    /// degrees. Every field unless given
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub fields: Vec<ScrapeField>,
+
+    /// Never negotiate HTTP/2, for proxies or servers that mishandle it
+    #[arg(long)]
+    pub http1_only: bool,
+
+    /// Send every request through this proxy, e.g. http://127.0.0.1:3128 or, with the tor
+    /// feature, socks5h://127.0.0.1:9050
+    #[arg(long)]
+    pub proxy: Option<String>,
+
+    /// The base url of a copy of the site to fetch a page from when the site doesn't serve it,
+    /// e.g. https://mirror.example.org/mgp/. Repeat for more, they're tried in order
+    #[arg(long = "mirror", value_name = "URL")]
+    pub mirrors: Vec<reqwest::Url>,
+
+    /// A user agent to send instead of the default one. Repeat for more, requests take turns
+    /// with them
+    #[arg(long = "user-agent-rotation", value_name = "UA")]
+    pub user_agents: Vec<String>,
+
+    /// Keep every fetched page in this directory and read pages from it instead of fetching them
+    /// again
+    #[arg(long, value_name = "DIR")]
+    pub cache_dir: Option<PathBuf>,
+
+    /// Fetch every page even when it's cached, the fetched pages still replace the cached ones. A
+    /// page the site says is unchanged since it was cached is read from the cache
+    #[arg(long, requires = "cache_dir")]
+    pub no_cache: bool,
+
+    /// Never fetch a page, only read the cached ones. A page that isn't cached fails its scrape,
+    /// e.g. to reparse a previous crawl offline
+    #[arg(long, requires = "cache_dir", conflicts_with = "no_cache")]
+    pub cache_only: bool,
+
+    /// The Tor control port to request a new circuit from, the password is read from
+    /// TOR_CONTROL_PASSWORD. Requires --proxy pointing at the same Tor daemon
+    #[cfg(feature = "tor")]
+    #[arg(long, requires = "proxy")]
+    pub tor_control: Option<std::net::SocketAddr>,
+
+    /// Request a new Tor circuit after this many requests, 0 only rotates when blocked
+    #[cfg(feature = "tor")]
+    #[arg(long, default_value_t = 50)]
+    pub tor_rotate_every: u64,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Export the database as one file per table
+    Export {
+        #[arg(long, value_enum, default_value_t = Format::Csv)]
+        format: Format,
+
+        /// Only export rows written after this RFC 3339 timestamp, e.g. 2024-03-01T00:00:00Z
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+
+        /// Only export these tables, comma separated, e.g. advisor_relations for just the edges of
+        /// the graph. Every table by default
+        #[arg(long, value_delimiter = ',', value_name = "TABLE")]
+        tables: Vec<String>,
+
+        /// Directory the files are written to, or with `--format sqlite` the database file, which
+        /// mustn't exist yet
+        #[arg(long, default_value = ".")]
+        out: PathBuf,
+
+        /// Start each CSV file with a UTF-8 byte order mark, for Excel to show accented names
+        /// correctly
+        #[arg(long)]
+        bom: bool,
+    },
+    /// Restore the tables of an `export --format copy` into an empty, migrated database, in one
+    /// transaction
+    Import {
+        /// Directory the export was written to
+        dir: PathBuf,
+
+        /// Only import these tables, comma separated. Every table by default
+        #[arg(long, value_delimiter = ',', value_name = "TABLE")]
+        tables: Vec<String>,
+    },
+    /// Compare two exports, e.g. from before and after a refresh run, and print how many
+    /// mathematicians and relations were added, removed or changed. Needs no database
+    Diff {
+        /// Directory of the earlier export
+        before: PathBuf,
+
+        /// Directory of the later export
+        after: PathBuf,
+
+        /// Also list every added (+), removed (-) and changed (~) row
+        #[arg(long)]
+        details: bool,
+    },
+    /// Print how many descendants a mathematician has among the stored advisor relations
+    Descendants {
+        id: Id,
+
+        /// Count in a copy of every advisor relation loaded into memory rather than with a
+        /// recursive query, faster for mathematicians with large subtrees
+        #[arg(long)]
+        in_memory: bool,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Count everyone's descendants over every advisor relation loaded into memory and store the
+    /// counts in descendant_counts, replacing the previous ones, e.g. after a scrape for ranking
+    /// by them in SQL
+    CountDescendants,
+    /// Load a CSV file mapping mathematicians to what other knowledge bases call them, with a
+    /// mgp_id,system,external_id header, e.g. 92443,wikidata,Q1000001. Prints how many mappings
+    /// were added or changed and the rows that were rejected
+    ImportExternalIds {
+        csv: PathBuf,
+
+        /// Only print what would be imported
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the mathematician another knowledge base calls `external_id`, with every id stored
+    /// for them
+    LookupExternal {
+        /// The knowledge base, e.g. wikidata
+        system: String,
+
+        external_id: String,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the advisors with the most direct students, to spot suspicious counts
+    TopAdvisors {
+        /// How many advisors to print
+        #[arg(short, default_value_t = 20)]
+        n: i64,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print every group of mathematicians that are each other's descendants, one per line, which
+    /// only errors in the data produce and which should be checked against the site
+    Cycles {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print everyone who graduated from a school, earliest first. Spellings that differ only in
+    /// case, accents, punctuation or a leading "The" count as the same school
+    AtSchool {
+        school: String,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the mathematicians whose name has every word of the search in any order, so that
+    /// "Pereira, Rajesh", "Pereira" and "Rajesh" all find Rajesh Pereira. A word also matches
+    /// the start of one
+    SearchNames {
+        search: String,
+
+        /// Print at most this many
+        #[arg(long, default_value_t = 50, value_parser = clap::value_parser!(i64).range(1..))]
+        limit: i64,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print everyone stored with neither an advisor nor a student, usually records whose
+    /// connections were never stored and are worth scraping again
+    Orphans {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the shape of the stored genealogy: how deep it goes, how many students advisors
+    /// have, how many mathematicians have no advisor or no students and how much of it hangs
+    /// together
+    GraphStats {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print how many mathematicians first graduated in each decade, or in buckets of another
+    /// width, and how many have no graduation. Spikes in implausible years point at parser errors
+    YearHistogram {
+        /// Years per bucket
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u16).range(1..=1000))]
+        width: u16,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Write a mathematician and their descendants as a nested JSON tree, each node with its id,
+    /// name and children, for tree visualizations such as D3's tidy tree
+    ExportSubtree {
+        id: Id,
+
+        /// How many generations of students to include below the mathematician. Nodes with
+        /// students below the cut are marked "truncated"
+        #[arg(long, default_value_t = 5,
+            value_parser = clap::value_parser!(u32).range(1..=i64::from(MAX_SUBTREE_DEPTH)))]
+        depth: u32,
+
+        /// Write the tree to this file rather than printing it
+        #[arg(long, value_name = "FILE")]
+        out: Option<PathBuf>,
+    },
+    /// Keep one graduation record per mathematician, dropping the ones older scrapes left behind
+    /// along with schools and countries nothing refers to anymore
+    RebuildGraduationRecords {
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Correct the stored names an older parser misordered, e.g. "Marshall Jr. Hall" for "Hall,
+    /// Marshall, Jr.", by listing them again from their advisor's students table. Prints each
+    /// change as a diff and the names that need a human to check them
+    RepairNames {
+        /// Only print what would be renamed
+        #[arg(long)]
+        dry_run: bool,
+
+        /// Check at most this many names
+        #[arg(long)]
+        limit: Option<i64>,
+    },
+    /// Delete the rows left behind by mathematicians that were deleted, e.g. by a merge, and print
+    /// how many of each
+    Prune {
+        /// Only print what would be removed
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Look schools up with a geocoder and store their coordinates, for drawing them on a map.
+    /// Schools the geocoder doesn't know are left without
+    #[cfg(feature = "geocode")]
+    GeocodeSchools {
+        /// The Nominatim search endpoint to ask
+        #[arg(long, default_value = combi::geocode::NOMINATIM)]
+        endpoint: String,
+
+        /// How long to wait between requests, Nominatim's public instance allows one a second
+        #[arg(long, value_parser = humantime::parse_duration, default_value = "1s")]
+        gap: Duration,
+
+        /// Only look up this many schools
+        #[arg(long, value_parser = clap::value_parser!(i64).range(1..))]
+        limit: Option<i64>,
+
+        /// Also ask about the schools the geocoder didn't know before
+        #[arg(long)]
+        retry_unresolved: bool,
+    },
+    /// Give the schools stored without a country one, from the country other spellings of their
+    /// name are in or from a table of known locations, and print the schools left without
+    ResolveLocations {
+        /// A CSV file with a school,country header of known locations, which win over the
+        /// spellings
+        #[arg(long, value_name = "CSV")]
+        table: Option<PathBuf>,
+
+        /// Only print what would be resolved
+        #[arg(long)]
+        dry_run: bool,
+    },
+    /// Print the scrape runs recorded with --run-label, the latest first
+    Runs {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the ids whose scrape took longest, recorded by scrapes run with --record-timings
+    SlowestScrapes {
+        /// How many ids to print
+        #[arg(short, default_value_t = 20)]
+        n: i64,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Scrape the stored mathematicians missing a field again, to fill gaps without re-scraping
+    /// everything. Takes the same options as a normal scrape
+    RescrapeMissing {
+        #[arg(long, value_enum)]
+        field: MissingField,
+    },
+    /// Scrape a mathematician and everyone descended from them, again where already stored. Takes
+    /// the same options as a normal scrape
+    ScrapeTree {
+        id: Id,
+
+        /// Take the descendants from the site's descendants page of the mathematician, which lists
+        /// the whole subtree at once, instead of finding them a generation at a time
+        #[arg(long)]
+        follow_descendants: bool,
+    },
+    /// Scrape the students stored without their own page, then the students those turn up, a pass
+    /// at a time until a pass finds nobody new. Takes the same options as a normal scrape
+    ScrapeClosure {
+        /// Stop after this many passes even if there are students left
+        #[arg(long, default_value = "20")]
+        max_passes: NonZeroUsize,
+    },
+    /// Scrape the students stored without their own page, only by name along with their advisor,
+    /// once each and log how many were completed. Takes the same options as a normal scrape
+    CompleteStudents,
+    /// Print the schema version, Postgres version, database size and rows per table
+    DbInfo {
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Print the DDL of the schema the migrations create, or the JSON Schema of the records
+    /// `show --format json` prints, without connecting to the database
+    ExportSchema {
+        #[arg(long, value_enum, default_value_t = SchemaFormat::Sql)]
+        format: SchemaFormat,
+    },
+    /// Fetch and parse the page of one mathematician and print what was found, without
+    /// connecting to the database. Handy for trying the tool out or reproducing a parser bug
+    Show {
+        id: Id,
+
+        #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+        format: OutputFormat,
+    },
+    /// Fetch and parse the page of one mathematician and print every field, missing ones
+    /// included, under the url of the page, for checking the parser by eye against the page in a
+    /// browser. Doesn't connect to the database
+    Qa { id: Id },
+    /// Fetch a random sample of live pages and check they still parse, to catch changes to the
+    /// site's layout. Writes nothing, fails if any fetched page didn't parse
+    SelfTest {
+        /// How many random ids to fetch
+        #[arg(long, default_value_t = 20)]
+        sample: usize,
+    },
+    /// Parse every .html file in a directory of fixtures and print a tab separated line per file
+    /// with its fields, flagging the ones that failed or look empty. Nothing is fetched and the
+    /// database isn't needed, fails if any file didn't parse
+    ParseFixtures { dir: PathBuf },
+    /// Fetch the pages of a random sample of stored mathematicians and report the fields that
+    /// differ from what's stored, with how many of the sampled ids changed in each. Changes in
+    /// most ids point at a parser that no longer reads the site right, changes in a few at
+    /// records the site updated since. Writes nothing, not even to the page cache
+    DriftCheck {
+        /// How many stored ids to fetch
+        #[arg(long, default_value_t = 20)]
+        sample: usize,
+    },
+    /// Print the effective configuration and check the database, migrations and site are
+    /// reachable, without scraping. Exits with 3 for bad configuration, 4 when the database is
+    /// unreachable, 5 for pending migrations and 6 when the site is unreachable
+    ConfigCheck,
+}
+
+/// How the commands that read the database print what they found
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum OutputFormat {
+    /// Lines meant for reading
+    Text,
+    /// A single JSON document, for scripts
+    Json,
+}
+
+/// Which schema export-schema prints
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum SchemaFormat {
+    /// The DDL of the database
+    Sql,
+    /// The JSON Schema of a scraped record
+    JsonSchema,
+}
+
+/// A field of a record `--fields` can select
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum ScrapeField {
+    Advisors,
+    Students,
+    Degrees,
+    Country,
+    Dissertations,
+    Provenance,
+}
+
+impl ScrapeField {
+    /// The fields `--fields` selects, every one of them when it wasn't given
+    pub fn mask(fields: &[ScrapeField]) -> ScrapeFields {
+        if fields.is_empty() {
+            return ScrapeFields::ALL;
+        }
+        fields.iter().fold(ScrapeFields::NONE, |mask, field| {
+            mask | match field {
+                ScrapeField::Advisors => ScrapeFields::ADVISORS,
+                ScrapeField::Students => ScrapeFields::STUDENTS,
+                ScrapeField::Degrees => ScrapeFields::DEGREES,
+                ScrapeField::Country => ScrapeFields::COUNTRY,
+                ScrapeField::Dissertations => ScrapeFields::DISSERTATIONS,
+                ScrapeField::Provenance => ScrapeFields::PROVENANCE,
+            }
+        })
+    }
+}
+
+/// A factor that can't shrink a duration
+fn parse_backoff(s: &str) -> Result<f64, String> {
+    let factor: f64 = s.parse().map_err(|_| format!("{s} is not a number"))?;
+    if !factor.is_finite() || factor < 0.0 {
+        return Err(format!("{s} must be 0 or more"));
+    }
+    Ok(factor)
+}
+
+/// A multiplier that can't shorten a duration
+fn parse_step(s: &str) -> Result<f64, String> {
+    let step: f64 = s.parse().map_err(|_| format!("{s} is not a number"))?;
+    if !step.is_finite() || step < 1.0 {
+        return Err(format!("{s} must be 1 or more"));
+    }
+    Ok(step)
+}
+
+/// A byte count with an optional decimal (K, M, G) or binary (Ki, Mi, Gi) unit, and an optional
+/// trailing B, e.g. 500MB, 2GiB or 1000
+fn parse_bytes(s: &str) -> Result<u64, String> {
+    let s = s.trim();
+    let digits = s.find(|c: char| !c.is_ascii_digit()).unwrap_or(s.len());
+    let (number, unit) = s.split_at(digits);
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("{s} doesn't start with a number"))?;
+    let multiplier: u64 = match unit.trim().trim_end_matches(['B', 'b']) {
+        "" => 1,
+        "K" | "k" => 1000,
+        "M" => 1000_u64.pow(2),
+        "G" => 1000_u64.pow(3),
+        "Ki" => 1 << 10,
+        "Mi" => 1 << 20,
+        "Gi" => 1 << 30,
+        other => return Err(format!("{other} is not a unit, use K, M, G, Ki, Mi or Gi")),
+    };
+
+    number
+        .checked_mul(multiplier)
+        .ok_or_else(|| format!("{s} is too large"))
+}
+
+impl Cli {
+    /// The log level asked for on the command line, `None` means defer to `RUST_LOG`
+    pub fn log_level(&self) -> Option<LevelFilter> {
+        if self.quiet {
+            return Some(LevelFilter::ERROR);
+        }
+
+        match self.verbose {
+            0 => None,
+            1 => Some(LevelFilter::INFO),
+            2 => Some(LevelFilter::DEBUG),
+            _ => Some(LevelFilter::TRACE),
+        }
+    }
+}
+
+impl From<&ScrapeArgs> for ScraperConfig {
+    fn from(args: &ScrapeArgs) -> Self {
+        Self {
+            max_retries: args.retries,
+            min_fields: args.min_fields,
+            pool_idle_timeout: Duration::from_secs(args.pool_idle_timeout),
+            pool_max_idle: args.pool_max_idle,
+            http1_only: args.http1_only,
+            relations_only: args.relations_only,
+            rescrape_missing: None,
+            tree_root: None,
+            follow_descendants: false,
+            closure_passes: None,
+            complete_students: false,
+            max_runtime: args.max_runtime,
+            max_bytes: args.max_bytes,
+            proxy: args.proxy.clone(),
+            mirrors: args.mirrors.clone(),
+            user_agents: args.user_agents.clone(),
+            page_cache: args.cache_dir.as_ref().map(|dir| {
+                let mode = if args.cache_only {
+                    CacheMode::Only
+                } else if args.no_cache {
+                    CacheMode::Refresh
+                } else {
+                    CacheMode::Use
+                };
+                (PageCache::new(dir), mode)
+            }),
+            student_chunk: args.student_chunk_size.map(NonZeroUsize::get),
+            scrape_gap: args.scrape_gap,
+            gap_backoff: args.gap_backoff,
+            throttle: args.throttle_on_error.then_some(ThrottleConfig {
+                step: args.throttle_step,
+                recovery: args.throttle_recovery,
+            }),
+            student_fetch_delay: args.student_fetch_delay,
+            inserters: args.inserters.map(|count| (count, args.insert_queue)),
+            insert_batch: args.insert_batch,
+            name_cache: args.name_cache,
+            filter: RecordFilter {
+                schools: NameList {
+                    allow: args.allow_schools.clone(),
+                    deny: args.deny_schools.clone(),
+                },
+                countries: NameList {
+                    allow: args.allow_countries.clone(),
+                    deny: args.deny_countries.clone(),
+                },
+                keep_unknown: args.keep_unknown,
+            },
+            text_limits: TextLimits {
+                name: args.max_name_length,
+                title: args.max_title_length,
+                school: args.max_school_length,
+            },
+            record_timings: args.record_timings,
+            record_field_updates: args.record_field_updates,
+            store_parse_failures: args.store_parse_failures,
+            run_label: args.run_label.clone(),
+            failed_ids: args.failed_ids.clone(),
+            exclude_ids: args.exclude_ids.clone(),
+            retry_failed: args.retry_failed,
+            missing_ids: args.missing_ids,
+            on_conflict: args.on_conflict,
+            fields: ScrapeField::mask(&args.fields),
+            #[cfg(feature = "tor")]
+            tor: args
+                .tor_control
+                .map(|control| (control, args.tor_rotate_every)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Cli {
+        Cli::try_parse_from(std::iter::once("combi").chain(args.iter().copied())).unwrap()
+    }
+
+    #[test]
+    fn log_level_defers_to_env_without_flags() {
+        assert_eq!(parse(&[]).log_level(), None);
+    }
+
+    #[test]
+    fn log_level_maps_verbosity() {
+        assert_eq!(parse(&["-v"]).log_level(), Some(LevelFilter::INFO));
+        assert_eq!(parse(&["-vv"]).log_level(), Some(LevelFilter::DEBUG));
+        assert_eq!(parse(&["-vvv"]).log_level(), Some(LevelFilter::TRACE));
+        assert_eq!(parse(&["-vvvv"]).log_level(), Some(LevelFilter::TRACE));
+        assert_eq!(parse(&["--quiet"]).log_level(), Some(LevelFilter::ERROR));
+    }
+
+    #[test]
+    fn quiet_conflicts_with_verbose() {
+        assert!(Cli::try_parse_from(["combi", "-q", "-v"]).is_err());
+    }
+
+    #[cfg(feature = "geocode")]
+    #[test]
+    fn geocode_schools_defaults_to_nominatim_once_a_second() {
+        let Some(Command::GeocodeSchools {
+            endpoint,
+            gap,
+            limit,
+            retry_unresolved,
+        }) = parse(&["geocode-schools"]).command
+        else {
+            panic!("expected geocode-schools");
+        };
+        assert_eq!(endpoint, combi::geocode::NOMINATIM);
+        assert_eq!(gap, Duration::from_secs(1));
+        assert_eq!(limit, None);
+        assert!(!retry_unresolved);
+    }
+
+    #[test]
+    fn export_parses_since() {
+        let cli = parse(&[
+            "export",
+            "--format",
+            "ndjson",
+            "--since",
+            "2024-03-01T00:00:00Z",
+        ]);
+        let Some(Command::Export { format, since, .. }) = cli.command else {
+            panic!("expected export");
+        };
+        assert_eq!(format, Format::Ndjson);
+        assert_eq!(since, "2024-03-01T00:00:00Z".parse().ok());
+    }
+
+    #[test]
+    fn export_takes_a_list_of_tables() {
+        let cli = parse(&["export", "--tables", "mathematicians,advisor_relations"]);
+        let Some(Command::Export { tables, .. }) = cli.command else {
+            panic!("expected export");
+        };
+        assert_eq!(tables, ["mathematicians", "advisor_relations"]);
+
+        let Some(Command::Export { tables, .. }) = parse(&["export"]).command else {
+            panic!("expected export");
+        };
+        assert!(tables.is_empty());
+    }
+
+    #[test]
+    fn import_takes_a_directory() {
+        let cli = parse(&["import", "backup", "--tables", "mathematicians"]);
+        let Some(Command::Import { dir, tables }) = cli.command else {
+            panic!("expected import");
+        };
+        assert_eq!(dir, PathBuf::from("backup"));
+        assert_eq!(tables, ["mathematicians"]);
+    }
+
+    #[test]
+    fn scrape_closure_caps_the_passes() {
+        let Some(Command::ScrapeClosure { max_passes }) = parse(&["scrape-closure"]).command else {
+            panic!("expected scrape-closure");
+        };
+        assert_eq!(max_passes.get(), 20);
+        assert!(Cli::try_parse_from(["combi", "scrape-closure", "--max-passes", "0"]).is_err());
+    }
+
+    #[test]
+    fn export_schema_prints_the_ddl_by_default() {
+        assert!(matches!(
+            parse(&["export-schema"]).command,
+            Some(Command::ExportSchema {
+                format: SchemaFormat::Sql
+            })
+        ));
+        assert!(matches!(
+            parse(&["export-schema", "--format", "json-schema"]).command,
+            Some(Command::ExportSchema {
+                format: SchemaFormat::JsonSchema
+            })
+        ));
+    }
+
+    #[test]
+    fn complete_students_takes_scrape_options() {
+        let cli = parse(&["--scrape-gap", "1s", "complete-students"]);
+        assert!(matches!(cli.command, Some(Command::CompleteStudents)));
+        assert_eq!(cli.scrape.scrape_gap, Duration::from_secs(1));
+    }
+
+    #[test]
+    fn pool_stats_are_off_by_default() {
+        assert_eq!(parse(&[]).pool_stats_every, None);
+        assert_eq!(
+            parse(&["db-info", "--pool-stats-every", "30s"]).pool_stats_every,
+            Some(Duration::from_secs(30))
+        );
+    }
+
+    #[test]
+    fn max_runtime_takes_a_human_duration() {
+        let cli = parse(&["--max-runtime", "2h 30m"]);
+        assert_eq!(cli.scrape.max_runtime, Some(Duration::from_secs(9000)));
+
+        assert_eq!(parse(&[]).scrape.max_runtime, None);
+        assert!(Cli::try_parse_from(["combi", "--max-runtime", "soon"]).is_err());
+    }
+
+    #[test]
+    fn student_fetch_delay_defaults_to_200ms() {
+        assert_eq!(
+            parse(&[]).scrape.student_fetch_delay,
+            Duration::from_millis(200)
+        );
+        assert_eq!(
+            parse(&["--student-fetch-delay", "1s"])
+                .scrape
+                .student_fetch_delay,
+            Duration::from_secs(1)
+        );
+    }
+
+    #[test]
+    fn the_scrape_gap_and_its_backoff_are_configurable() {
+        let cli = parse(&[]);
+        assert_eq!(cli.scrape.scrape_gap, Duration::from_millis(700));
+        assert_eq!(cli.scrape.gap_backoff, 4.0);
+
+        let cli = parse(&["--scrape-gap", "2s", "--gap-backoff", "0.5"]);
+        assert_eq!(cli.scrape.scrape_gap, Duration::from_secs(2));
+        assert_eq!(cli.scrape.gap_backoff, 0.5);
+
+        assert!(Cli::try_parse_from(["combi", "--gap-backoff", "-1"]).is_err());
+        assert!(Cli::try_parse_from(["combi", "--gap-backoff", "inf"]).is_err());
+    }
+
+    #[test]
+    fn throttling_is_opt_in_and_tunable() {
+        let cli = parse(&[]);
+        assert!(!cli.scrape.throttle_on_error);
+        assert_eq!(cli.scrape.throttle_step, 2.0);
+        assert_eq!(cli.scrape.throttle_recovery, 0.1);
+
+        let cli = parse(&[
+            "--throttle-on-error",
+            "--throttle-step",
+            "1.5",
+            "--throttle-recovery",
+            "0.5",
+        ]);
+        assert!(cli.scrape.throttle_on_error);
+        assert_eq!(cli.scrape.throttle_step, 1.5);
+        assert_eq!(cli.scrape.throttle_recovery, 0.5);
+
+        // the parameters are meaningless without it
+        assert!(Cli::try_parse_from(["combi", "--throttle-step", "3"]).is_err());
+        assert!(
+            Cli::try_parse_from(["combi", "--throttle-on-error", "--throttle-step", "0.5"])
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn filters_can_be_repeated() {
+        let cli = parse(&[
+            "--allow-country",
+            "Canada",
+            "--allow-country",
+            "United States",
+            "--deny-school",
+            "McGill University",
+        ]);
+        assert_eq!(cli.scrape.allow_countries, ["Canada", "United States"]);
+        assert_eq!(cli.scrape.deny_schools, ["McGill University"]);
+        assert!(cli.scrape.allow_schools.is_empty());
+        assert!(!cli.scrape.keep_unknown);
+    }
+
+    #[test]
+    fn rescrape_missing_takes_a_field_and_scrape_options() {
+        let cli = parse(&["--retries", "5", "rescrape-missing", "--field", "school"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::RescrapeMissing {
+                field: MissingField::School
+            })
+        ));
+        assert_eq!(cli.scrape.retries, 5);
+    }
+
+    #[test]
+    fn resolve_locations_takes_an_optional_table() {
+        let cli = parse(&["resolve-locations", "--table", "locations.csv", "--dry-run"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::ResolveLocations {
+                table: Some(ref table),
+                dry_run: true,
+            }) if table == &PathBuf::from("locations.csv")
+        ));
+        assert!(matches!(
+            parse(&["resolve-locations"]).command,
+            Some(Command::ResolveLocations {
+                table: None,
+                dry_run: false,
+            })
+        ));
+    }
+
+    #[test]
+    fn runs_are_labelled_with_a_scrape_option() {
+        let cli = parse(&["--run-label", "nightly"]);
+        assert_eq!(cli.scrape.run_label.as_deref(), Some("nightly"));
+        assert!(matches!(
+            parse(&["runs", "--format", "json"]).command,
+            Some(Command::Runs {
+                format: OutputFormat::Json
+            })
+        ));
+    }
+
+    #[test]
+    fn diff_takes_two_directories() {
+        let cli = parse(&["diff", "before", "after", "--details"]);
+        let Some(Command::Diff {
+            before,
+            after,
+            details,
+        }) = cli.command
+        else {
+            panic!("not a diff: {:?}", cli.command);
+        };
+        assert_eq!((before, after), ("before".into(), "after".into()));
+        assert!(details);
+    }
+
+    #[test]
+    fn read_commands_print_text_unless_asked_for_json() {
+        assert!(matches!(
+            parse(&["descendants", "92443"]).command,
+            Some(Command::Descendants {
+                format: OutputFormat::Text,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse(&["top-advisors", "-n", "5", "--format", "json"]).command,
+            Some(Command::TopAdvisors {
+                n: 5,
+                format: OutputFormat::Json
+            })
+        ));
+        assert!(matches!(
+            parse(&["db-info", "--format", "json"]).command,
+            Some(Command::DbInfo {
+                format: OutputFormat::Json
+            })
+        ));
+        assert!(matches!(
+            parse(&["graph-stats", "--format", "json"]).command,
+            Some(Command::GraphStats {
+                format: OutputFormat::Json
+            })
+        ));
+        assert!(Cli::try_parse_from(["combi", "slowest-scrapes", "--format", "yaml"]).is_err());
+    }
+
+    #[test]
+    fn fields_are_every_field_unless_given() {
+        assert_eq!(
+            ScrapeField::mask(&parse(&[]).scrape.fields),
+            ScrapeFields::ALL
+        );
+        let fields = parse(&["--fields", "advisors,students"]).scrape.fields;
+        assert_eq!(
+            ScrapeField::mask(&fields),
+            ScrapeFields::ADVISORS | ScrapeFields::STUDENTS
+        );
+        assert!(Cli::try_parse_from(["combi", "--fields", "advisors,names"]).is_err());
+    }
+
+    #[test]
+    fn count_descendants_takes_no_arguments() {
+        assert!(matches!(
+            parse(&["count-descendants"]).command,
+            Some(Command::CountDescendants)
+        ));
+        assert!(Cli::try_parse_from(["combi", "count-descendants", "92443"]).is_err());
+    }
+
+    #[test]
+    fn year_histogram_defaults_to_decades() {
+        assert!(matches!(
+            parse(&["year-histogram"]).command,
+            Some(Command::YearHistogram {
+                width: 10,
+                format: OutputFormat::Text
+            })
+        ));
+        assert!(matches!(
+            parse(&["year-histogram", "--width", "25"]).command,
+            Some(Command::YearHistogram { width: 25, .. })
+        ));
+        assert!(Cli::try_parse_from(["combi", "year-histogram", "--width", "0"]).is_err());
+    }
+
+    #[test]
+    fn show_takes_an_id_and_the_scrape_options() {
+        let cli = parse(&["--retries", "5", "show", "92443", "--format", "json"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Show {
+                id: Id(92443),
+                format: OutputFormat::Json
+            })
+        ));
+        assert_eq!(cli.scrape.retries, 5);
+    }
+
+    #[test]
+    fn qa_takes_an_id() {
+        let cli = parse(&["qa", "92443"]);
+        assert!(matches!(cli.command, Some(Command::Qa { id: Id(92443) })));
+    }
+
+    #[test]
+    fn at_school_takes_the_name() {
+        let cli = parse(&["at-school", "University of Toronto"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::AtSchool { school, .. }) if school == "University of Toronto"
+        ));
+        assert!(Cli::try_parse_from(["combi", "at-school"]).is_err());
+    }
+
+    #[test]
+    fn search_names_takes_the_search() {
+        let cli = parse(&["search-names", "Pereira, Rajesh"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::SearchNames { search, limit: 50, .. }) if search == "Pereira, Rajesh"
+        ));
+    }
+
+    #[test]
+    fn text_limits_are_off_by_default() {
+        let cli = parse(&[]);
+        assert_eq!(cli.scrape.max_name_length, None);
+        assert_eq!(cli.scrape.max_title_length, None);
+        assert_eq!(cli.scrape.max_school_length, None);
+
+        let cli = parse(&["--max-title-length", "500"]);
+        assert_eq!(cli.scrape.max_title_length, NonZeroUsize::new(500));
+        assert!(Cli::try_parse_from(["combi", "--max-name-length", "0"]).is_err());
+    }
+
+    #[test]
+    fn parse_fixtures_takes_a_directory() {
+        assert!(matches!(
+            parse(&["parse-fixtures", "fixtures"]).command,
+            Some(Command::ParseFixtures { dir }) if dir == std::path::Path::new("fixtures")
+        ));
+        assert!(Cli::try_parse_from(["combi", "parse-fixtures"]).is_err());
+    }
+
+    #[test]
+    fn orphans_takes_a_format() {
+        assert!(matches!(
+            parse(&["orphans", "--format", "json"]).command,
+            Some(Command::Orphans {
+                format: OutputFormat::Json
+            })
+        ));
+    }
+
+    #[test]
+    fn retry_failed_is_its_own_scan() {
+        assert!(parse(&["--retry-failed"]).scrape.retry_failed);
+        assert!(Cli::try_parse_from(["combi", "--retry-failed", "--relations-only"]).is_err());
+    }
+
+    #[test]
+    fn exclude_ids_takes_a_path() {
+        assert_eq!(parse(&[]).scrape.exclude_ids, None);
+        assert_eq!(
+            parse(&["--exclude-ids", "bad.txt"]).scrape.exclude_ids,
+            Some(PathBuf::from("bad.txt"))
+        );
+    }
+
+    #[test]
+    fn subtree_depth_is_capped() {
+        let cli = parse(&["export-subtree", "10416", "--out", "knuth.json"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::ExportSubtree {
+                id: Id(10416),
+                depth: 5,
+                out: Some(_)
+            })
+        ));
+        assert!(Cli::try_parse_from(["combi", "export-subtree", "1", "--depth", "0"]).is_err());
+        assert!(Cli::try_parse_from(["combi", "export-subtree", "1", "--depth", "101"]).is_err());
+    }
+
+    #[test]
+    fn repair_names_takes_a_dry_run_and_a_limit() {
+        assert!(matches!(
+            parse(&["repair-names", "--dry-run", "--limit", "10"]).command,
+            Some(Command::RepairNames {
+                dry_run: true,
+                limit: Some(10)
+            })
+        ));
+    }
+
+    #[test]
+    fn external_ids_are_imported_from_a_file_and_looked_up_by_system() {
+        assert!(matches!(
+            parse(&["import-external-ids", "wikidata.csv", "--dry-run"]).command,
+            Some(Command::ImportExternalIds { ref csv, dry_run: true })
+                if csv == &PathBuf::from("wikidata.csv")
+        ));
+        assert!(matches!(
+            parse(&["lookup-external", "wikidata", "Q1000001"]).command,
+            Some(Command::LookupExternal { ref system, ref external_id, format: OutputFormat::Text })
+                if system == "wikidata" && external_id == "Q1000001"
+        ));
+    }
+
+    #[test]
+    fn drift_check_samples_20_ids_by_default() {
+        assert!(matches!(
+            parse(&["drift-check"]).command,
+            Some(Command::DriftCheck { sample: 20 })
+        ));
+        assert!(matches!(
+            parse(&["drift-check", "--sample", "5"]).command,
+            Some(Command::DriftCheck { sample: 5 })
+        ));
+    }
+
+    #[test]
+    fn insert_batches_need_inserters() {
+        assert_eq!(parse(&[]).scrape.insert_batch.get(), 1);
+        assert_eq!(
+            parse(&["--inserters", "2", "--insert-batch", "50"])
+                .scrape
+                .insert_batch
+                .get(),
+            50
+        );
+        assert!(Cli::try_parse_from(["combi", "--insert-batch", "50"]).is_err());
+    }
+
+    #[test]
+    fn cache_flags_need_a_cache_and_exclude_each_other() {
+        let args = parse(&["--cache-dir", "pages", "--cache-only"]).scrape;
+        assert_eq!(args.cache_dir, Some(PathBuf::from("pages")));
+        assert!(args.cache_only);
+        assert!(
+            parse(&["--cache-dir", "pages", "--no-cache"])
+                .scrape
+                .no_cache
+        );
+        assert!(Cli::try_parse_from(["combi", "--cache-only"]).is_err());
+        assert!(Cli::try_parse_from(["combi", "--no-cache"]).is_err());
+        assert!(Cli::try_parse_from([
+            "combi",
+            "--cache-dir",
+            "pages",
+            "--no-cache",
+            "--cache-only"
+        ])
+        .is_err());
+    }
+
+    #[test]
+    fn conflicts_update_by_default() {
+        assert_eq!(parse(&[]).scrape.on_conflict, ConflictStrategy::Update);
+        assert_eq!(
+            parse(&["--on-conflict", "error"]).scrape.on_conflict,
+            ConflictStrategy::Error
+        );
+    }
+
+    #[test]
+    fn missing_ids_are_summarized_by_default() {
+        assert_eq!(parse(&[]).scrape.missing_ids, MissingIds::Summary);
+        assert_eq!(
+            parse(&["--missing-ids", "each"]).scrape.missing_ids,
+            MissingIds::Each
+        );
+    }
+
+    #[test]
+    fn scrape_tree_takes_a_root_and_scrape_options() {
+        let cli = parse(&[
+            "--retries",
+            "2",
+            "scrape-tree",
+            "92443",
+            "--follow-descendants",
+        ]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::ScrapeTree {
+                id: Id(92443),
+                follow_descendants: true
+            })
+        ));
+        assert_eq!(cli.scrape.retries, 2);
+    }
+
+    #[test]
+    fn descendants_takes_an_id() {
+        let cli = parse(&["descendants", "42"]);
+        assert!(matches!(
+            cli.command,
+            Some(Command::Descendants { id: Id(42), .. })
+        ));
+        assert!(Cli::try_parse_from(["combi", "descendants", "0"]).is_err());
+        assert!(matches!(
+            parse(&["descendants", "42", "--in-memory"]).command,
+            Some(Command::Descendants {
+                in_memory: true,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn max_bytes_takes_units() {
+        assert_eq!(parse(&["--max-bytes", "1000"]).scrape.max_bytes, Some(1000));
+        assert_eq!(
+            parse(&["--max-bytes", "500MB"]).scrape.max_bytes,
+            Some(500_000_000)
+        );
+        assert_eq!(
+            parse(&["--max-bytes", "2GiB"]).scrape.max_bytes,
+            Some(2 << 30)
+        );
+        assert_eq!(
+            parse(&["--max-bytes", "64k"]).scrape.max_bytes,
+            Some(64_000)
+        );
+        assert_eq!(parse(&[]).scrape.max_bytes, None);
+        assert!(Cli::try_parse_from(["combi", "--max-bytes", "5TB"]).is_err());
+        assert!(Cli::try_parse_from(["combi", "--max-bytes", "MB"]).is_err());
+    }
+
+    #[test]
+    fn top_advisors_defaults_to_twenty() {
+        assert!(matches!(
+            parse(&["top-advisors"]).command,
+            Some(Command::TopAdvisors { n: 20, .. })
+        ));
+        assert!(matches!(
+            parse(&["top-advisors", "-n", "5"]).command,
+            Some(Command::TopAdvisors { n: 5, .. })
+        ));
+    }
+
+    #[test]
+    fn global_flags_work_before_commands() {
+        assert!(matches!(
+            parse(&["-v", "export"]).command,
+            Some(Command::Export { .. })
+        ));
+        assert!(matches!(
+            parse(&["export", "-q"]).command,
+            Some(Command::Export { .. })
+        ));
+    }
+
+    #[test]
+    fn statement_timeout_defaults_to_five_minutes() {
+        assert_eq!(parse(&[]).statement_timeout, Duration::from_secs(300));
+        assert_eq!(
+            parse(&["descendants", "42", "--statement-timeout", "30s"]).statement_timeout,
+            Duration::from_secs(30)
+        );
+    }
+
+    #[test]
+    fn retries_default_and_override() {
+        assert_eq!(parse(&[]).scrape.retries, 3);
+        assert_eq!(parse(&["--retries", "1", "-v"]).scrape.retries, 1);
+        assert!(Cli::try_parse_from(["combi", "--retries", "0"]).is_err());
+    }
+}
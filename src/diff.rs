@@ -0,0 +1,284 @@
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::File;
+use std::io::BufRead;
+use std::io::BufReader;
+use std::path::Path;
+
+use color_eyre::eyre::eyre;
+use serde::de::DeserializeOwned;
+
+use crate::export::AdvisorRelationRow;
+use crate::export::Format;
+use crate::export::MathematicianRow;
+
+/// The tables of one export, keyed the way the export orders them
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Snapshot {
+    pub mathematicians: BTreeMap<i32, MathematicianRow>,
+    pub relations: BTreeMap<(i32, i32), AdvisorRelationRow>,
+}
+
+impl Snapshot {
+    /// Reads the files `combi export` wrote into `dir`, in whichever format they were written
+    pub fn read_dir(dir: &Path) -> color_eyre::Result<Self> {
+        for format in [Format::Csv, Format::Ndjson, Format::Json] {
+            let path = |table: &str| dir.join(format!("{table}.{}", format.extension()));
+            let (mathematicians, relations) = (path("mathematicians"), path("advisor_relations"));
+            if mathematicians.exists() && relations.exists() {
+                return Self::read(
+                    format,
+                    BufReader::new(File::open(mathematicians)?),
+                    BufReader::new(File::open(relations)?),
+                );
+            }
+        }
+
+        Err(eyre!("{} doesn't hold an export", dir.display()))
+    }
+
+    /// Reads a snapshot from the exported mathematicians and advisor relations
+    pub fn read(
+        format: Format,
+        mathematicians: impl BufRead,
+        relations: impl BufRead,
+    ) -> color_eyre::Result<Self> {
+        let mathematicians = read_rows::<MathematicianRow>(format, mathematicians)?
+            .into_iter()
+            .map(|row| (row.id, row))
+            .collect();
+        let relations = read_rows::<AdvisorRelationRow>(format, relations)?
+            .into_iter()
+            .map(|row| ((row.advisor, row.advisee), row))
+            .collect();
+
+        Ok(Self {
+            mathematicians,
+            relations,
+        })
+    }
+}
+
+fn read_rows<T: DeserializeOwned>(
+    format: Format,
+    input: impl BufRead,
+) -> color_eyre::Result<Vec<T>> {
+    match format {
+        Format::Csv => Ok(csv::Reader::from_reader(input)
+            .into_deserialize()
+            .collect::<Result<_, _>>()?),
+        Format::Ndjson => {
+            let mut rows = vec![];
+            for line in input.lines() {
+                let line = line?;
+                if !line.trim().is_empty() {
+                    rows.push(serde_json::from_str(&line)?);
+                }
+            }
+            Ok(rows)
+        }
+        Format::Json => Ok(serde_json::from_reader(input)?),
+        Format::Sqlite | Format::Copy => {
+            Err(eyre!("only csv, ndjson and json exports can be compared"))
+        }
+    }
+}
+
+/// What changed from one snapshot to a later one. Write times are ignored, a row only counts as
+/// changed when its data did
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct SnapshotDiff {
+    pub added_mathematicians: Vec<i32>,
+    pub removed_mathematicians: Vec<i32>,
+    /// Mathematicians whose name or source url changed
+    pub changed_mathematicians: Vec<i32>,
+    pub added_relations: Vec<(i32, i32)>,
+    pub removed_relations: Vec<(i32, i32)>,
+    /// Relations whose rank changed
+    pub changed_relations: Vec<(i32, i32)>,
+}
+
+impl SnapshotDiff {
+    /// The lists of ids behind the summary, one `+`, `-` or `~` line per row
+    pub fn details(&self) -> String {
+        let mut out = String::new();
+        let mut list = |sign: char, table: &str, rows: Vec<String>| {
+            for row in rows {
+                out.push_str(&format!("{sign} {table} {row}\n"));
+            }
+        };
+        let ids = |ids: &[i32]| ids.iter().map(i32::to_string).collect();
+        let pairs = |pairs: &[(i32, i32)]| {
+            pairs
+                .iter()
+                .map(|(advisor, advisee)| format!("{advisor} -> {advisee}"))
+                .collect()
+        };
+
+        list('+', "mathematician", ids(&self.added_mathematicians));
+        list('-', "mathematician", ids(&self.removed_mathematicians));
+        list('~', "mathematician", ids(&self.changed_mathematicians));
+        list('+', "relation", pairs(&self.added_relations));
+        list('-', "relation", pairs(&self.removed_relations));
+        list('~', "relation", pairs(&self.changed_relations));
+        out
+    }
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "mathematicians: {} added, {} removed, {} changed",
+            self.added_mathematicians.len(),
+            self.removed_mathematicians.len(),
+            self.changed_mathematicians.len()
+        )?;
+        writeln!(
+            f,
+            "relations:      {} added, {} removed, {} changed",
+            self.added_relations.len(),
+            self.removed_relations.len(),
+            self.changed_relations.len()
+        )
+    }
+}
+
+/// Compares `before` with `after`, every list in key order
+pub fn diff(before: &Snapshot, after: &Snapshot) -> SnapshotDiff {
+    let (added_mathematicians, removed_mathematicians, changed_mathematicians) =
+        diff_maps(&before.mathematicians, &after.mathematicians, |a, b| {
+            a.name != b.name || a.source_url != b.source_url
+        });
+    let (added_relations, removed_relations, changed_relations) =
+        diff_maps(&before.relations, &after.relations, |a, b| a.rank != b.rank);
+
+    SnapshotDiff {
+        added_mathematicians,
+        removed_mathematicians,
+        changed_mathematicians,
+        added_relations,
+        removed_relations,
+        changed_relations,
+    }
+}
+
+/// The keys only in `after`, only in `before`, and in both but `changed`
+fn diff_maps<K: Ord + Copy, V>(
+    before: &BTreeMap<K, V>,
+    after: &BTreeMap<K, V>,
+    changed: impl Fn(&V, &V) -> bool,
+) -> (Vec<K>, Vec<K>, Vec<K>) {
+    let added = after
+        .keys()
+        .filter(|key| !before.contains_key(key))
+        .copied()
+        .collect();
+    let removed = before
+        .keys()
+        .filter(|key| !after.contains_key(key))
+        .copied()
+        .collect();
+    let changed = before
+        .iter()
+        .filter(|(key, old)| after.get(key).is_some_and(|new| changed(old, new)))
+        .map(|(key, _)| *key)
+        .collect();
+
+    (added, removed, changed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::export::export_advisor_relations;
+    use crate::export::export_mathematicians;
+    use sqlx::PgPool;
+
+    fn snapshot(mathematicians: &str, relations: &str) -> Snapshot {
+        Snapshot::read(
+            Format::Csv,
+            format!("id,name,source_url,updated_at\n{mathematicians}").as_bytes(),
+            format!("advisor,advisee,rank,updated_at\n{relations}").as_bytes(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn finds_added_removed_and_changed_rows() {
+        let before = snapshot(
+            "1,Advisor,,2024-01-01T00:00:00Z\n\
+             2,Student,,2024-01-01T00:00:00Z\n\
+             3,Gone,,2024-01-01T00:00:00Z\n",
+            "1,2,1,2024-01-01T00:00:00Z\n1,3,2,2024-01-01T00:00:00Z\n",
+        );
+        let after = snapshot(
+            // 1 was only rewritten, 2 got a source url
+            "1,Advisor,,2024-06-01T00:00:00Z\n\
+             2,Student,https://www.mathgenealogy.org/id.php?id=2,2024-06-01T00:00:00Z\n\
+             4,New,,2024-06-01T00:00:00Z\n",
+            "1,2,2,2024-06-01T00:00:00Z\n1,4,1,2024-06-01T00:00:00Z\n",
+        );
+
+        let diff = diff(&before, &after);
+        assert_eq!(
+            diff,
+            SnapshotDiff {
+                added_mathematicians: vec![4],
+                removed_mathematicians: vec![3],
+                changed_mathematicians: vec![2],
+                added_relations: vec![(1, 4)],
+                removed_relations: vec![(1, 3)],
+                changed_relations: vec![(1, 2)],
+            }
+        );
+        assert_eq!(
+            diff.to_string(),
+            "mathematicians: 1 added, 1 removed, 1 changed\n\
+             relations:      1 added, 1 removed, 1 changed\n"
+        );
+        assert_eq!(
+            diff.details(),
+            "+ mathematician 4\n\
+             - mathematician 3\n\
+             ~ mathematician 2\n\
+             + relation 1 -> 4\n\
+             - relation 1 -> 3\n\
+             ~ relation 1 -> 2\n"
+        );
+    }
+
+    #[test]
+    fn identical_snapshots_have_no_diff() {
+        let same = snapshot("1,Advisor,,2024-01-01T00:00:00Z\n", "");
+        assert_eq!(diff(&same, &same), SnapshotDiff::default());
+    }
+
+    #[sqlx::test]
+    async fn reads_back_what_export_wrote(pool: PgPool) {
+        sqlx::query("INSERT INTO mathematicians(id, name) VALUES (1, 'Advisor'), (2, 'Student');")
+            .execute(&pool)
+            .await
+            .unwrap();
+        sqlx::query("INSERT INTO advisor_relations(advisor, advisee, rank) VALUES (1, 2, 1);")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        for format in [Format::Csv, Format::Ndjson, Format::Json] {
+            let (mut mathematicians, mut relations) = (vec![], vec![]);
+            export_mathematicians(&pool, format, None, &mut mathematicians)
+                .await
+                .unwrap();
+            export_advisor_relations(&pool, format, None, &mut relations)
+                .await
+                .unwrap();
+
+            let snapshot =
+                Snapshot::read(format, mathematicians.as_slice(), relations.as_slice()).unwrap();
+            assert_eq!(snapshot.mathematicians.keys().collect::<Vec<_>>(), [&1, &2]);
+            assert_eq!(snapshot.relations[&(1, 2)].rank, Some(1));
+        }
+    }
+}
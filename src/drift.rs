@@ -0,0 +1,269 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use scraper::Html;
+use sqlx::PgPool;
+
+use crate::parser::Id;
+use crate::parser::ScrapeFields;
+use crate::scrape::stored_fields;
+use crate::scrape::Scraper;
+use crate::scrape::StoredFields;
+
+/// A field whose stored value isn't what the live page has now
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FieldChange {
+    pub id: Id,
+    pub field: &'static str,
+    pub stored: Option<String>,
+    pub live: Option<String>,
+}
+
+/// How a sample of stored mathematicians compares to their live pages
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Drift {
+    pub sampled: usize,
+    /// Ids whose live page has what's stored
+    pub unchanged: usize,
+    /// Ids that couldn't be compared, their page couldn't be fetched or parsed or they aren't
+    /// stored, with why
+    pub unreachable: Vec<(Id, String)>,
+    pub changes: Vec<FieldChange>,
+}
+
+impl Drift {
+    /// How many ids changed in each field. A field most ids changed in points at the parser, a
+    /// few here and there at the site's data being updated
+    pub fn by_field(&self) -> BTreeMap<&'static str, usize> {
+        let mut fields = BTreeMap::new();
+        for change in &self.changes {
+            *fields.entry(change.field).or_default() += 1;
+        }
+        fields
+    }
+}
+
+impl fmt::Display for Drift {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let changed = self.sampled - self.unchanged - self.unreachable.len();
+        writeln!(
+            f,
+            "sampled {} stored ids: {} unchanged, {changed} changed, {} unreachable",
+            self.sampled,
+            self.unchanged,
+            self.unreachable.len()
+        )?;
+        for (field, ids) in self.by_field() {
+            writeln!(f, "{field:<18} changed in {ids} of {}", self.sampled)?;
+        }
+        for change in &self.changes {
+            writeln!(
+                f,
+                "changed     {} {}: {:?} -> {:?}",
+                change.id, change.field, change.stored, change.live
+            )?;
+        }
+        for (id, error) in &self.unreachable {
+            writeln!(f, "unreachable {id}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `n` stored mathematicians picked at random among the ones scraped from their own page
+pub async fn sample_stored_ids(pool: &PgPool, n: usize) -> color_eyre::Result<Vec<Id>> {
+    let ids = sqlx::query_scalar!(
+        "SELECT id FROM mathematicians WHERE page_hash IS NOT NULL ORDER BY random() LIMIT $1;",
+        i64::try_from(n)?
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(ids.into_iter().map(Id).collect())
+}
+
+/// Fetches the first page of each of `ids`, as far apart as the scrapes of a run, and compares
+/// what it parses into with what's stored about the id. Nothing is written anywhere
+pub async fn run(scraper: &Scraper, ids: &[Id]) -> color_eyre::Result<Drift> {
+    let mut drift = Drift {
+        sampled: ids.len(),
+        ..Drift::default()
+    };
+
+    let mut mark = scraper.pacer.mark();
+    for &id in ids {
+        let mut connection = scraper.db.read.acquire().await?;
+        let stored_name =
+            sqlx::query_scalar!("SELECT name FROM mathematicians WHERE id = $1;", id as Id)
+                .fetch_optional(&mut *connection)
+                .await?;
+        let Some(stored_name) = stored_name else {
+            drift.unreachable.push((id, "it isn't stored".to_string()));
+            continue;
+        };
+        let stored = stored_fields(&mut connection, id)
+            .await?
+            .unwrap_or_default();
+        drop(connection);
+
+        scraper.pacer.wait(mark, || true).await;
+        mark = scraper.pacer.mark();
+
+        let live = scraper.fetch_first_page(id).await.and_then(|(body, _)| {
+            let (record, _) = scraper
+                .source
+                .parse(&Html::parse_document(&body), ScrapeFields::ALL)?;
+            Ok(record)
+        });
+        let mut live = match live {
+            Ok(record) => record,
+            Err(e) => {
+                drift.unreachable.push((id, e.to_string()));
+                continue;
+            }
+        };
+
+        // compared as it would be stored
+        scraper.config.text_limits.apply(&mut live);
+
+        let mut changes = vec![];
+        if stored_name != live.name {
+            changes.push(FieldChange {
+                id,
+                field: "name",
+                stored: Some(stored_name),
+                live: Some(live.name.clone()),
+            });
+        }
+        let scraped = StoredFields::scraped(&live);
+        changes.extend(
+            stored
+                .changes(&scraped)
+                .map(|(field, stored, live)| FieldChange {
+                    id,
+                    field,
+                    stored: stored.map(str::to_string),
+                    live: live.map(str::to_string),
+                }),
+        );
+
+        if changes.is_empty() {
+            drift.unchanged += 1;
+        }
+        drift.changes.extend(changes);
+    }
+
+    Ok(drift)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scrape::test::fixture;
+    use crate::scrape::test::scraper;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+
+    async fn site(page: &str) -> MockServer {
+        let server = MockServer::start().await;
+        Mock::given(path("/id.php"))
+            .and(query_param("id", "92443"))
+            .respond_with(fixture(page))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        Mock::given(path("/id.php"))
+            .respond_with(fixture("Tai-Yih.html"))
+            .mount(&server)
+            .await;
+        server
+    }
+
+    #[sqlx::test]
+    async fn reports_the_fields_the_live_page_changed(pool: PgPool) {
+        let stored = site("rajesh.html").await;
+        scraper(pool.clone(), &stored)
+            .scrape(Id(92443))
+            .await
+            .unwrap();
+        let before: Vec<(i32, String)> =
+            sqlx::query_as("SELECT id, name FROM mathematicians ORDER BY id;")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+
+        // the page as it was stored has nothing new
+        let drift = run(&scraper(pool.clone(), &stored), &[Id(92443)])
+            .await
+            .unwrap();
+        assert_eq!(drift.unchanged, 1);
+        assert!(drift.changes.is_empty(), "{drift}");
+
+        let updated = site("multipart-school.html").await;
+        let drift = run(&scraper(pool.clone(), &updated), &[Id(92443), Id(1)])
+            .await
+            .unwrap();
+        assert_eq!(drift.sampled, 2);
+        assert_eq!(drift.unchanged, 0);
+        // an id that isn't stored has nothing to compare with
+        assert_eq!(drift.unreachable, [(Id(1), "it isn't stored".to_string())]);
+        let fields: Vec<_> = drift.by_field().into_keys().collect();
+        assert_eq!(fields, ["country", "name", "school", "year"]);
+        assert!(drift.changes.contains(&FieldChange {
+            id: Id(92443),
+            field: "school",
+            stored: Some("University of Toronto".to_string()),
+            live: Some("東京帝国大学 (Tokyo Imperial University)".to_string()),
+        }));
+
+        // nothing was written
+        let after: Vec<(i32, String)> =
+            sqlx::query_as("SELECT id, name FROM mathematicians ORDER BY id;")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(before, after);
+    }
+
+    #[sqlx::test]
+    async fn samples_ids_scraped_from_their_own_page(pool: PgPool) {
+        sqlx::query(
+            "INSERT INTO mathematicians(id, name, page_hash) VALUES
+                (1, 'Scraped', '\\x00'), (2, 'Only a student', NULL);",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        assert_eq!(sample_stored_ids(&pool, 5).await.unwrap(), [Id(1)]);
+        assert!(sample_stored_ids(&pool, 0).await.unwrap().is_empty());
+    }
+
+    #[test]
+    fn the_summary_counts_changes_per_field() {
+        let change = |id, field: &'static str| FieldChange {
+            id: Id(id),
+            field,
+            stored: Some("old".to_string()),
+            live: None,
+        };
+        let drift = Drift {
+            sampled: 4,
+            unchanged: 1,
+            unreachable: vec![(Id(9), "Failed to get".to_string())],
+            changes: vec![change(1, "school"), change(1, "year"), change(2, "school")],
+        };
+
+        assert_eq!(
+            drift.to_string(),
+            "sampled 4 stored ids: 1 unchanged, 2 changed, 1 unreachable\n\
+             school             changed in 2 of 4\n\
+             year               changed in 1 of 4\n\
+             changed     1 school: Some(\"old\") -> None\n\
+             changed     1 year: Some(\"old\") -> None\n\
+             changed     2 school: Some(\"old\") -> None\n\
+             unreachable 9: Failed to get\n"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+/// Failures the scraper needs to tell apart from a generic error, recovered from a
+/// `color_eyre::Report` with `downcast_ref`
+#[derive(Debug, thiserror::Error)]
+pub enum ScraperError {
+    /// The site served a rate limit or CAPTCHA page instead of the record
+    #[error("the site is rate limiting or blocking requests")]
+    RateLimited,
+
+    /// The site has no record with this id
+    #[error("there is no record with id {0}")]
+    NotFound(i32),
+
+    /// The page was served but isn't a record the parser understands
+    #[error("the page didn't parse: {0}")]
+    Parse(String),
+
+    /// The site answered 401 or 403, it wants credentials or doesn't accept the ones sent
+    #[error("the site refused access with {0}, check SITE_USERNAME, SITE_TOKEN or SITE_COOKIE")]
+    Unauthorized(u16),
+
+    /// The site answered with another status that isn't a page, a 4xx that asking again won't
+    /// change
+    #[error("the site answered {0}")]
+    Status(u16),
+
+    /// Only cached pages were to be read and this one isn't cached
+    #[error("{0} isn't in the page cache")]
+    NotCached(String),
+}
+
+/// The page is the site's notice that an id has no record, served with a 200 like a record. The
+/// parser can't tell which id was asked for, the scraper turns this into [`ScraperError::NotFound`]
+#[derive(Debug, PartialEq, Eq, Clone, Copy, thiserror::Error)]
+#[error("the page says there is no record with the id")]
+pub struct NoRecord;
+
+/// A number that can't be a mathgenealogy id, which start at 1
+#[derive(Debug, PartialEq, Eq, Clone, Copy, thiserror::Error)]
+#[error("{0} is not a valid id, ids start at 1")]
+pub struct InvalidId(pub i32);
+
+/// A number that is outside the range of plausible graduation years
+#[derive(Debug, PartialEq, Eq, Clone, Copy, thiserror::Error)]
+#[error("{0} is not a plausible year")]
+pub struct InvalidYear(pub i16);
@@ -0,0 +1,485 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::io::Write;
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use futures::TryStreamExt;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::FromRow;
+use sqlx::PgPool;
+use tracing::info;
+
+/// The file formats a table can be exported as
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum Format {
+    Csv,
+    Ndjson,
+    /// A single JSON array per table, streamed a row at a time like the others
+    Json,
+    /// A single SQLite database of every table, see [`crate::sqlite`]
+    Sqlite,
+    /// Postgres' binary `COPY` format, one file per table, for a fast backup that
+    /// [`crate::bulk::import`] restores
+    Copy,
+}
+
+impl Format {
+    pub(crate) fn extension(self) -> &'static str {
+        match self {
+            Format::Csv => "csv",
+            Format::Ndjson => "ndjson",
+            Format::Json => "json",
+            Format::Sqlite => "db",
+            Format::Copy => "copy",
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, FromRow, Serialize, Deserialize)]
+pub struct MathematicianRow {
+    pub id: i32,
+    pub name: String,
+    pub source_url: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, FromRow, Serialize, Deserialize)]
+pub struct AdvisorRelationRow {
+    pub advisor: i32,
+    pub advisee: i32,
+    pub rank: Option<i32>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, FromRow, Serialize, Deserialize)]
+pub struct ExternalIdRow {
+    pub mathematician: i32,
+    pub system: String,
+    pub external_id: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Serializes rows one at a time in the chosen format
+enum RowWriter<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Ndjson(W),
+    /// `started` once a row was written, so that the next one gets a comma before it
+    Json {
+        out: W,
+        started: bool,
+    },
+}
+
+impl<W: Write> RowWriter<W> {
+    fn new(format: Format, out: W) -> color_eyre::Result<Self> {
+        match format {
+            Format::Csv => Ok(RowWriter::Csv(Box::new(csv::Writer::from_writer(out)))),
+            Format::Ndjson => Ok(RowWriter::Ndjson(out)),
+            Format::Json => {
+                let mut out = out;
+                out.write_all(b"[")?;
+                Ok(RowWriter::Json {
+                    out,
+                    started: false,
+                })
+            }
+            Format::Sqlite | Format::Copy => Err(eyre!("{format:?} exports don't serialize rows")),
+        }
+    }
+
+    fn write<T: Serialize>(&mut self, row: &T) -> color_eyre::Result<()> {
+        match self {
+            RowWriter::Csv(writer) => writer.serialize(row)?,
+            RowWriter::Ndjson(out) => {
+                serde_json::to_writer(&mut *out, row)?;
+                out.write_all(b"\n")?;
+            }
+            RowWriter::Json { out, started } => {
+                out.write_all(if *started { b",\n" } else { b"\n" })?;
+                serde_json::to_writer(&mut *out, row)?;
+                *started = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> color_eyre::Result<W> {
+        let mut out = match self {
+            RowWriter::Csv(writer) => writer.into_inner().map_err(|e| e.into_error())?,
+            RowWriter::Ndjson(out) => out,
+            RowWriter::Json { mut out, started } => {
+                out.write_all(if started { b"\n]\n" } else { b"]\n" })?;
+                out
+            }
+        };
+        out.flush()?;
+        Ok(out)
+    }
+}
+
+/// Streams the mathematicians written after `since` (or all of them) to `out`, returning how many
+/// rows were written
+pub async fn export_mathematicians<W: Write>(
+    pool: &PgPool,
+    format: Format,
+    since: Option<DateTime<Utc>>,
+    out: W,
+) -> color_eyre::Result<u64> {
+    let mut writer = RowWriter::new(format, out)?;
+    let mut rows = sqlx::query_as!(
+        MathematicianRow,
+        r"SELECT id, name, source_url, updated_at FROM mathematicians
+        WHERE $1::timestamptz IS NULL OR updated_at > $1
+        ORDER BY id;",
+        since,
+    )
+    .fetch(pool);
+
+    let mut count = 0;
+    while let Some(row) = rows.try_next().await? {
+        writer.write(&row)?;
+        count += 1;
+    }
+    writer.finish()?;
+
+    Ok(count)
+}
+
+/// Streams the advisor relations written after `since` (or all of them) to `out`, returning how
+/// many rows were written
+pub async fn export_advisor_relations<W: Write>(
+    pool: &PgPool,
+    format: Format,
+    since: Option<DateTime<Utc>>,
+    out: W,
+) -> color_eyre::Result<u64> {
+    let mut writer = RowWriter::new(format, out)?;
+    let mut rows = sqlx::query_as!(
+        AdvisorRelationRow,
+        r"SELECT advisor, advisee, rank, updated_at FROM advisor_relations
+        WHERE $1::timestamptz IS NULL OR updated_at > $1
+        ORDER BY advisor, advisee;",
+        since,
+    )
+    .fetch(pool);
+
+    let mut count = 0;
+    while let Some(row) = rows.try_next().await? {
+        writer.write(&row)?;
+        count += 1;
+    }
+    writer.finish()?;
+
+    Ok(count)
+}
+
+/// The UTF-8 byte order mark, which Excel needs at the start of a CSV file to read it as UTF-8
+/// rather than the system's code page
+pub const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// The tables a csv, ndjson or json export writes, one file each
+pub const EXPORTED_TABLES: [&str; 3] = ["mathematicians", "advisor_relations", "external_ids"];
+
+/// The tables of `known` named in `selected`, in the order of `known`, or all of them when none
+/// are named. A name that isn't known is an error listing the ones that are
+pub fn select_tables<'a>(
+    known: &[&'a str],
+    selected: &[String],
+) -> color_eyre::Result<Vec<&'a str>> {
+    if let Some(unknown) = selected.iter().find(|s| !known.contains(&s.as_str())) {
+        return Err(eyre!(
+            "{unknown:?} isn't a table this format exports, pick from {}",
+            known.join(", ")
+        ));
+    }
+
+    Ok(known
+        .iter()
+        .copied()
+        .filter(|table| selected.is_empty() || selected.iter().any(|s| s == table))
+        .collect())
+}
+
+/// Exports the `tables` (every table when empty) into `dir`, one `<table>.<format>` file each. A
+/// SQLite export is a copy of the database written to the file `dir` instead. `bom` starts each
+/// CSV file with [`UTF8_BOM`]
+pub async fn export(
+    pool: &PgPool,
+    format: Format,
+    since: Option<DateTime<Utc>>,
+    tables: &[String],
+    dir: &Path,
+    bom: bool,
+) -> color_eyre::Result<()> {
+    if bom && format != Format::Csv {
+        return Err(eyre!("--bom only applies to CSV exports"));
+    }
+
+    if format == Format::Sqlite {
+        if since.is_some() {
+            return Err(eyre!(
+                "SQLite exports are always complete, --since doesn't apply"
+            ));
+        }
+        let tables = select_tables(crate::info::TABLES, tables)?;
+        crate::sqlite::export(pool, &tables, dir).await?;
+        info!("Exported the database to {}", dir.display());
+        return Ok(());
+    }
+
+    if format == Format::Copy {
+        if since.is_some() {
+            return Err(eyre!(
+                "COPY exports are always complete, --since doesn't apply"
+            ));
+        }
+        let tables = select_tables(crate::info::TABLES, tables)?;
+        crate::bulk::export(pool, &tables, dir).await?;
+        info!("Exported the database to {}", dir.display());
+        return Ok(());
+    }
+
+    let tables = select_tables(&EXPORTED_TABLES, tables)?;
+    std::fs::create_dir_all(dir)?;
+    let file = |table: &str| -> std::io::Result<BufWriter<File>> {
+        let path = dir.join(format!("{table}.{}", format.extension()));
+        let mut out = BufWriter::new(File::create(path)?);
+        if bom {
+            out.write_all(UTF8_BOM)?;
+        }
+        Ok(out)
+    };
+
+    for table in tables {
+        let count = match table {
+            "mathematicians" => export_mathematicians(pool, format, since, file(table)?).await?,
+            "advisor_relations" => {
+                export_advisor_relations(pool, format, since, file(table)?).await?
+            }
+            "external_ids" => export_external_ids(pool, format, since, file(table)?).await?,
+            _ => unreachable!("{table} is in EXPORTED_TABLES"),
+        };
+        info!("Exported {count} rows of {table}");
+    }
+
+    Ok(())
+}
+
+/// Streams the external ids written after `since` (or all of them) to `out`, returning how many
+/// rows were written
+pub async fn export_external_ids<W: Write>(
+    pool: &PgPool,
+    format: Format,
+    since: Option<DateTime<Utc>>,
+    out: W,
+) -> color_eyre::Result<u64> {
+    let mut writer = RowWriter::new(format, out)?;
+    let mut rows = sqlx::query_as!(
+        ExternalIdRow,
+        r"SELECT mathematician, system, external_id, updated_at FROM external_ids
+        WHERE $1::timestamptz IS NULL OR updated_at > $1
+        ORDER BY mathematician, system;",
+        since,
+    )
+    .fetch(pool);
+
+    let mut count = 0;
+    while let Some(row) = rows.try_next().await? {
+        writer.write(&row)?;
+        count += 1;
+    }
+    writer.finish()?;
+
+    Ok(count)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn seed(pool: &PgPool) {
+        sqlx::query(
+            r"INSERT INTO mathematicians(id, name, source_url, updated_at) VALUES
+            (1, 'Old Advisor', NULL, '2024-01-01T00:00:00Z'),
+            (2, 'New Student', 'https://www.mathgenealogy.org/id.php?id=2', '2024-06-01T00:00:00Z');",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+        sqlx::query(
+            r"INSERT INTO advisor_relations(advisor, advisee, rank, updated_at) VALUES
+            (1, 2, 1, '2024-06-01T00:00:00Z');",
+        )
+        .execute(pool)
+        .await
+        .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn export_since_only_includes_newer_rows(pool: PgPool) {
+        seed(&pool).await;
+        let since = "2024-03-01T00:00:00Z".parse().ok();
+
+        let mut out = vec![];
+        let count = export_mathematicians(&pool, Format::Ndjson, since, &mut out)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let row: MathematicianRow = serde_json::from_slice(&out).unwrap();
+        assert_eq!(row.id, 2);
+        assert_eq!(row.name, "New Student");
+        assert_eq!(
+            row.source_url.as_deref(),
+            Some("https://www.mathgenealogy.org/id.php?id=2")
+        );
+    }
+
+    #[test]
+    fn tables_are_selected_by_name() {
+        let select = |names: &[&str]| {
+            let names: Vec<_> = names.iter().map(|n| n.to_string()).collect();
+            select_tables(&EXPORTED_TABLES, &names)
+        };
+        assert_eq!(select(&[]).unwrap(), EXPORTED_TABLES);
+        assert_eq!(
+            select(&["advisor_relations"]).unwrap(),
+            ["advisor_relations"]
+        );
+        // the order and repeats of the selection don't matter
+        assert_eq!(
+            select(&[
+                "external_ids",
+                "advisor_relations",
+                "mathematicians",
+                "mathematicians"
+            ])
+            .unwrap(),
+            EXPORTED_TABLES
+        );
+
+        let typo = select(&["mathematicans"]).unwrap_err().to_string();
+        assert!(typo.contains("\"mathematicans\""), "{typo}");
+        assert!(typo.contains("mathematicians, advisor_relations"), "{typo}");
+    }
+
+    #[sqlx::test]
+    async fn export_writes_only_the_selected_tables(pool: PgPool) {
+        let dir = std::env::temp_dir().join(format!("combi-tables-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let tables = ["advisor_relations".to_string()];
+        export(&pool, Format::Csv, None, &tables, &dir, false)
+            .await
+            .unwrap();
+        assert!(dir.join("advisor_relations.csv").exists());
+        assert!(!dir.join("mathematicians.csv").exists());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[sqlx::test]
+    async fn csv_files_start_with_a_bom_only_when_asked(pool: PgPool) {
+        seed(&pool).await;
+        let dir = std::env::temp_dir().join(format!("combi-bom-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let header = b"advisor,advisee,rank,updated_at\n";
+
+        export(&pool, Format::Csv, None, &[], &dir, false)
+            .await
+            .unwrap();
+        let plain = std::fs::read(dir.join("advisor_relations.csv")).unwrap();
+        assert!(plain.starts_with(header));
+
+        export(&pool, Format::Csv, None, &[], &dir, true)
+            .await
+            .unwrap();
+        for table in EXPORTED_TABLES {
+            let file = std::fs::read(dir.join(format!("{table}.csv"))).unwrap();
+            assert!(file.starts_with(UTF8_BOM), "{table}");
+        }
+        let marked = std::fs::read(dir.join("advisor_relations.csv")).unwrap();
+        assert_eq!(marked[UTF8_BOM.len()..], plain);
+
+        // the csv reader skips the mark, so a diff still reads the export
+        let rows: Vec<AdvisorRelationRow> = csv::Reader::from_reader(marked.as_slice())
+            .into_deserialize()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(rows.len(), 1);
+
+        let e = export(&pool, Format::Ndjson, None, &[], &dir, true)
+            .await
+            .unwrap_err();
+        assert_eq!(e.to_string(), "--bom only applies to CSV exports");
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[sqlx::test]
+    async fn export_json_is_one_array(pool: PgPool) {
+        let mut out = vec![];
+        let count = export_mathematicians(&pool, Format::Json, None, &mut out)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+        assert_eq!(String::from_utf8(out).unwrap(), "[]\n");
+
+        seed(&pool).await;
+        let mut out = vec![];
+        let count = export_mathematicians(&pool, Format::Json, None, &mut out)
+            .await
+            .unwrap();
+        assert_eq!(count, 2);
+
+        let rows: Vec<MathematicianRow> = serde_json::from_slice(&out).unwrap();
+        let ids: Vec<_> = rows.iter().map(|row| row.id).collect();
+        assert_eq!(ids, [1, 2]);
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.starts_with("[\n{"), "{out}");
+        assert!(out.ends_with("}\n]\n"), "{out}");
+    }
+
+    #[sqlx::test]
+    async fn export_csv_without_since_includes_everything(pool: PgPool) {
+        seed(&pool).await;
+
+        let mut out = vec![];
+        let count = export_advisor_relations(&pool, Format::Csv, None, &mut out)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        let out = String::from_utf8(out).unwrap();
+        assert_eq!(
+            out,
+            "advisor,advisee,rank,updated_at\n1,2,1,2024-06-01T00:00:00Z\n"
+        );
+    }
+
+    #[sqlx::test]
+    async fn external_ids_are_exported_as_cross_references(pool: PgPool) {
+        sqlx::query(
+            r"INSERT INTO external_ids(mathematician, system, external_id, updated_at) VALUES
+            (92443, 'wikidata', 'Q1000001', '2024-06-01T00:00:00Z');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let mut out = vec![];
+        let count = export_external_ids(&pool, Format::Csv, None, &mut out)
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "mathematician,system,external_id,updated_at\n\
+             92443,wikidata,Q1000001,2024-06-01T00:00:00Z\n"
+        );
+    }
+}
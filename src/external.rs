@@ -0,0 +1,374 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Read;
+
+use color_eyre::eyre::eyre;
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::Deserialize;
+use serde::Serialize;
+use sqlx::PgPool;
+
+use crate::mathematician::Listing;
+use crate::parser::Id;
+use crate::parser::Year;
+
+lazy_static! {
+    static ref SYSTEM: Regex = Regex::new(r"^[a-z0-9_-]+$").unwrap();
+    static ref WIKIDATA_QID: Regex = Regex::new(r"^Q[1-9][0-9]*$").unwrap();
+}
+
+/// A row of the CSV file `import-external-ids` reads, with a `mgp_id,system,external_id` header
+#[derive(Debug, Deserialize)]
+struct MappingRow {
+    mgp_id: i32,
+    system: String,
+    external_id: String,
+}
+
+/// What another knowledge base calls a mathematician
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct ExternalId {
+    pub system: String,
+    pub external_id: String,
+}
+
+/// What [`import_external_ids`] did, or would have done on a dry run
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct ExternalIdImport {
+    /// Mappings that weren't stored before
+    pub added: u64,
+    /// Mathematicians whose id in a system changed
+    pub changed: u64,
+    /// Mappings stored already
+    pub unchanged: u64,
+    /// Rows repeating an earlier row of the file
+    pub duplicates: u64,
+    /// Rows that weren't stored, by line, with why
+    pub rejected: Vec<(u64, String)>,
+}
+
+impl fmt::Display for ExternalIdImport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "added:      {}", self.added)?;
+        writeln!(f, "changed:    {}", self.changed)?;
+        writeln!(f, "unchanged:  {}", self.unchanged)?;
+        writeln!(f, "duplicates: {}", self.duplicates)?;
+        writeln!(f, "rejected:   {}", self.rejected.len())?;
+        for (line, reason) in &self.rejected {
+            writeln!(f, "  line {line}: {reason}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A mapping with its system lowercased and both ids trimmed, or why it can't be stored
+fn validate(row: MappingRow) -> Result<(Id, ExternalId), String> {
+    let system = row.system.trim().to_lowercase();
+    let external_id = row.external_id.trim().to_string();
+    if row.mgp_id <= 0 {
+        return Err(format!("{} isn't a mathematician's id", row.mgp_id));
+    }
+    if !SYSTEM.is_match(&system) {
+        return Err(format!(
+            "{:?} isn't a system name, use letters, digits, - and _",
+            row.system
+        ));
+    }
+    if external_id.is_empty() || external_id.contains(char::is_whitespace) {
+        return Err(format!("{:?} isn't an id", row.external_id));
+    }
+    if system == "wikidata" && !WIKIDATA_QID.is_match(&external_id) {
+        return Err(format!("{external_id:?} isn't a Wikidata QID like Q42"));
+    }
+
+    Ok((
+        Id(row.mgp_id),
+        ExternalId {
+            system,
+            external_id,
+        },
+    ))
+}
+
+/// Stores the mappings of the CSV file read from `csv`, with a `mgp_id,system,external_id` header,
+/// replacing the id a mathematician had in a system before. Rows that don't validate, that map an
+/// id the file or the database gives to another mathematician, or that give a mathematician a
+/// second id in the same system are rejected, repeats of a row are skipped. Everything happens in
+/// one transaction, which a dry run rolls back
+pub async fn import_external_ids(
+    pool: &PgPool,
+    csv: impl Read,
+    dry_run: bool,
+) -> color_eyre::Result<ExternalIdImport> {
+    let mut reader = csv::Reader::from_reader(csv);
+    let headers = reader.headers()?.clone();
+
+    let mut import = ExternalIdImport::default();
+    // what the file maps so far, both ways
+    let mut by_mathematician: HashMap<(Id, String), String> = HashMap::new();
+    let mut by_external: HashMap<(String, String), Id> = HashMap::new();
+    let mut mappings = vec![];
+    for record in reader.records() {
+        let record = record?;
+        let line = record.position().map_or(0, |p| p.line());
+        let mapping = record
+            .deserialize(Some(&headers))
+            .map_err(|e| e.to_string())
+            .and_then(validate);
+        let (id, external) = match mapping {
+            Ok(mapping) => mapping,
+            Err(reason) => {
+                import.rejected.push((line, reason));
+                continue;
+            }
+        };
+
+        let system = external.system.clone();
+        match by_mathematician.get(&(id, system.clone())) {
+            Some(earlier) if *earlier == external.external_id => {
+                import.duplicates += 1;
+                continue;
+            }
+            Some(earlier) => {
+                let reason = format!("{id} has the {system} id {earlier} already");
+                import.rejected.push((line, reason));
+                continue;
+            }
+            None => {}
+        }
+        if let Some(other) = by_external.get(&(system.clone(), external.external_id.clone())) {
+            let reason = format!("{system} {} names {other} already", external.external_id);
+            import.rejected.push((line, reason));
+            continue;
+        }
+
+        by_mathematician.insert((id, system.clone()), external.external_id.clone());
+        by_external.insert((system, external.external_id.clone()), id);
+        mappings.push((line, id, external));
+    }
+
+    let mut transaction = pool.begin().await?;
+    for (line, id, external) in mappings {
+        let stored = sqlx::query!(
+            r#"SELECT mathematician AS "mathematician: Id", external_id FROM external_ids
+            WHERE system = $1 AND (mathematician = $2 OR external_id = $3);"#,
+            external.system,
+            id as Id,
+            external.external_id,
+        )
+        .fetch_all(&mut *transaction)
+        .await?;
+        if let Some(other) = stored.iter().find(|row| row.mathematician != id) {
+            let reason = format!(
+                "{} {} is stored for {} already",
+                external.system, external.external_id, other.mathematician
+            );
+            import.rejected.push((line, reason));
+            continue;
+        }
+        match stored.first() {
+            Some(row) if row.external_id == external.external_id => {
+                import.unchanged += 1;
+                continue;
+            }
+            Some(_) => import.changed += 1,
+            None => import.added += 1,
+        }
+
+        sqlx::query!(
+            r"INSERT INTO external_ids (mathematician, system, external_id) VALUES ($1, $2, $3)
+            ON CONFLICT (mathematician, system) DO UPDATE SET external_id = EXCLUDED.external_id;",
+            id as Id,
+            external.system,
+            external.external_id,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    if dry_run {
+        transaction.rollback().await?;
+    } else {
+        transaction.commit().await?;
+    }
+
+    Ok(import)
+}
+
+/// A mathematician found by what another knowledge base calls them, with every id stored for
+/// them in any system
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Linked {
+    pub mathematician: Listing,
+    pub external_ids: Vec<ExternalId>,
+}
+
+impl fmt::Display for Linked {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", self.mathematician)?;
+        for id in &self.external_ids {
+            writeln!(f, "  {}: {}", id.system, id.external_id)?;
+        }
+        Ok(())
+    }
+}
+
+/// The mathematician `system` calls `external_id`, `None` when nobody is mapped to it or they
+/// aren't stored
+pub async fn find_by_external_id(
+    pool: &PgPool,
+    system: &str,
+    external_id: &str,
+) -> color_eyre::Result<Option<Linked>> {
+    let Some(row) = sqlx::query!(
+        r#"SELECT m.id, m.name, g.school AS "school?", g.year AS "year?"
+        FROM external_ids x
+        JOIN mathematicians m ON m.id = x.mathematician
+        LEFT JOIN LATERAL (
+            SELECT s.name AS school, g.year
+            FROM graduation_records g
+            JOIN schools s ON s.id = g.school
+            WHERE g.mathematician = m.id
+            ORDER BY g.position, g.year, s.name
+            LIMIT 1
+        ) g ON true
+        WHERE x.system = $1 AND x.external_id = $2;"#,
+        system.trim().to_lowercase(),
+        external_id.trim(),
+    )
+    .fetch_optional(pool)
+    .await?
+    else {
+        return Ok(None);
+    };
+
+    let external_ids = sqlx::query_as!(
+        ExternalId,
+        "SELECT system, external_id FROM external_ids WHERE mathematician = $1 ORDER BY system;",
+        row.id,
+    )
+    .fetch_all(pool)
+    .await?;
+    if external_ids.is_empty() {
+        return Err(eyre!("{} lost its external ids", row.id));
+    }
+
+    Ok(Some(Linked {
+        mathematician: Listing {
+            id: Id(row.id),
+            name: row.name,
+            school: row.school,
+            year: row.year.and_then(|year| Year::try_from(year).ok()),
+        },
+        external_ids,
+    }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use sqlx::Executor;
+
+    const MAPPING: &str = "mgp_id,system,external_id
+92443,Wikidata,Q1000001
+92443,wikidata,Q1000001
+15957, wikidata ,Q1000002
+15957,wikidata,Q1000003
+6807,wikidata,Q1000002
+6807,orcid,0000-0002-1825-0097
+0,wikidata,Q1000004
+6807,wikidata,not-a-qid
+6807,the web,x
+";
+
+    async fn stored(pool: &PgPool) -> Vec<(i32, String, String)> {
+        sqlx::query_as(
+            "SELECT mathematician, system, external_id FROM external_ids ORDER BY mathematician, system",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn mappings_are_validated_and_deduplicated(pool: PgPool) {
+        let preview = import_external_ids(&pool, MAPPING.as_bytes(), true)
+            .await
+            .unwrap();
+        assert!(stored(&pool).await.is_empty());
+
+        let import = import_external_ids(&pool, MAPPING.as_bytes(), false)
+            .await
+            .unwrap();
+        assert_eq!(import, preview);
+        assert_eq!(import.added, 3);
+        assert_eq!(import.duplicates, 1);
+        let rejected: Vec<_> = import.rejected.iter().map(|(line, _)| *line).collect();
+        assert_eq!(rejected, [5, 6, 8, 9, 10]);
+        assert_eq!(
+            import.rejected[0].1,
+            "15957 has the wikidata id Q1000002 already"
+        );
+        assert_eq!(
+            import.rejected[1].1,
+            "wikidata Q1000002 names 15957 already"
+        );
+        assert_eq!(
+            stored(&pool).await,
+            [
+                (6807, "orcid".to_string(), "0000-0002-1825-0097".to_string()),
+                (15957, "wikidata".to_string(), "Q1000002".to_string()),
+                (92443, "wikidata".to_string(), "Q1000001".to_string()),
+            ]
+        );
+
+        // a later file can change a mathematician's id, not take one another has
+        let later = "mgp_id,system,external_id
+92443,wikidata,Q1000005
+6807,wikidata,Q1000002
+6807,orcid,0000-0002-1825-0097
+";
+        let import = import_external_ids(&pool, later.as_bytes(), false)
+            .await
+            .unwrap();
+        assert_eq!((import.changed, import.unchanged), (1, 1));
+        assert_eq!(
+            import.rejected,
+            [(
+                3,
+                "wikidata Q1000002 is stored for 15957 already".to_string()
+            )]
+        );
+    }
+
+    #[sqlx::test]
+    async fn mathematicians_are_found_by_external_id(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians (id, name) VALUES (92443, 'Rajesh Pereira');
+            INSERT INTO external_ids (mathematician, system, external_id) VALUES
+                (92443, 'wikidata', 'Q1000001'), (92443, 'orcid', '0000-0002-1825-0097'),
+                (1, 'wikidata', 'Q1000002');",
+        )
+        .await
+        .unwrap();
+
+        let linked = find_by_external_id(&pool, "Wikidata", "Q1000001")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(linked.mathematician.id, Id(92443));
+        assert_eq!(
+            linked.to_string(),
+            "Rajesh Pereira (92443)\n  orcid: 0000-0002-1825-0097\n  wikidata: Q1000001\n"
+        );
+
+        // mapped, but not stored
+        assert_eq!(
+            find_by_external_id(&pool, "wikidata", "Q1000002")
+                .await
+                .unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use crate::parser::ScrapeRecord;
+
+/// Names a field of a record must or mustn't be one of, compared ignoring case
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct NameList {
+    /// When not empty, only these are admitted
+    pub allow: Vec<String>,
+
+    /// Never admitted, even when allowed
+    pub deny: Vec<String>,
+}
+
+impl NameList {
+    fn is_empty(&self) -> bool {
+        self.allow.is_empty() && self.deny.is_empty()
+    }
+
+    /// Whether `name` passes, `None` when the record doesn't have one and the list has a say
+    fn admits(&self, name: Option<&str>) -> Option<bool> {
+        if self.is_empty() {
+            return Some(true);
+        }
+        let name = name.map(str::trim).filter(|name| !name.is_empty())?;
+        // lowercased in full, plenty of schools aren't spelled in ASCII
+        let name = name.to_lowercase();
+        let listed = |list: &[String]| list.iter().any(|n| n.trim().to_lowercase() == name);
+
+        Some((self.allow.is_empty() || listed(&self.allow)) && !listed(&self.deny))
+    }
+}
+
+/// Which records get stored, by the school and country they graduated from, for building a
+/// subset of the graph such as only Canadian mathematicians
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordFilter {
+    pub schools: NameList,
+    pub countries: NameList,
+
+    /// Store records missing a school or country that a list would have to check, rather than
+    /// dropping them
+    pub keep_unknown: bool,
+}
+
+impl RecordFilter {
+    /// Why `record` shouldn't be stored, `None` if it should
+    pub fn rejection(&self, record: &ScrapeRecord) -> Option<String> {
+        let fields = [
+            ("school", &self.schools, record.school.as_deref()),
+            ("country", &self.countries, record.country.as_deref()),
+        ];
+
+        for (field, list, value) in fields {
+            match list.admits(value) {
+                Some(true) => {}
+                Some(false) => {
+                    let value = value.unwrap_or_default();
+                    return Some(format!("the {field} {value:?} is filtered out"));
+                }
+                None if self.keep_unknown => {}
+                None => return Some(format!("the {field} is unknown")),
+            }
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::StudentsSection;
+
+    fn record(school: Option<&str>, country: Option<&str>) -> ScrapeRecord {
+        ScrapeRecord {
+            name: "Rajesh Pereira".to_string(),
+            students: vec![],
+            students_section: StudentsSection::NoneKnown,
+            dissertations: vec![],
+            dissertation_year: None,
+            advisors: vec![],
+            school: school.map(str::to_string),
+            country: country.map(str::to_string),
+            year: None,
+            degree: None,
+            further_degrees: vec![],
+            contributor: None,
+            source_updated: None,
+        }
+    }
+
+    fn list(allow: &[&str], deny: &[&str]) -> NameList {
+        NameList {
+            allow: allow.iter().map(|n| n.to_string()).collect(),
+            deny: deny.iter().map(|n| n.to_string()).collect(),
+        }
+    }
+
+    #[test]
+    fn no_lists_admit_everything() {
+        let filter = RecordFilter::default();
+
+        assert_eq!(filter.rejection(&record(None, None)), None);
+        assert_eq!(
+            filter.rejection(&record(Some("University of Toronto"), Some("Canada"))),
+            None
+        );
+    }
+
+    #[test]
+    fn allowlists_admit_only_their_names() {
+        let filter = RecordFilter {
+            countries: list(&["canada"], &[]),
+            ..RecordFilter::default()
+        };
+
+        assert_eq!(filter.rejection(&record(None, Some("Canada"))), None);
+        assert!(filter
+            .rejection(&record(None, Some("United States")))
+            .is_some());
+    }
+
+    #[test]
+    fn names_are_compared_ignoring_case_beyond_ascii() {
+        let filter = RecordFilter {
+            schools: list(&["georg-august-universität göttingen"], &[]),
+            countries: list(&[], &["ÖSTERREICH"]),
+            ..RecordFilter::default()
+        };
+
+        assert_eq!(
+            filter.rejection(&record(
+                Some("Georg-August-Universität Göttingen"),
+                Some("Germany")
+            )),
+            None
+        );
+        assert!(filter
+            .rejection(&record(
+                Some("GEORG-AUGUST-UNIVERSITÄT GÖTTINGEN"),
+                Some("Österreich")
+            ))
+            .is_some());
+    }
+
+    #[test]
+    fn denylists_win_over_allowlists() {
+        let filter = RecordFilter {
+            schools: list(
+                &["University of Toronto", "McGill University"],
+                &["McGill University"],
+            ),
+            ..RecordFilter::default()
+        };
+
+        assert_eq!(
+            filter.rejection(&record(Some("University of Toronto"), None)),
+            None
+        );
+        assert!(filter
+            .rejection(&record(Some("McGill University"), None))
+            .is_some());
+    }
+
+    #[test]
+    fn unknown_fields_are_dropped_unless_kept() {
+        let mut filter = RecordFilter {
+            countries: list(&[], &["United States"]),
+            ..RecordFilter::default()
+        };
+        // the school isn't filtered on, so it may be missing either way
+        assert_eq!(
+            filter.rejection(&record(None, None)),
+            Some("the country is unknown".to_string())
+        );
+        assert_eq!(
+            filter.rejection(&record(None, Some(" "))),
+            filter.rejection(&record(None, None))
+        );
+
+        filter.keep_unknown = true;
+        assert_eq!(filter.rejection(&record(None, None)), None);
+        assert!(filter
+            .rejection(&record(None, Some("United States")))
+            .is_some());
+    }
+}
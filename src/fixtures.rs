@@ -0,0 +1,112 @@
+use std::fmt;
+use std::path::Path;
+
+use combi::parser;
+use combi::parser::ScrapeRecord;
+use scraper::Html;
+
+/// What the parser made of every page in a directory of fixtures, by file name
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct FixtureReport {
+    pub pages: Vec<(String, Result<ScrapeRecord, String>)>,
+    /// Records with fewer of the optional fields are flagged as empty
+    pub min_fields: usize,
+}
+
+impl FixtureReport {
+    pub fn failed(&self) -> usize {
+        self.pages.iter().filter(|(_, page)| page.is_err()).count()
+    }
+}
+
+/// Parses every `.html` file in `dir` with [`parser::scrape`], nothing is fetched or stored
+pub fn parse_dir(dir: &Path, min_fields: usize) -> color_eyre::Result<FixtureReport> {
+    let mut pages = vec![];
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|extension| extension != "html") {
+            continue;
+        }
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        let body = std::fs::read_to_string(&path)?;
+        let record = parser::scrape(&Html::parse_document(&body)).map_err(|e| e.to_string());
+        pages.push((name.into_owned(), record));
+    }
+    // the directory's order isn't stable, the report's has to be to diff it
+    pages.sort_by(|(a, _), (b, _)| a.cmp(b));
+
+    Ok(FixtureReport { pages, min_fields })
+}
+
+impl fmt::Display for FixtureReport {
+    /// A tab separated line per page with its status, the fields that have a single value and how
+    /// many there are of the rest, `-` for what the page doesn't have
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "file\tstatus\tname\tdegree\tschool\tyear\tcountry\tdissertations\tadvisors\tstudents"
+        )?;
+        for (file, page) in &self.pages {
+            let record = match page {
+                Ok(record) => record,
+                Err(e) => {
+                    writeln!(f, "{file}\tfailed: {e}")?;
+                    continue;
+                }
+            };
+            let status = if record.populated_fields() < self.min_fields {
+                "empty"
+            } else {
+                "ok"
+            };
+            let field = |value: Option<&str>| value.unwrap_or("-").to_string();
+            writeln!(
+                f,
+                "{file}\t{status}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                record.name,
+                field(record.degree.as_deref()),
+                field(record.school.as_deref()),
+                record
+                    .year
+                    .map_or("-".to_string(), |year| year.get().to_string()),
+                field(record.country.as_deref()),
+                record.dissertations.len(),
+                record.advisors.len(),
+                record.students.len(),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn every_page_gets_a_line_in_file_order() {
+        let dir = std::env::temp_dir().join(format!("combi-fixtures-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::copy("rajesh.html", dir.join("rajesh.html")).unwrap();
+        std::fs::copy("not-found.html", dir.join("a-missing-id.html")).unwrap();
+        std::fs::write(
+            dir.join("nobody.html"),
+            r#"<div id="mainContent"><h2>Nobody</h2></div>"#,
+        )
+        .unwrap();
+        std::fs::copy("rajesh.golden.json", dir.join("rajesh.golden.json")).unwrap();
+
+        let report = parse_dir(&dir, 1).unwrap();
+        assert_eq!(report.failed(), 1);
+        assert_eq!(
+            report.to_string(),
+            "file\tstatus\tname\tdegree\tschool\tyear\tcountry\tdissertations\tadvisors\tstudents\n\
+             a-missing-id.html\tfailed: the page says there is no record with the id\n\
+             nobody.html\tempty\tNobody\t-\t-\t-\t-\t0\t0\t0\n\
+             rajesh.html\tok\tRajesh Pereira\tPh.D.\tUniversity of Toronto\t2003\tCanada\t1\t1\t4\n"
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
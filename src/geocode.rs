@@ -0,0 +1,358 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::time::Duration;
+
+use color_eyre::eyre::eyre;
+use serde::Deserialize;
+use sqlx::PgPool;
+use tracing::info;
+use tracing::warn;
+
+use crate::query::normalize_school;
+
+/// OpenStreetMap's Nominatim, which asks for at most a request a second
+pub const NOMINATIM: &str = "https://nominatim.openstreetmap.org/search";
+
+/// Where a place is, in degrees
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Coordinates {
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+/// Looks places up by name
+#[async_trait::async_trait]
+pub trait Geocoder: Send + Sync + fmt::Debug {
+    /// Where `place` is, `None` when the geocoder doesn't know it
+    async fn locate(&self, place: &str) -> color_eyre::Result<Option<Coordinates>>;
+}
+
+/// A Nominatim instance, searched with its `/search` endpoint at `endpoint`
+#[derive(Debug)]
+pub struct Nominatim {
+    client: reqwest::Client,
+    endpoint: String,
+}
+
+impl Nominatim {
+    /// The client has to send a user agent naming the application, Nominatim refuses requests
+    /// without one
+    pub fn new(client: reqwest::Client, endpoint: String) -> Self {
+        Self { client, endpoint }
+    }
+}
+
+/// The fields of a search result that are used, Nominatim sends the coordinates as strings
+#[derive(Debug, Deserialize)]
+struct Place {
+    lat: String,
+    lon: String,
+}
+
+#[async_trait::async_trait]
+impl Geocoder for Nominatim {
+    async fn locate(&self, place: &str) -> color_eyre::Result<Option<Coordinates>> {
+        let body = self
+            .client
+            .get(&self.endpoint)
+            .query(&[("q", place), ("format", "jsonv2"), ("limit", "1")])
+            .send()
+            .await?
+            .error_for_status()?
+            .text()
+            .await?;
+        let places: Vec<Place> = serde_json::from_str(&body)?;
+        let Some(place) = places.first() else {
+            return Ok(None);
+        };
+
+        let degrees = |value: &str| {
+            value
+                .parse::<f64>()
+                .map_err(|e| eyre!("Nominatim sent a coordinate of {value:?}: {e}"))
+        };
+        Ok(Some(Coordinates {
+            latitude: degrees(&place.lat)?,
+            longitude: degrees(&place.lon)?,
+        }))
+    }
+}
+
+/// What [`geocode_schools`] did
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Geocoding {
+    /// Schools stored with coordinates
+    pub located: u64,
+    /// Schools the geocoder didn't know, stored without
+    pub unresolved: u64,
+    /// Schools whose lookup failed, left to the next run
+    pub failed: u64,
+    /// Schools answered by an earlier lookup of the same place rather than the geocoder
+    pub reused: u64,
+}
+
+impl fmt::Display for Geocoding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "located:    {}", self.located)?;
+        writeln!(f, "unresolved: {}", self.unresolved)?;
+        writeln!(f, "failed:     {}", self.failed)?;
+        writeln!(f, "reused:     {}", self.reused)
+    }
+}
+
+/// A school and the country it's located in, if it has a single one
+struct School {
+    id: i32,
+    name: String,
+    country: Option<String>,
+}
+
+impl School {
+    /// What the geocoder is asked for, the country narrows down schools of common names
+    fn place(&self) -> String {
+        match &self.country {
+            Some(country) => format!("{}, {country}", self.name),
+            None => self.name.clone(),
+        }
+    }
+
+    /// Spellings of the same school in the same country are the same place
+    fn key(&self) -> (String, Option<String>) {
+        (normalize_school(&self.name), self.country.clone())
+    }
+}
+
+/// Stores coordinates for up to `limit` schools that have none stored yet, also the ones the
+/// geocoder didn't know before with `retry_unresolved`. Requests are `gap` apart to stay within the
+/// geocoder's rate limit, and every answer, stored ones included, is reused for other spellings of
+/// the same school so that each place is only looked up once. A school the geocoder doesn't know is
+/// stored without coordinates, one whose lookup failed isn't stored at all
+pub async fn geocode_schools(
+    pool: &PgPool,
+    geocoder: &dyn Geocoder,
+    gap: Duration,
+    limit: Option<i64>,
+    retry_unresolved: bool,
+) -> color_eyre::Result<Geocoding> {
+    // a school's country is only used when it's located in a single one
+    let located = sqlx::query!(
+        r#"SELECT s.id, s.name,
+            (SELECT min(c.name) FROM school_locations l
+                JOIN countries c ON c.id = l.country
+                WHERE l.school = s.id
+                HAVING count(*) = 1) AS country,
+            g.latitude, g.longitude
+        FROM school_coordinates g
+        JOIN schools s ON s.id = g.school
+        WHERE g.latitude IS NOT NULL OR NOT $1;"#,
+        retry_unresolved,
+    )
+    .fetch_all(pool)
+    .await?;
+    let mut answers: HashMap<_, Option<Coordinates>> = located
+        .into_iter()
+        .map(|row| {
+            let school = School {
+                id: row.id,
+                name: row.name,
+                country: row.country,
+            };
+            let coordinates = row
+                .latitude
+                .zip(row.longitude)
+                .map(|(latitude, longitude)| Coordinates {
+                    latitude,
+                    longitude,
+                });
+            (school.key(), coordinates)
+        })
+        .collect();
+
+    let schools = sqlx::query_as!(
+        School,
+        r#"SELECT s.id, s.name,
+            (SELECT min(c.name) FROM school_locations l
+                JOIN countries c ON c.id = l.country
+                WHERE l.school = s.id
+                HAVING count(*) = 1) AS country
+        FROM schools s
+        LEFT JOIN school_coordinates g ON g.school = s.id
+        WHERE g.school IS NULL OR ($1 AND g.latitude IS NULL)
+        ORDER BY s.id
+        LIMIT $2;"#,
+        retry_unresolved,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+    info!("Geocoding {} schools", schools.len());
+
+    let mut geocoding = Geocoding::default();
+    let mut asked = false;
+    for school in schools {
+        let coordinates = match answers.get(&school.key()) {
+            Some(coordinates) => {
+                geocoding.reused += 1;
+                *coordinates
+            }
+            None => {
+                if asked {
+                    tokio::time::sleep(gap).await;
+                }
+                asked = true;
+                match geocoder.locate(&school.place()).await {
+                    Ok(coordinates) => {
+                        answers.insert(school.key(), coordinates);
+                        coordinates
+                    }
+                    Err(e) => {
+                        warn!("Failed to geocode {:?}: {e}", school.name);
+                        geocoding.failed += 1;
+                        continue;
+                    }
+                }
+            }
+        };
+
+        match coordinates {
+            Some(_) => geocoding.located += 1,
+            None => {
+                info!("The geocoder doesn't know {:?}", school.place());
+                geocoding.unresolved += 1;
+            }
+        }
+        sqlx::query!(
+            r"INSERT INTO school_coordinates (school, latitude, longitude) VALUES ($1, $2, $3)
+            ON CONFLICT (school) DO UPDATE
+            SET latitude = EXCLUDED.latitude, longitude = EXCLUDED.longitude, geocoded_at = now();",
+            school.id,
+            coordinates.map(|c| c.latitude),
+            coordinates.map(|c| c.longitude),
+        )
+        .execute(pool)
+        .await?;
+    }
+
+    Ok(geocoding)
+}
+
+#[cfg(test)]
+mod test {
+    use sqlx::Executor;
+    use wiremock::matchers::method;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    use super::*;
+
+    async fn nominatim() -> (MockServer, Nominatim) {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .and(query_param("q", "University of Toronto, Canada"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(
+                r#"[{"place_id": 1, "lat": "43.6629", "lon": "-79.3957", "name": "University of Toronto"}]"#,
+            ))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .and(query_param("q", "Universität Nirgendwo"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("[]"))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/search"))
+            .and(query_param("q", "Overloaded University"))
+            .respond_with(ResponseTemplate::new(429))
+            .mount(&server)
+            .await;
+
+        let geocoder = Nominatim::new(reqwest::Client::new(), format!("{}/search", server.uri()));
+        (server, geocoder)
+    }
+
+    #[tokio::test]
+    async fn nominatim_reads_the_first_result() {
+        let (_server, geocoder) = nominatim().await;
+        assert_eq!(
+            geocoder
+                .locate("University of Toronto, Canada")
+                .await
+                .unwrap(),
+            Some(Coordinates {
+                latitude: 43.6629,
+                longitude: -79.3957,
+            })
+        );
+        assert_eq!(
+            geocoder.locate("Universität Nirgendwo").await.unwrap(),
+            None
+        );
+        assert!(geocoder.locate("Overloaded University").await.is_err());
+    }
+
+    #[sqlx::test]
+    async fn schools_are_geocoded_once_each(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO schools (name) VALUES
+                ('University of Toronto'),
+                ('The University of Toronto'),
+                ('Universität Nirgendwo'),
+                ('Overloaded University');
+            INSERT INTO countries (name) VALUES ('Canada');
+            INSERT INTO school_locations (school, country) VALUES (1, 1), (2, 1);",
+        )
+        .await
+        .unwrap();
+        let (server, geocoder) = nominatim().await;
+
+        let geocoding = geocode_schools(&pool, &geocoder, Duration::ZERO, None, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            geocoding,
+            Geocoding {
+                located: 2,
+                unresolved: 1,
+                failed: 1,
+                reused: 1,
+            }
+        );
+        // the second spelling of Toronto reused the first's answer
+        assert_eq!(server.received_requests().await.unwrap().len(), 3);
+
+        let stored: Vec<(i32, Option<f64>)> =
+            sqlx::query_as("SELECT school, latitude FROM school_coordinates ORDER BY school")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(stored, [(1, Some(43.6629)), (2, Some(43.6629)), (3, None)]);
+
+        // only the failed lookup is tried again, the unknown school only when asked to
+        let geocoding = geocode_schools(&pool, &geocoder, Duration::ZERO, None, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            geocoding,
+            Geocoding {
+                failed: 1,
+                ..Geocoding::default()
+            }
+        );
+        let geocoding = geocode_schools(&pool, &geocoder, Duration::ZERO, Some(1), true)
+            .await
+            .unwrap();
+        assert_eq!(
+            geocoding,
+            Geocoding {
+                unresolved: 1,
+                ..Geocoding::default()
+            }
+        );
+        assert_eq!(server.received_requests().await.unwrap().len(), 5);
+    }
+}
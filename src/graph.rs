@@ -0,0 +1,957 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use async_trait::async_trait;
+use sqlx::PgPool;
+
+use crate::mathematician::Listing;
+use crate::parser::Id;
+use crate::parser::Year;
+
+/// Counts everyone descended from `id` through `advisor_relations`, i.e. their students, their
+/// students' students and so on, each counted once however many advisors lead to them
+pub async fn descendant_count(pool: &PgPool, id: Id) -> color_eyre::Result<i64> {
+    // UNION rather than UNION ALL drops rows already found, so a cycle in the relations (the site
+    // has a few) stops the recursion instead of looping forever
+    let count = sqlx::query_scalar!(
+        r#"WITH RECURSIVE descendants(id) AS (
+            SELECT advisee FROM advisor_relations WHERE advisor = $1
+            UNION
+            SELECT r.advisee FROM advisor_relations r
+            JOIN descendants d ON r.advisor = d.id
+        )
+        SELECT COUNT(*) AS "count!" FROM descendants WHERE id <> $1;"#,
+        id as Id,
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(count)
+}
+
+/// Rows [`store_descendant_counts`] inserts per statement, a few hundred kilobytes of ids
+const COUNT_BATCH: usize = 50_000;
+
+/// Replaces the stored descendant counts with `counts`, e.g. [`Graph::all_descendant_counts`], in
+/// one transaction so that readers see either the old counts or all of the new ones. Returns how
+/// many were stored
+pub async fn store_descendant_counts(
+    pool: &PgPool,
+    counts: &[(Id, i64)],
+) -> color_eyre::Result<usize> {
+    let mut transaction = pool.begin().await?;
+    // not TRUNCATE, which would hold off readers until the commit
+    sqlx::query!("DELETE FROM descendant_counts;")
+        .execute(&mut *transaction)
+        .await?;
+    for batch in counts.chunks(COUNT_BATCH) {
+        let ids: Vec<i32> = batch.iter().map(|(id, _)| id.0).collect();
+        let descendants = batch
+            .iter()
+            .map(|&(_, count)| i32::try_from(count))
+            .collect::<Result<Vec<_>, _>>()?;
+        sqlx::query!(
+            r"INSERT INTO descendant_counts(mathematician, descendants)
+            SELECT * FROM UNNEST($1::integer[], $2::integer[]);",
+            &ids,
+            &descendants,
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+    transaction.commit().await?;
+
+    Ok(counts.len())
+}
+
+/// The `n` advisors with the most direct students, most first and ties broken by name, each with
+/// the graduation of their first degree. A count far above the rest usually means the parser
+/// attached someone else's students
+pub async fn top_advisors(pool: &PgPool, n: i64) -> color_eyre::Result<Vec<(Listing, i64)>> {
+    let rows = sqlx::query!(
+        r#"SELECT t.id, t.name, t.students AS "students!", g.school AS "school?", g.year AS "year?"
+        FROM (
+            SELECT m.id, m.name, COUNT(*) AS students FROM advisor_relations r
+            JOIN mathematicians m ON m.id = r.advisor
+            GROUP BY m.id, m.name
+            ORDER BY COUNT(*) DESC, m.name, m.id
+            LIMIT $1
+        ) t
+        LEFT JOIN LATERAL (
+            SELECT s.name AS school, g.year FROM graduation_records g
+            JOIN schools s ON s.id = g.school
+            WHERE g.mathematician = t.id
+            ORDER BY g.position, g.year, s.name
+            LIMIT 1
+        ) g ON true
+        ORDER BY t.students DESC, t.name, t.id;"#,
+        n,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let advisor = Listing {
+                id: Id(row.id),
+                name: row.name,
+                school: row.school,
+                year: row.year.and_then(|year| Year::try_from(year).ok()),
+            };
+            (advisor, row.students)
+        })
+        .collect())
+}
+
+/// Every group of mathematicians that are each other's descendants through `advisor_relations`,
+/// which only errors in the data produce. A group is everyone on one or more overlapping cycles,
+/// sorted by id, and the groups are sorted by their smallest id
+pub async fn detect_cycles(pool: &PgPool) -> color_eyre::Result<Vec<Vec<Id>>> {
+    Ok(load_graph(pool).await?.cycles())
+}
+
+/// The questions the analysis commands ask of the advisor relations, answered either by queries
+/// against the database or from a [`Graph`] loaded into memory once
+#[async_trait]
+pub trait AdvisorGraph: Send + Sync {
+    /// See [`descendant_count`]
+    async fn descendant_count(&self, id: Id) -> color_eyre::Result<i64>;
+
+    /// See [`detect_cycles`]
+    async fn detect_cycles(&self) -> color_eyre::Result<Vec<Vec<Id>>>;
+}
+
+/// Answers with a query per question, for when the relations don't fit in memory or only one
+/// question is asked
+#[derive(Debug, Clone)]
+pub struct SqlGraph {
+    pool: PgPool,
+}
+
+impl SqlGraph {
+    pub fn new(pool: PgPool) -> Self {
+        Self { pool }
+    }
+}
+
+#[async_trait]
+impl AdvisorGraph for SqlGraph {
+    async fn descendant_count(&self, id: Id) -> color_eyre::Result<i64> {
+        descendant_count(&self.pool, id).await
+    }
+
+    async fn detect_cycles(&self) -> color_eyre::Result<Vec<Vec<Id>>> {
+        detect_cycles(&self.pool).await
+    }
+}
+
+/// Marks a mathematician [`Graph::cycles`] hasn't reached yet
+const UNVISITED: u32 = u32::MAX;
+
+/// Mathematicians [`Graph::all_descendant_counts`] looks for among everyone's descendants at a
+/// time, which keeps the bitsets of the whole site at a few tens of megabytes
+const COUNT_BLOCK: usize = 1024;
+
+/// All of `advisor_relations` in compressed sparse row form: the students of the mathematician at
+/// position `i` of `ids` are the positions `students[offsets[i]..offsets[i + 1]]`. Positions are
+/// `u32` to keep the whole site, a few hundred thousand mathematicians, at a few megabytes
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Graph {
+    /// Everyone that is an advisor or advisee, sorted
+    ids: Vec<i32>,
+    offsets: Vec<u32>,
+    students: Vec<u32>,
+}
+
+/// Reads every advisor relation into a [`Graph`], one query for everything the analysis commands
+/// would otherwise ask with a recursive query each
+pub async fn load_graph(pool: &PgPool) -> color_eyre::Result<Graph> {
+    let relations = sqlx::query!("SELECT advisor, advisee FROM advisor_relations;")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .map(|row| (row.advisor, row.advisee))
+        .collect::<Vec<_>>();
+
+    Ok(Graph::from_relations(&relations))
+}
+
+impl Graph {
+    /// The graph of `(advisor, advisee)` pairs
+    pub fn from_relations(relations: &[(i32, i32)]) -> Self {
+        let mut ids: Vec<i32> = relations.iter().flat_map(|&(a, b)| [a, b]).collect();
+        ids.sort_unstable();
+        ids.dedup();
+        let position = |id| ids.binary_search(&id).unwrap() as u32;
+
+        let mut edges: Vec<(u32, u32)> = relations
+            .iter()
+            .map(|&(advisor, advisee)| (position(advisor), position(advisee)))
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+
+        let mut offsets = vec![0; ids.len() + 1];
+        for &(advisor, _) in &edges {
+            offsets[advisor as usize + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+
+        Self {
+            students: edges.into_iter().map(|(_, advisee)| advisee).collect(),
+            offsets,
+            ids,
+        }
+    }
+
+    /// How many mathematicians have an advisor or a student
+    pub fn len(&self) -> usize {
+        self.ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    fn position(&self, id: Id) -> Option<u32> {
+        self.ids.binary_search(&id.0).ok().map(|i| i as u32)
+    }
+
+    fn students_of(&self, position: u32) -> &[u32] {
+        let position = position as usize;
+        &self.students[self.offsets[position] as usize..self.offsets[position + 1] as usize]
+    }
+
+    /// Same as [`descendant_count`], without a query
+    pub fn count_descendants(&self, id: Id) -> i64 {
+        let Some(root) = self.position(id) else {
+            return 0;
+        };
+
+        let mut seen = vec![false; self.len()];
+        seen[root as usize] = true;
+        let mut pending = vec![root];
+        let mut count = 0;
+        while let Some(advisor) = pending.pop() {
+            for &student in self.students_of(advisor) {
+                if !std::mem::replace(&mut seen[student as usize], true) {
+                    count += 1;
+                    pending.push(student);
+                }
+            }
+        }
+        count
+    }
+
+    /// [`Graph::count_descendants`] of everyone in the graph, in id order. A walk from everyone
+    /// would go over the same students again and again, so instead every mathematician's
+    /// descendants are built from their students' as bitsets, students first, for a block of
+    /// [`COUNT_BLOCK`] descendants at a time. That's at most relations times mathematicians / 64
+    /// word operations whatever the genealogy's shape. Everyone on a cycle has the same
+    /// descendants, the others on it included, so the bitsets are kept per component
+    pub fn all_descendant_counts(&self) -> Vec<(Id, i64)> {
+        const WORDS: usize = COUNT_BLOCK / 64;
+
+        let component_of = self.components();
+        let components = component_of.iter().max().map_or(0, |&c| c as usize + 1);
+        // the relations between components, which only lead to lower numbered ones
+        let mut students: Vec<Vec<u32>> = vec![vec![]; components];
+        for advisor in 0..self.len() as u32 {
+            let component = component_of[advisor as usize];
+            for &student in self.students_of(advisor) {
+                let theirs = component_of[student as usize];
+                if theirs != component {
+                    students[component as usize].push(theirs);
+                }
+            }
+        }
+        for theirs in &mut students {
+            theirs.sort_unstable();
+            theirs.dedup();
+        }
+
+        // in blocks of components, since none of the components before a block can reach it
+        let mut by_component: Vec<u32> = (0..self.len() as u32).collect();
+        by_component.sort_by_key(|&position| component_of[position as usize]);
+
+        // everyone reaches themselves through their component, which isn't one of theirs
+        let mut reached = vec![-1; components];
+        let mut bits = vec![0u64; components * WORDS];
+        for block in by_component.chunks(COUNT_BLOCK) {
+            let first = component_of[block[0] as usize] as usize;
+            bits[first * WORDS..].fill(0);
+            for (member, &position) in block.iter().enumerate() {
+                let component = component_of[position as usize] as usize;
+                bits[component * WORDS + member / 64] |= 1 << (member % 64);
+            }
+            for component in first..components {
+                let (done, rest) = bits.split_at_mut(component * WORDS);
+                let own = &mut rest[..WORDS];
+                // the bits of the components before the block are left over from the last one
+                let theirs = &students[component];
+                let before = theirs.partition_point(|&theirs| (theirs as usize) < first);
+                for &theirs in &theirs[before..] {
+                    let theirs = &done[theirs as usize * WORDS..][..WORDS];
+                    for (word, their) in own.iter_mut().zip(theirs) {
+                        *word |= their;
+                    }
+                }
+                reached[component] += own.iter().map(|w| i64::from(w.count_ones())).sum::<i64>();
+            }
+        }
+
+        self.ids
+            .iter()
+            .zip(&component_of)
+            .map(|(&id, &component)| (Id(id), reached[component as usize]))
+            .collect()
+    }
+
+    /// The strongly connected component of every mathematician, found with Tarjan's algorithm on
+    /// an explicit stack since chains of students run deeper than the call stack allows. The
+    /// components are numbered in the order they're completed, so a relation never leads from a
+    /// component to one with a higher number
+    fn components(&self) -> Vec<u32> {
+        let mut index = vec![UNVISITED; self.len()];
+        let mut low = vec![0; self.len()];
+        let mut on_stack = vec![false; self.len()];
+        let mut stack = vec![];
+        let mut component_of = vec![UNVISITED; self.len()];
+        let mut components = 0;
+        let mut visited = 0;
+
+        for root in 0..self.len() as u32 {
+            if index[root as usize] != UNVISITED {
+                continue;
+            }
+
+            // the mathematicians being visited, each with the position of the next student to visit
+            let mut visiting = vec![(root, 0)];
+            while let Some((node, next)) = visiting.pop() {
+                let n = node as usize;
+                if next == 0 {
+                    index[n] = visited;
+                    low[n] = visited;
+                    visited += 1;
+                    stack.push(node);
+                    on_stack[n] = true;
+                }
+
+                if let Some(&student) = self.students_of(node).get(next) {
+                    visiting.push((node, next + 1));
+                    if index[student as usize] == UNVISITED {
+                        visiting.push((student, 0));
+                    } else if on_stack[student as usize] {
+                        low[n] = low[n].min(index[student as usize]);
+                    }
+                    continue;
+                }
+
+                if low[n] == index[n] {
+                    loop {
+                        let member = stack.pop().unwrap();
+                        on_stack[member as usize] = false;
+                        component_of[member as usize] = components;
+                        if member == node {
+                            break;
+                        }
+                    }
+                    components += 1;
+                }
+                if let Some(&(advisor, _)) = visiting.last() {
+                    let advisor = advisor as usize;
+                    low[advisor] = low[advisor].min(low[n]);
+                }
+            }
+        }
+
+        component_of
+    }
+
+    /// Same as [`detect_cycles`], the strongly connected components of more than one
+    /// mathematician
+    pub fn cycles(&self) -> Vec<Vec<Id>> {
+        let mut members: HashMap<u32, Vec<i32>> = HashMap::new();
+        for (position, component) in self.components().into_iter().enumerate() {
+            members
+                .entry(component)
+                .or_default()
+                .push(self.ids[position]);
+        }
+
+        let mut cycles: Vec<Vec<i32>> = members
+            .into_values()
+            .filter(|members| members.len() > 1)
+            .collect();
+        for cycle in &mut cycles {
+            cycle.sort_unstable();
+        }
+        cycles.sort_unstable();
+        cycles
+            .into_iter()
+            .map(|cycle| cycle.into_iter().map(Id).collect())
+            .collect()
+    }
+
+    /// The number of relations on the longest chain of advisors and students. Everyone on a cycle
+    /// counts as a single generation, so the chain can't go round it forever
+    fn max_depth(&self) -> u32 {
+        let component_of = self.components();
+        let components = component_of.iter().max().map_or(0, |&c| c as usize + 1);
+        let mut members: Vec<Vec<u32>> = vec![vec![]; components];
+        for (position, &component) in component_of.iter().enumerate() {
+            members[component as usize].push(position as u32);
+        }
+
+        // students are in lower numbered components, so theirs are known by the time it's needed
+        let mut height = vec![0; components];
+        for component in 0..components {
+            for &member in &members[component] {
+                for &student in self.students_of(member) {
+                    let theirs = component_of[student as usize] as usize;
+                    if theirs != component {
+                        height[component] = height[component].max(height[theirs] + 1);
+                    }
+                }
+            }
+        }
+        height.into_iter().max().unwrap_or(0)
+    }
+
+    /// The number of mathematicians in the largest group connected by relations in either
+    /// direction
+    fn largest_component(&self) -> usize {
+        fn root(parent: &mut [u32], mut node: u32) -> u32 {
+            while parent[node as usize] != node {
+                parent[node as usize] = parent[parent[node as usize] as usize];
+                node = parent[node as usize];
+            }
+            node
+        }
+
+        let mut parent: Vec<u32> = (0..self.len() as u32).collect();
+        for advisor in 0..self.len() as u32 {
+            for &student in self.students_of(advisor) {
+                let (a, b) = (root(&mut parent, advisor), root(&mut parent, student));
+                parent[a.max(b) as usize] = a.min(b);
+            }
+        }
+
+        let mut sizes = vec![0; self.len()];
+        for node in 0..self.len() as u32 {
+            sizes[root(&mut parent, node) as usize] += 1;
+        }
+        sizes.into_iter().max().unwrap_or(0)
+    }
+
+    /// An overview of the shape of the genealogy
+    pub fn stats(&self) -> GraphStats {
+        let mut has_advisor = vec![false; self.len()];
+        for &student in &self.students {
+            has_advisor[student as usize] = true;
+        }
+
+        let mut students: Vec<usize> = (0..self.len() as u32)
+            .map(|advisor| self.students_of(advisor).len())
+            .filter(|&students| students > 0)
+            .collect();
+        students.sort_unstable();
+        let median_students = match students.len() {
+            0 => 0.0,
+            n if n % 2 == 1 => students[n / 2] as f64,
+            n => (students[n / 2 - 1] + students[n / 2]) as f64 / 2.0,
+        };
+
+        GraphStats {
+            mathematicians: self.len(),
+            relations: self.students.len(),
+            roots: has_advisor.iter().filter(|&&has| !has).count(),
+            leaves: self.len() - students.len(),
+            max_depth: self.max_depth(),
+            mean_students: match students.len() {
+                0 => 0.0,
+                advisors => self.students.len() as f64 / advisors as f64,
+            },
+            median_students,
+            largest_component: self.largest_component(),
+        }
+    }
+
+    /// `root` and their students as a tree down to `max_depth` relations below it, without names.
+    /// A student with several advisors in the subtree appears under each of them
+    pub fn subtree(&self, root: Id, max_depth: u32) -> SubtreeNode {
+        match self.position(root) {
+            Some(position) => self.subtree_from(position, max_depth, &mut vec![]),
+            None => SubtreeNode::new(root),
+        }
+    }
+
+    /// The tree below `node`, with `ancestors` the positions on the way down to it. Recursing is
+    /// fine as the depth is capped at [`MAX_SUBTREE_DEPTH`]
+    fn subtree_from(&self, node: u32, depth: u32, ancestors: &mut Vec<u32>) -> SubtreeNode {
+        let mut tree = SubtreeNode::new(Id(self.ids[node as usize]));
+        let students = self.students_of(node);
+        if students.is_empty() {
+            return tree;
+        }
+        if depth == 0 {
+            tree.truncated = true;
+            return tree;
+        }
+
+        ancestors.push(node);
+        for &student in students {
+            if ancestors.contains(&student) {
+                let mut cut = SubtreeNode::new(Id(self.ids[student as usize]));
+                cut.cycle = true;
+                tree.children.push(cut);
+            } else {
+                tree.children
+                    .push(self.subtree_from(student, depth - 1, ancestors));
+            }
+        }
+        ancestors.pop();
+        tree
+    }
+}
+
+/// The deepest a subtree can be exported, which bounds the recursion building it. The longest
+/// chains of the genealogy are a few dozen generations
+pub const MAX_SUBTREE_DEPTH: u32 = 100;
+
+/// A mathematician and their students nested below them, the shape tidy tree layouts such as
+/// D3's `d3.hierarchy` take
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)]
+pub struct SubtreeNode {
+    pub id: Id,
+    /// `None` for ids only known from a relation
+    pub name: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SubtreeNode>,
+    /// The mathematician is also an ancestor of this node, so the branch is cut here rather than
+    /// repeated forever. Only errors in the data produce cycles, see [`detect_cycles`]
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub cycle: bool,
+    /// The mathematician has students that were left out, being as deep as the tree goes
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub truncated: bool,
+}
+
+impl SubtreeNode {
+    fn new(id: Id) -> Self {
+        Self {
+            id,
+            name: None,
+            children: vec![],
+            cycle: false,
+            truncated: false,
+        }
+    }
+
+    /// How many nodes the tree has, a mathematician appearing more than once counting each time
+    pub fn node_count(&self) -> usize {
+        1 + self
+            .children
+            .iter()
+            .map(SubtreeNode::node_count)
+            .sum::<usize>()
+    }
+
+    fn ids(&self, ids: &mut Vec<i32>) {
+        ids.push(self.id.0);
+        for child in &self.children {
+            child.ids(ids);
+        }
+    }
+
+    fn name_from(&mut self, names: &HashMap<i32, String>) {
+        self.name = names.get(&self.id.0).cloned();
+        for child in &mut self.children {
+            child.name_from(names);
+        }
+    }
+}
+
+/// The subtree of `root` down to `max_depth` relations as [`Graph::subtree`] builds it from every
+/// stored relation, with the names of the stored mathematicians filled in
+pub async fn load_subtree(
+    pool: &PgPool,
+    root: Id,
+    max_depth: u32,
+) -> color_eyre::Result<SubtreeNode> {
+    let mut tree = load_graph(pool).await?.subtree(root, max_depth);
+
+    let mut ids = vec![];
+    tree.ids(&mut ids);
+    ids.sort_unstable();
+    ids.dedup();
+    let names = sqlx::query!(
+        "SELECT id, name FROM mathematicians WHERE id = ANY($1);",
+        &ids
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(|row| (row.id, row.name))
+    .collect();
+    tree.name_from(&names);
+
+    if tree.name.is_none() && tree.children.is_empty() {
+        return Err(color_eyre::eyre::eyre!(
+            "{root} isn't stored and has no relations"
+        ));
+    }
+    Ok(tree)
+}
+
+/// The shape of the genealogy, over the mathematicians with at least one advisor or student
+#[derive(Debug, Clone, PartialEq, serde::Serialize)]
+pub struct GraphStats {
+    pub mathematicians: usize,
+    pub relations: usize,
+    /// Mathematicians without an advisor
+    pub roots: usize,
+    /// Mathematicians without students
+    pub leaves: usize,
+    /// Relations on the longest chain of advisors and students, see [`Graph::stats`]
+    pub max_depth: u32,
+    /// Students per advisor, over those with at least one
+    pub mean_students: f64,
+    pub median_students: f64,
+    /// Mathematicians in the largest group connected by relations
+    pub largest_component: usize,
+}
+
+impl fmt::Display for GraphStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "mathematicians:       {}", self.mathematicians)?;
+        writeln!(f, "relations:            {}", self.relations)?;
+        writeln!(f, "roots:                {}", self.roots)?;
+        writeln!(f, "leaves:               {}", self.leaves)?;
+        writeln!(f, "max depth:            {}", self.max_depth)?;
+        writeln!(
+            f,
+            "students per advisor: {:.2} mean, {} median",
+            self.mean_students, self.median_students
+        )?;
+        writeln!(f, "largest component:    {}", self.largest_component)
+    }
+}
+
+#[async_trait]
+impl AdvisorGraph for Graph {
+    async fn descendant_count(&self, id: Id) -> color_eyre::Result<i64> {
+        Ok(self.count_descendants(id))
+    }
+
+    async fn detect_cycles(&self) -> color_eyre::Result<Vec<Vec<Id>>> {
+        Ok(self.cycles())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use sqlx::Executor;
+
+    async fn relate(pool: &PgPool, relations: &[(i32, i32)]) {
+        for &(advisor, advisee) in relations {
+            sqlx::query("INSERT INTO advisor_relations(advisor, advisee) VALUES ($1, $2);")
+                .bind(advisor)
+                .bind(advisee)
+                .execute(pool)
+                .await
+                .unwrap();
+        }
+    }
+
+    #[sqlx::test]
+    async fn no_students_means_no_descendants(pool: PgPool) {
+        assert_eq!(descendant_count(&pool, Id(1)).await.unwrap(), 0);
+    }
+
+    #[sqlx::test]
+    async fn counts_transitive_students_once(pool: PgPool) {
+        // 4 is a student of both 2 and 3, who are both students of 1
+        relate(&pool, &[(1, 2), (1, 3), (2, 4), (3, 4), (4, 5)]).await;
+
+        assert_eq!(descendant_count(&pool, Id(1)).await.unwrap(), 4);
+        assert_eq!(descendant_count(&pool, Id(2)).await.unwrap(), 2);
+        assert_eq!(descendant_count(&pool, Id(5)).await.unwrap(), 0);
+    }
+
+    #[sqlx::test]
+    async fn cycles_terminate_and_exclude_the_root(pool: PgPool) {
+        relate(&pool, &[(1, 2), (2, 3), (3, 1)]).await;
+
+        assert_eq!(descendant_count(&pool, Id(1)).await.unwrap(), 2);
+    }
+
+    #[test]
+    fn cycles_are_grouped_and_sorted() {
+        // 2 -> 3 -> 2 and 5 -> 6 -> 7 -> 5 -> 8 -> 6 hang off an acyclic tree rooted at 1
+        let relations = [
+            (1, 2),
+            (1, 4),
+            (2, 3),
+            (3, 2),
+            (4, 7),
+            (7, 5),
+            (5, 6),
+            (6, 7),
+            (5, 8),
+            (8, 6),
+            (8, 9),
+        ];
+        assert_eq!(
+            Graph::from_relations(&relations).cycles(),
+            [vec![Id(2), Id(3)], vec![Id(5), Id(6), Id(7), Id(8)]]
+        );
+
+        assert!(Graph::from_relations(&[(1, 2), (1, 3), (2, 4), (3, 4)])
+            .cycles()
+            .is_empty());
+    }
+
+    #[test]
+    fn long_chains_dont_overflow_the_stack() {
+        let mut relations: Vec<_> = (1..200_000).map(|id| (id, id + 1)).collect();
+        assert_eq!(Graph::from_relations(&relations).stats().max_depth, 199_999);
+        relations.push((200_000, 1));
+        assert_eq!(Graph::from_relations(&relations).cycles()[0].len(), 200_000);
+    }
+
+    #[sqlx::test]
+    async fn cycles_are_found_in_the_stored_relations(pool: PgPool) {
+        relate(&pool, &[(1, 2), (2, 3), (3, 1), (3, 4)]).await;
+        assert_eq!(
+            detect_cycles(&pool).await.unwrap(),
+            [vec![Id(1), Id(2), Id(3)]]
+        );
+    }
+
+    #[test]
+    fn the_graph_is_stored_as_rows_of_students() {
+        let graph = Graph::from_relations(&[(10, 30), (10, 20), (20, 30), (10, 20)]);
+        assert_eq!(
+            graph,
+            Graph {
+                ids: vec![10, 20, 30],
+                offsets: vec![0, 2, 3, 3],
+                students: vec![1, 2, 2],
+            }
+        );
+        assert!(Graph::from_relations(&[]).is_empty());
+    }
+
+    #[test]
+    fn stats_describe_the_shape() {
+        // 1 and 9 are roots, 4 -> 5 -> 6 -> 4 is a cycle below 1, and 9 -> 10 stands apart
+        let graph = Graph::from_relations(&[
+            (1, 2),
+            (1, 3),
+            (1, 4),
+            (2, 7),
+            (4, 5),
+            (5, 6),
+            (6, 4),
+            (6, 8),
+            (9, 10),
+        ]);
+        assert_eq!(
+            graph.stats(),
+            GraphStats {
+                mathematicians: 10,
+                relations: 9,
+                roots: 2,
+                leaves: 4,
+                // 1 -> the cycle -> 8
+                max_depth: 2,
+                mean_students: 9.0 / 6.0,
+                median_students: 1.0,
+                largest_component: 8,
+            }
+        );
+
+        let empty = Graph::default().stats();
+        assert_eq!(empty.max_depth, 0);
+        assert_eq!(empty.mean_students, 0.0);
+    }
+
+    #[sqlx::test]
+    async fn both_graphs_give_the_same_answers(pool: PgPool) {
+        let relations = [
+            (1, 2),
+            (1, 3),
+            (2, 4),
+            (3, 4),
+            (4, 5),
+            (5, 6),
+            (6, 4),
+            (7, 8),
+        ];
+        relate(&pool, &relations).await;
+        let sql = SqlGraph::new(pool.clone());
+        let memory = load_graph(&pool).await.unwrap();
+        assert_eq!(memory, Graph::from_relations(&relations));
+
+        for id in 0..=9 {
+            assert_eq!(
+                memory.descendant_count(Id(id)).await.unwrap(),
+                sql.descendant_count(Id(id)).await.unwrap(),
+                "descendants of {id}"
+            );
+        }
+        assert_eq!(
+            memory.detect_cycles().await.unwrap(),
+            sql.detect_cycles().await.unwrap()
+        );
+    }
+
+    #[test]
+    fn every_count_is_the_same_as_counting_alone() {
+        // 2 -> 3 -> 2 is a cycle under 1, 5 has two advisors and 7 -> 8 is apart
+        let relations = [
+            (1, 2),
+            (2, 3),
+            (3, 2),
+            (3, 4),
+            (2, 5),
+            (4, 5),
+            (5, 6),
+            (7, 8),
+        ];
+        let graph = Graph::from_relations(&relations);
+
+        let counts = graph.all_descendant_counts();
+        let ids: Vec<_> = counts.iter().map(|&(id, _)| id).collect();
+        assert_eq!(ids, (1..=8).map(Id).collect::<Vec<_>>());
+        for (id, count) in counts {
+            assert_eq!(count, graph.count_descendants(id), "descendants of {id}");
+        }
+        assert_eq!(
+            graph.all_descendant_counts()[..3],
+            [(Id(1), 5), (Id(2), 4), (Id(3), 4)]
+        );
+
+        // a few blocks, with second advisors and a cycle back to the top
+        let mut relations: Vec<_> = (2..3_000).map(|id| (id / 2, id)).collect();
+        relations.extend((10..3_000).step_by(5).map(|id| (id - 7, id)));
+        relations.push((2_500, 3));
+        let graph = Graph::from_relations(&relations);
+        for (id, count) in graph.all_descendant_counts() {
+            assert_eq!(count, graph.count_descendants(id), "descendants of {id}");
+        }
+    }
+
+    #[sqlx::test]
+    async fn stored_counts_are_replaced_as_a_whole(pool: PgPool) {
+        let stored = || async {
+            sqlx::query_as::<_, (i32, i32)>(
+                "SELECT mathematician, descendants FROM descendant_counts ORDER BY mathematician;",
+            )
+            .fetch_all(&pool)
+            .await
+            .unwrap()
+        };
+
+        let counts = Graph::from_relations(&[(1, 2), (2, 3)]).all_descendant_counts();
+        assert_eq!(store_descendant_counts(&pool, &counts).await.unwrap(), 3);
+        assert_eq!(stored().await, [(1, 2), (2, 1), (3, 0)]);
+
+        // more than a batch, and 1 isn't in the relations anymore
+        let relations: Vec<_> = (3..COUNT_BATCH as i32 + 10).map(|id| (2, id)).collect();
+        let counts = Graph::from_relations(&relations).all_descendant_counts();
+        store_descendant_counts(&pool, &counts).await.unwrap();
+        let stored = stored().await;
+        assert_eq!(stored.len(), COUNT_BATCH + 8);
+        assert_eq!(stored[0], (2, COUNT_BATCH as i32 + 7));
+        assert_eq!(stored[1], (3, 0));
+    }
+
+    #[sqlx::test]
+    async fn top_advisors_are_ordered_by_students_then_name(pool: PgPool) {
+        sqlx::query(
+            "INSERT INTO mathematicians(id, name) VALUES (1, 'Zed'), (2, 'Abe'), (3, 'Max');",
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+        relate(&pool, &[(1, 4), (1, 5), (2, 6), (2, 7), (3, 8)]).await;
+
+        let top = top_advisors(&pool, 2).await.unwrap();
+        let top: Vec<_> = top.iter().map(|(m, n)| (m.name.as_str(), *n)).collect();
+        assert_eq!(top, [("Abe", 2), ("Zed", 2)]);
+
+        assert_eq!(top_advisors(&pool, 10).await.unwrap().len(), 3);
+    }
+
+    #[sqlx::test]
+    async fn top_advisors_of_the_same_name_can_be_told_apart(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES (1, 'John Smith'), (2, 'John Smith');
+            INSERT INTO schools(name) VALUES ('Harvard University');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES (1, 1, 1950);",
+        )
+        .await
+        .unwrap();
+        relate(&pool, &[(1, 3), (1, 4), (2, 5)]).await;
+
+        let top: Vec<_> = top_advisors(&pool, 2)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(advisor, _)| advisor.to_string())
+            .collect();
+        assert_eq!(
+            top,
+            ["John Smith (1, Harvard University 1950)", "John Smith (2)"]
+        );
+    }
+
+    #[test]
+    fn subtrees_cut_cycles_and_stop_at_their_depth() {
+        // 4 is wrongly recorded as 1's advisor, and 5 has a student of their own
+        let graph = Graph::from_relations(&[(1, 2), (1, 3), (2, 4), (4, 1), (3, 5), (5, 6)]);
+
+        let tree = serde_json::to_value(graph.subtree(Id(1), 3)).unwrap();
+        assert_eq!(
+            tree,
+            serde_json::json!({"id": 1, "name": null, "children": [
+                {"id": 2, "name": null, "children": [
+                    {"id": 4, "name": null, "children": [
+                        {"id": 1, "name": null, "cycle": true},
+                    ]},
+                ]},
+                {"id": 3, "name": null, "children": [
+                    {"id": 5, "name": null, "children": [{"id": 6, "name": null}]},
+                ]},
+            ]})
+        );
+
+        let shallow = graph.subtree(Id(1), 1);
+        assert_eq!(shallow.node_count(), 3);
+        assert!(shallow.children.iter().all(|child| child.truncated));
+        assert_eq!(graph.subtree(Id(7), 3), SubtreeNode::new(Id(7)));
+    }
+
+    #[sqlx::test]
+    async fn loaded_subtrees_have_names(pool: PgPool) {
+        pool.execute("INSERT INTO mathematicians(id, name) VALUES (1, 'Gauss'), (2, 'Gerling');")
+            .await
+            .unwrap();
+        relate(&pool, &[(1, 2), (1, 3)]).await;
+
+        let tree = load_subtree(&pool, Id(1), 5).await.unwrap();
+        assert_eq!(tree.name.as_deref(), Some("Gauss"));
+        let names: Vec<_> = tree.children.iter().map(|c| c.name.as_deref()).collect();
+        // 3 is only known as a student
+        assert_eq!(names, [Some("Gerling"), None]);
+
+        assert!(load_subtree(&pool, Id(4), 5).await.is_err());
+    }
+}
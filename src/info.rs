@@ -0,0 +1,294 @@
+use std::fmt;
+
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+/// Every table the migrations create, in the order they're reported
+pub const TABLES: &[&str] = &[
+    "mathematicians",
+    "advisor_relations",
+    "dissertations",
+    "dissertation_advisors",
+    "graduation_records",
+    "schools",
+    "school_locations",
+    "countries",
+    "canonical_id_aliases",
+    "scrape_progress",
+    "scrape_timings",
+    "fetched_students",
+    "scrape_failures",
+    "field_updates",
+    "scrape_runs",
+    "parse_failures",
+    "school_coordinates",
+    "external_ids",
+    "descendant_counts",
+];
+
+/// A summary of a database for checking on an instance
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct DbInfo {
+    /// The latest applied migration as `(version, description)`, `None` before any were applied
+    pub migration: Option<(i64, String)>,
+    pub server_version: String,
+    /// The size of the whole database, human readable
+    pub size: String,
+    /// Rows per table, `None` for tables that don't exist yet
+    pub row_counts: Vec<(&'static str, Option<i64>)>,
+}
+
+pub async fn db_info(pool: &PgPool) -> color_eyre::Result<DbInfo> {
+    let migration = sqlx::query_as::<_, (i64, String)>(
+        "SELECT version, description FROM _sqlx_migrations WHERE success ORDER BY version DESC LIMIT 1;",
+    )
+    .fetch_optional(pool)
+    .await
+    .or_else(|e| match &e {
+        // the migrations table only exists once something was applied
+        sqlx::Error::Database(db) if db.code().as_deref() == Some("42P01") => Ok(None),
+        _ => Err(e),
+    })?;
+
+    let (server_version, size) = sqlx::query_as::<_, (String, String)>(
+        "SELECT current_setting('server_version'), pg_size_pretty(pg_database_size(current_database()));",
+    )
+    .fetch_one(pool)
+    .await?;
+
+    let mut row_counts = vec![];
+    for &table in TABLES {
+        let exists: bool = sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL;")
+            .bind(table)
+            .fetch_one(pool)
+            .await?;
+
+        let count = match exists {
+            true => Some(
+                sqlx::query_scalar(&format!("SELECT COUNT(*) FROM {table};"))
+                    .fetch_one(pool)
+                    .await?,
+            ),
+            false => None,
+        };
+        row_counts.push((table, count));
+    }
+
+    Ok(DbInfo {
+        migration,
+        server_version,
+        size,
+        row_counts,
+    })
+}
+
+/// The tables of [`TABLES`] that don't exist in the database
+pub async fn missing_tables(pool: &PgPool) -> sqlx::Result<Vec<&'static str>> {
+    let missing: Vec<String> =
+        sqlx::query_scalar("SELECT t FROM unnest($1::text[]) t WHERE to_regclass(t) IS NULL;")
+            .bind(TABLES)
+            .fetch_all(pool)
+            .await?;
+
+    Ok(TABLES
+        .iter()
+        .copied()
+        .filter(|table| missing.iter().any(|m| m == table))
+        .collect())
+}
+
+/// Fails unless every table the scraper writes to exists, so a database that wasn't migrated is
+/// reported once up front rather than by every query of a run
+pub async fn ensure_schema(pool: &PgPool) -> color_eyre::Result<()> {
+    let missing = missing_tables(pool).await?;
+    if missing.is_empty() {
+        return Ok(());
+    }
+    Err(eyre!(
+        "the database is missing the tables {}, apply the migrations with `sqlx migrate run` \
+        against it first, `combi config-check` shows which are pending",
+        missing.join(", ")
+    ))
+}
+
+/// The DDL of every migration in order, i.e. the schema a fully migrated database has. Embedded
+/// at build time, so it can't drift from the migrations
+pub fn schema() -> String {
+    let mut ddl = String::new();
+    for migration in sqlx::migrate!().iter() {
+        ddl.push_str(&format!(
+            "-- {} {}\n{}\n",
+            migration.version,
+            migration.description,
+            migration.sql.trim_end()
+        ));
+        ddl.push('\n');
+    }
+    ddl
+}
+
+/// The JSON Schema of a [`ScrapeRecord`](crate::parser::ScrapeRecord) as `show --format json`
+/// prints it and the sinks publish it, generated from the types so it follows their serde
+/// attributes
+pub fn record_json_schema() -> serde_json::Value {
+    schemars::schema_for!(crate::parser::ScrapeRecord).to_value()
+}
+
+impl DbInfo {
+    /// The same summary as the text output, with the row counts keyed by table and `null` for
+    /// tables that don't exist yet
+    pub fn to_json(&self) -> serde_json::Value {
+        let migration = self.migration.as_ref().map(|(version, description)| {
+            serde_json::json!({ "version": version, "description": description })
+        });
+        let row_counts: serde_json::Map<_, _> = self
+            .row_counts
+            .iter()
+            .map(|(table, count)| (table.to_string(), serde_json::json!(count)))
+            .collect();
+
+        serde_json::json!({
+            "migration": migration,
+            "server_version": self.server_version,
+            "size": self.size,
+            "row_counts": row_counts,
+        })
+    }
+}
+
+impl fmt::Display for DbInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let migration = match &self.migration {
+            Some((version, description)) => format!("{version} ({description})"),
+            None => "none applied".to_string(),
+        };
+        writeln!(f, "{:<24}{migration}", "schema version")?;
+        writeln!(f, "{:<24}{}", "postgres", self.server_version)?;
+        writeln!(f, "{:<24}{}", "database size", self.size)?;
+        writeln!(f)?;
+
+        writeln!(f, "{:<24}{:>12}", "table", "rows")?;
+        for (table, count) in &self.row_counts {
+            match count {
+                Some(count) => writeln!(f, "{table:<24}{count:>12}")?,
+                None => writeln!(f, "{table:<24}{:>12}", "missing")?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[sqlx::test]
+    async fn db_info_counts_rows(pool: PgPool) {
+        sqlx::query("INSERT INTO mathematicians(id, name) VALUES (1, 'A'), (2, 'B');")
+            .execute(&pool)
+            .await
+            .unwrap();
+
+        let info = db_info(&pool).await.unwrap();
+        assert!(info.migration.is_some());
+        assert_eq!(info.row_counts.len(), TABLES.len());
+        assert_eq!(info.row_counts[0], ("mathematicians", Some(2)));
+        assert_eq!(info.row_counts[1], ("advisor_relations", Some(0)));
+
+        let table = info.to_string();
+        assert!(table.contains("mathematicians"));
+        assert!(table.lines().any(|l| l.starts_with("schema version")));
+
+        let json = info.to_json();
+        assert_eq!(json["row_counts"]["mathematicians"], 2);
+        assert!(json["migration"]["version"].is_i64());
+    }
+
+    #[sqlx::test(migrations = false)]
+    async fn an_unmigrated_database_is_reported_up_front(pool: PgPool) {
+        assert_eq!(missing_tables(&pool).await.unwrap(), TABLES);
+
+        let e = ensure_schema(&pool).await.unwrap_err().to_string();
+        assert!(e.contains("missing the tables mathematicians, "), "{e}");
+        assert!(e.contains("sqlx migrate run"), "{e}");
+    }
+
+    #[sqlx::test]
+    async fn a_migrated_database_has_every_table(pool: PgPool) {
+        assert!(missing_tables(&pool).await.unwrap().is_empty());
+        ensure_schema(&pool).await.unwrap();
+    }
+
+    #[sqlx::test]
+    async fn tables_are_the_tables_the_migrations_create(pool: PgPool) {
+        let mut created: Vec<String> = sqlx::query_scalar(
+            r"SELECT table_name::text FROM information_schema.tables
+            WHERE table_schema = current_schema() AND table_type = 'BASE TABLE'
+                AND table_name <> '_sqlx_migrations';",
+        )
+        .fetch_all(&pool)
+        .await
+        .unwrap();
+        created.sort();
+
+        let mut tables = TABLES.to_vec();
+        tables.sort();
+        assert_eq!(created, tables);
+    }
+
+    #[test]
+    fn schema_creates_every_table_in_order() {
+        let schema = schema();
+        for table in TABLES {
+            assert!(
+                schema.contains(&format!("CREATE TABLE {table}"))
+                    || schema.contains(&format!("CREATE TABLE IF NOT EXISTS {table}")),
+                "{table}"
+            );
+        }
+
+        let initial = schema.find("-- 1 initial").unwrap();
+        let timings = schema.find("-- 10 scrape timings").unwrap();
+        assert!(initial < timings);
+    }
+
+    #[test]
+    fn the_record_schema_describes_every_serialized_field() {
+        let schema = record_json_schema();
+        let page = std::fs::read_to_string("rajesh.html").unwrap();
+        let record = serde_json::to_value(crate::parser::parse_record(&page).unwrap()).unwrap();
+
+        let fields: Vec<_> = record.as_object().unwrap().keys().collect();
+        let described: Vec<_> = schema["properties"].as_object().unwrap().keys().collect();
+        assert_eq!(fields, described);
+        assert_eq!(
+            schema["properties"]["students_section"]["$ref"],
+            "#/$defs/StudentsSection"
+        );
+
+        let year = &schema["$defs"]["Year"];
+        assert_eq!(year["minimum"], crate::parser::Year::MIN);
+        assert_eq!(year["maximum"], crate::parser::Year::MAX);
+    }
+
+    #[test]
+    fn display_marks_missing_tables() {
+        let info = DbInfo {
+            migration: None,
+            server_version: "16.2".to_string(),
+            size: "8 MB".to_string(),
+            row_counts: vec![("mathematicians", None)],
+        };
+
+        assert_eq!(
+            info.to_string(),
+            "schema version          none applied\n\
+             postgres                16.2\n\
+             database size           8 MB\n\
+             \n\
+             table                           rows\n\
+             mathematicians               missing\n"
+        );
+    }
+}
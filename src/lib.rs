@@ -0,0 +1,30 @@
+pub mod auth;
+pub mod bulk;
+pub mod cache;
+pub mod diff;
+pub mod drift;
+pub mod error;
+pub mod export;
+pub mod external;
+pub mod filter;
+#[cfg(feature = "geocode")]
+pub mod geocode;
+pub mod graph;
+pub mod info;
+pub mod mathematician;
+pub mod names;
+pub mod parser;
+pub mod qa;
+pub mod query;
+pub mod records;
+pub mod rename;
+pub mod repair;
+pub mod sanitize;
+pub mod scrape;
+pub mod selftest;
+pub mod sink;
+pub mod source;
+pub mod sqlite;
+pub mod store;
+#[cfg(feature = "tor")]
+pub mod tor;
@@ -1,459 +1,521 @@
-#![allow(dead_code)]
-
-mod mathematician;
-mod parser;
-
+mod check;
+mod cli;
+mod fixtures;
+
+use clap::Parser;
+use cli::Cli;
+use cli::Command;
+use cli::OutputFormat;
+use cli::SchemaFormat;
 use color_eyre::eyre::eyre;
-use mathematician::Country;
-use mathematician::Dissertation;
-use mathematician::GraduationRecord;
-use mathematician::Mathematician;
-use mathematician::School;
-use rand_distr::Distribution;
-use rand_distr::Uniform;
-use reqwest::Client;
-use scraper::Html;
-use sqlx::PgConnection;
-use sqlx::Postgres;
-use sqlx::Transaction;
-use std::fmt::Debug;
-use std::sync::Arc;
+use combi::drift;
+use combi::graph::AdvisorGraph;
+use combi::graph::SqlGraph;
+use combi::qa;
+use combi::rename;
+use combi::scrape::log_pool_stats;
+use combi::scrape::resolve_locations;
+use combi::scrape::scrape_all;
+use combi::scrape::Databases;
+use combi::scrape::Scraper;
+use combi::scrape::ScraperConfig;
+#[cfg(feature = "geocode")]
+use combi::scrape::DEFAULT_USER_AGENT;
+use combi::selftest;
+#[cfg(feature = "geocode")]
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::time::Instant;
 use tracing::debug;
-use tracing::error;
 use tracing::info;
-use tracing::instrument;
+use tracing::level_filters::LevelFilter;
 use tracing::warn;
+use tracing_subscriber::EnvFilter;
 
-#[instrument(skip(executor))]
-async fn insert_school<'a, E>(executor: E, school: &School) -> color_eyre::Result<()>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let _ = sqlx::query!(
-        "INSERT INTO schools(name) VALUES ($1) ON CONFLICT DO NOTHING;",
-        school.name,
-    )
-    .execute(executor)
-    .await
-    .inspect_err(|e| {
-        error!("Failed to insert school name {e}");
-    })?;
-
-    Ok(())
-}
-
-#[instrument(skip(executor))]
-async fn insert_school_location<'a, E>(
-    executor: E,
-    school: &School,
-    country: &Country,
-) -> color_eyre::Result<()>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let _ = sqlx::query!(
-        "INSERT INTO school_locations(school, country) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
-        school.name,
-        country.name,
-    )
-    .execute(executor)
-    .await
-    .inspect_err(|e| {
-        error!("Failed to insert school location {e}");
-    })?;
-
-    debug!("school location inserted {} {}", school.name, country.name);
-    Ok(())
-}
-
-#[instrument(skip(executor))]
-async fn insert_country<'a, E>(executor: E, country: &Country) -> color_eyre::Result<()>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let _ = sqlx::query!(
-        "INSERT INTO countries(name) VALUES ($1) ON CONFLICT DO NOTHING;",
-        country.name,
-    )
-    .execute(executor)
-    .await
-    .inspect_err(|e| {
-        error!("Failed to insert country {e}");
-    })?;
-
-    Ok(())
-}
-
-#[instrument(skip(executor))]
-async fn insert_dissertation<'a, E>(
-    executor: E,
-    dissertation: &Dissertation,
-) -> color_eyre::Result<()>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let _ = sqlx::query!(
-        "INSERT INTO dissertations(title, author) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
-        dissertation.title,
-        dissertation.author.id.0
-    )
-    .execute(executor)
-    .await
-    .inspect_err(|e| {
-        error!("Failed to insert dissertation {e}");
-    })?;
-
+/// Prints `value` for `--format json`, as one pretty-printed document
+fn print_json(value: &impl serde::Serialize) -> color_eyre::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
     Ok(())
 }
 
-#[instrument(skip(executor))]
-async fn insert_adivsor_relation<'a, E>(
-    executor: E,
-    advisor: parser::Id,
-    advisee: parser::Id,
-) -> color_eyre::Result<()>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let _ = sqlx::query!(
-        "INSERT INTO advisor_relations(advisor, advisee) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
-        advisor.0,
-        advisee.0,
-    )
-    .execute(executor)
-    .await
-    .inspect_err(|e| {
-        error!("Failed to insert advisor relation: {e}");
-    })?;
-    Ok(())
-}
-
-#[instrument(skip(executor))]
-async fn insert_mathematician<'a, E>(
-    executor: E,
-    id: parser::Id,
-    name: impl AsRef<str> + Debug,
-) -> color_eyre::Result<()>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let _ = sqlx::query!(
-        "INSERT INTO mathematicians(id, name) VALUES ($1, $2) ON CONFLICT DO NOTHING;",
-        id.0,
-        name.as_ref(),
-    )
-    .execute(executor)
-    .await
-    .inspect_err(|e| {
-        error!("Failed to insert mathematician: {e}");
-    })?;
-
-    Ok(())
-}
-
-#[instrument(skip(executor))]
-async fn insert_relation<'a, E>(
-    executor: E,
-    advisor: parser::Id,
-    advisee: parser::Id,
-) -> color_eyre::Result<()>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let query = sqlx::query!(
-        r"INSERT INTO advisor_relations(advisor, advisee) VALUES ($1, $2);",
-        advisor.0,
-        advisee.0,
-    );
-    let _result = query.execute(executor).await.inspect_err(|e| {
-        error!("Failed to insert advisor relation: {e}");
-    })?;
-
-    Ok(())
-}
-
-#[instrument(skip(executor))]
-// TODO: figure out what the fuck am I suppose to do make executor a generic
-async fn insert_grad_record<E>(
-    executor: &mut PgConnection,
-    grad_record: &GraduationRecord,
-) -> color_eyre::Result<()> {
-    insert_school(&mut *executor, &grad_record.school).await?;
-    let _ = sqlx::query!(
-        "INSERT INTO graduation_records(mathematician, school, year) VALUES ($1, $2, $3) ON CONFLICT DO NOTHING;",
-        &grad_record.mathematician.id.0,
-        grad_record.school.name,
-        grad_record.year as i32)
-        .execute(&mut *executor)
-        .await
-        .inspect_err(|e| {
-            error!("Failed to insert graduation record {e}");
-        })?;
-
-    Ok(())
-}
-
-async fn has_mathematician<'a, E>(executor: E, id: parser::Id) -> color_eyre::Result<bool>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let query = sqlx::query!(
-        r"SELECT COUNT(1) FROM mathematicians WHERE id = $1 LIMIT 1;",
-        id.0
-    );
-    let result = query.fetch_one(executor).await?;
-
-    Ok(result.count == Some(1))
-}
-
-#[instrument(level = "debug", skip(executor))]
-async fn has_advisor_advisee<'a, E>(
-    executor: E,
-    advisor: parser::Id,
-    advisee: parser::Id,
-) -> color_eyre::Result<bool>
-where
-    E: sqlx::Executor<'a, Database = sqlx::Postgres>,
-{
-    let query = sqlx::query!(
-        r"SELECT COUNT(*) FROM advisor_relations WHERE advisor = $1 AND advisee = $2 LIMIT 1;",
-        advisor.0,
-        advisee.0,
-    );
-    let result = query.fetch_one(executor).await.inspect_err(|e| {
-        error!("Failed to find out about advisor relation {e}");
-    })?;
-
-    Ok(result.count == Some(1))
+/// Reads the variables of the `.env` file in the working directory, or the closest directory above
+/// it, into the environment, returning where it was found. Variables that are already set win over
+/// the file, and there not being a file isn't an error
+fn load_dotenv() -> color_eyre::Result<Option<std::path::PathBuf>> {
+    match dotenvy::dotenv() {
+        Ok(path) => Ok(Some(path)),
+        Err(e) if e.not_found() => Ok(None),
+        Err(e) => Err(eyre!("Failed to read .env: {e}")),
+    }
 }
 
-#[instrument(skip(transaction))]
-async fn insert_record<'a>(
-    mut transaction: Transaction<'a, Postgres>,
-    record: (parser::Id, &parser::ScrapeRecord),
-) -> color_eyre::Result<()> {
-    let advisor_id = record.0;
-    let advisor = record.1;
-
-    insert_mathematician(&mut *transaction, advisor_id, &advisor.name).await?;
-    debug!("mathematician inserted");
+#[tokio::main]
+async fn main() -> color_eyre::Result<()> {
+    // before anything reads the environment, e.g. RUST_LOG
+    let dotenv = load_dotenv();
+    let cli = Cli::parse();
+
+    // init tracing with fmt substribers, the command line wins over RUST_LOG
+    let filter = match cli.log_level() {
+        Some(level) => EnvFilter::default().add_directive(level.into()),
+        None => EnvFilter::builder()
+            .with_default_directive(LevelFilter::ERROR.into())
+            .from_env_lossy(),
+    };
+    // logs go to stderr so that what a command prints can be piped, e.g. its JSON
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .init();
 
-    if let Some(country) = &advisor.country {
-        let country = Country {
-            name: country.clone(),
-        };
-        insert_country(&mut *transaction, &country).await?;
-        debug!("country inserted");
+    color_eyre::install()?;
+    if let Some(path) = dotenv? {
+        debug!("Read the environment from {}", path.display());
     }
 
-    if let Some(dissertation) = &advisor.dissertation {
-        let dissertation = Dissertation {
-            title: dissertation.clone(),
-            author: Mathematician {
-                id: advisor_id,
-                name: advisor.name.clone(),
-            },
-        };
-
-        insert_dissertation(&mut *transaction, &dissertation).await?;
-        debug!("disseration inserted");
+    if let Some(Command::ExportSchema { format }) = cli.command {
+        match format {
+            SchemaFormat::Sql => print!("{}", combi::info::schema()),
+            SchemaFormat::JsonSchema => print_json(&combi::info::record_json_schema())?,
+        }
+        return Ok(());
     }
 
-    if let Some(school) = &advisor.school {
-        let school = School {
-            name: school.clone(),
-        };
-        insert_school(&mut *transaction, &school).await?;
-        debug!("school inserted");
+    if let Some(Command::Diff {
+        before,
+        after,
+        details,
+    }) = &cli.command
+    {
+        let before = combi::diff::Snapshot::read_dir(before)?;
+        let after = combi::diff::Snapshot::read_dir(after)?;
+        let diff = combi::diff::diff(&before, &after);
+        print!("{diff}");
+        if *details {
+            print!("{}", diff.details());
+        }
+        return Ok(());
+    }
 
-        // TODO: yikes, duplicate inserts
-        if let Some(country) = &advisor.country {
-            let country = Country {
-                name: country.clone(),
-            };
-            insert_school_location(&mut *transaction, &school, &country).await?;
-            debug!("school location inserted");
+    if let Some(Command::Show { id, format }) = cli.command {
+        let config = ScraperConfig::from(&cli.scrape);
+        let scraper = Scraper::new(Databases::unconnected(), config, vec![])?;
+        let (record, url) = scraper.fetch_record(id).await?;
+        info!("Fetched {url}");
+        match format {
+            OutputFormat::Text => print!("{record}"),
+            OutputFormat::Json => print_json(&record)?,
         }
+        return Ok(());
     }
 
-    let mathematician = Mathematician {
-        id: advisor_id,
-        name: advisor.name.clone(),
-    };
+    if let Some(Command::Qa { id }) = cli.command {
+        let config = ScraperConfig::from(&cli.scrape);
+        let scraper = Scraper::new(Databases::unconnected(), config, vec![])?;
+        print!("{}", qa::run(&scraper, id).await?);
+        return Ok(());
+    }
 
-    if let Some(school) = &advisor.school {
-        if let Some(year) = advisor.year {
-            let graduation_record = GraduationRecord {
-                mathematician,
-                school: School {
-                    name: school.clone(),
-                },
-                year: year as i32,
-            };
-            insert_grad_record::<PgConnection>(&mut *transaction, &graduation_record).await?;
-            debug!("grad record inserted");
+    if let Some(Command::SelfTest { sample }) = cli.command {
+        let config = ScraperConfig::from(&cli.scrape);
+        let scraper = Scraper::new(Databases::unconnected(), config, vec![])?;
+        let summary = selftest::run(&scraper, &selftest::random_ids(sample)).await;
+        print!("{summary}");
+        if !summary.broken.is_empty() {
+            return Err(eyre!("{} pages didn't parse", summary.broken.len()));
         }
+        return Ok(());
     }
 
-    for student in &advisor.students {
-        if let Some(student_id) = student.id {
-            insert_mathematician(&mut *transaction, student_id, &student.name).await?;
-            insert_adivsor_relation(&mut *transaction, advisor_id, student_id).await?;
-            debug!("adivsor avisee record inserted");
+    if let Some(Command::ParseFixtures { dir }) = &cli.command {
+        let report = fixtures::parse_dir(dir, cli.scrape.min_fields)?;
+        print!("{report}");
+        if report.failed() > 0 {
+            return Err(eyre!("{} fixtures didn't parse", report.failed()));
         }
+        return Ok(());
     }
 
-    transaction.commit().await?;
-    Ok(())
-}
+    if let Some(Command::ConfigCheck) = cli.command {
+        let config = ScraperConfig::from(&cli.scrape);
+        if let Err(failure) = check::run(&config).await {
+            std::process::exit(failure.exit_code());
+        }
+        return Ok(());
+    }
 
-#[derive(Debug)]
-struct Scraper {
-    db_pool: Arc<sqlx::Pool<sqlx::Postgres>>,
-    client: Client,
-}
+    let postgres_url = std::env::var("POSTGRES_URL").map_err(|_| {
+        eyre!(
+            "POSTGRES_URL is not set, set it to the database to scrape into, e.g. \
+            POSTGRES_URL=postgres://combi@localhost/combi, or put that line in a .env file"
+        )
+    })?;
+    let read_url = std::env::var("POSTGRES_READ_URL").ok();
+    let db = Databases::connect(&postgres_url, read_url.as_deref(), cli.statement_timeout).await?;
+    if let Some(every) = cli.pool_stats_every {
+        log_pool_stats(db.clone(), every, read_url.is_some());
+    }
+    // db-info is how to look at a database that isn't migrated yet
+    if !matches!(cli.command, Some(Command::DbInfo { .. })) {
+        combi::info::ensure_schema(&db.write).await?;
+    }
 
-impl Scraper {
-    #[instrument(skip(self))]
-    async fn get_page(&self, url: &str) -> color_eyre::Result<Html> {
-        async fn get_page(client: &Client, url: &str) -> color_eyre::Result<String> {
-            Ok(client.get(url).send().await?.text().await?)
+    match cli.command {
+        Some(Command::Export {
+            format,
+            since,
+            tables,
+            out,
+            bom,
+        }) => combi::export::export(&db.read, format, since, &tables, &out, bom).await,
+        Some(Command::Import { dir, tables }) => {
+            let tables = combi::export::select_tables(combi::info::TABLES, &tables)?;
+            combi::bulk::import(&db.write, &tables, &dir).await
         }
-
-        let mut retry = 3;
-        let page = loop {
-            if retry == 0 {
-                error!("Failed to get {url} after 3 tries");
-                return Err(eyre!("Failed to get {url}"));
+        Some(Command::Descendants {
+            id,
+            in_memory,
+            format,
+        }) => {
+            let graph: Box<dyn AdvisorGraph> = if in_memory {
+                Box::new(combi::graph::load_graph(&db.read).await?)
+            } else {
+                Box::new(SqlGraph::new(db.read.clone()))
+            };
+            let count = graph.descendant_count(id).await?;
+            match format {
+                OutputFormat::Text => println!("{count}"),
+                OutputFormat::Json => {
+                    print_json(&serde_json::json!({ "id": id, "descendants": count }))?
+                }
             }
-
-            match get_page(&self.client, &url).await {
-                Ok(page) => break page,
-                Err(e) => {
-                    debug!("Failed to get page: {e}");
-
-                    let factor = {
-                        let dist = Uniform::new(10.0, 30.0);
-                        let mut rng = rand::thread_rng();
-                        dist.sample(&mut rng)
-                    };
-
-                    let wait_duration = Duration::from_millis((1000. * factor) as u64);
-
-                    warn!("{url} Connection failed, waiting for {wait_duration:?}");
-                    sleep(wait_duration).await;
-                    retry -= 1;
+            Ok(())
+        }
+        Some(Command::CountDescendants) => {
+            let started = Instant::now();
+            let graph = combi::graph::load_graph(&db.read).await?;
+            let counts = graph.all_descendant_counts();
+            info!(
+                "Counted the descendants of {} mathematicians in {:?}",
+                counts.len(),
+                started.elapsed()
+            );
+
+            let started = Instant::now();
+            let stored = combi::graph::store_descendant_counts(&db.write, &counts).await?;
+            info!("Stored the counts in {:?}", started.elapsed());
+            println!("Stored the descendant counts of {stored} mathematicians");
+            Ok(())
+        }
+        Some(Command::TopAdvisors { n, format }) => {
+            let advisors = combi::graph::top_advisors(&db.read, n).await?;
+            match format {
+                OutputFormat::Text => {
+                    for (advisor, students) in advisors {
+                        println!("{students}\t{advisor}");
+                    }
+                }
+                OutputFormat::Json => {
+                    let advisors: Vec<_> = advisors
+                        .iter()
+                        .map(|(advisor, students)| {
+                            serde_json::json!({ "advisor": advisor, "students": students })
+                        })
+                        .collect();
+                    print_json(&advisors)?;
                 }
             }
-        };
-
-        Ok(Html::parse_document(&page))
-    }
-
-    #[instrument(skip(self))]
-    async fn scrape(&self, id: parser::Id) -> color_eyre::Result<()> {
-        //
-        // first see if the mathematician already exists
-        if has_mathematician(&*self.db_pool, id).await? {
-            return Ok(());
-        }
-        info!("Started scraping");
-
-        // scrape the page
-        let url = format!("https://www.mathgenealogy.org/id.php?id={}", id.0);
-
-        let advisor = {
-            let page = self.get_page(&url).await.inspect_err(|e| {
-                error!("Failed to get page: {e}");
-            })?;
-            let mut advisor = parser::scrape(&page).inspect_err(|e| {
-                error!("Failed to scrape page: {e}");
-            })?;
-
-            advisor
-        };
-        info!("Main mathematician scraped");
-        insert_mathematician(&*self.db_pool, id, &advisor.name).await?;
-
-        let mut advisees = vec![];
-        // visit all the students
-        for student in &advisor.students {
-            let Some(student_id) = student.id else {
-                continue;
+            Ok(())
+        }
+        Some(Command::AtSchool { school, format }) => {
+            let graduates = combi::query::mathematicians_at_school(&db.read, &school).await?;
+            match format {
+                OutputFormat::Text => {
+                    for graduate in &graduates {
+                        println!("{graduate}");
+                    }
+                }
+                OutputFormat::Json => print_json(&graduates)?,
+            }
+            Ok(())
+        }
+        Some(Command::ImportExternalIds { csv, dry_run }) => {
+            let file = std::fs::File::open(&csv)?;
+            let import = combi::external::import_external_ids(&db.write, file, dry_run).await?;
+            if dry_run {
+                println!("Dry run, nothing was changed");
+            }
+            print!("{import}");
+            Ok(())
+        }
+        Some(Command::LookupExternal {
+            system,
+            external_id,
+            format,
+        }) => {
+            let linked =
+                combi::external::find_by_external_id(&db.read, &system, &external_id).await?;
+            let Some(linked) = linked else {
+                return Err(eyre!("Nobody stored is {system} {external_id}"));
             };
-
-            if has_mathematician(&*self.db_pool, student_id).await?
-                && has_advisor_advisee(&*self.db_pool, id, student_id).await?
-            {
-                // if they're already in the database, skip
-                continue;
+            match format {
+                OutputFormat::Text => print!("{linked}"),
+                OutputFormat::Json => print_json(&linked)?,
             }
-
-            let url = format!("https://www.mathgenealogy.org/id.php?id={}", student_id.0);
-            let student_page = self.get_page(&url).await?;
-            let mut student = parser::scrape(&student_page)?;
-            info!("Student scraped {student:?}");
-
-            // we only explore one layer deep
-            advisees.push(student);
+            Ok(())
         }
-
-        info!("Started transaction");
-        let transaction = self.db_pool.begin().await?;
-        insert_record(transaction, (id, &advisor)).await?;
-        info!("Transaction committed");
-
-        Ok(())
-    }
-}
-
-#[tokio::main]
-async fn main() -> color_eyre::Result<()> {
-    // init tracing with fmt substribers
-    tracing_subscriber::fmt::init();
-
-    color_eyre::install()?;
-
-    let postgres_url = std::env::var(&"POSTGRES_URL").expect("POSTGRES_URL is not set");
-
-    let db_pool = sqlx::postgres::PgPoolOptions::new()
-        .max_connections(12)
-        .connect(&postgres_url)
-        .await?;
-    let pool = Arc::new(db_pool);
-
-    let client = reqwest::Client::new();
-
-    let scraper = Scraper {
-        db_pool: Arc::clone(&pool),
-        client,
-    };
-    let scraper = Arc::new(scraper);
-
-    let mut tasks = vec![];
-    // let mut rng = thread_rng();
-    // let dist = Uniform::new(0, 307384);
-
-    for id in 1..=307433 {
-        // let id = dist.sample(&mut rng);
-        let id = parser::Id(id);
-        let scraper = Arc::clone(&scraper);
-
-        if !has_mathematician(&*scraper.db_pool, id).await? {
-            let task = tokio::spawn(async move { scraper.scrape(id).await });
-
-            // sleep for 1 second
-            let sleep_duration = Duration::from_millis(700);
-            sleep(sleep_duration).await;
-            tasks.push(task);
+        Some(Command::SearchNames {
+            search,
+            limit,
+            format,
+        }) => {
+            let found = combi::query::search_names(&db.read, &search, limit).await?;
+            match format {
+                OutputFormat::Text => {
+                    for mathematician in &found {
+                        println!("{mathematician}");
+                    }
+                }
+                OutputFormat::Json => print_json(&found)?,
+            }
+            Ok(())
         }
+        Some(Command::Orphans { format }) => {
+            let orphans = combi::query::orphans(&db.read).await?;
+            match format {
+                OutputFormat::Text => {
+                    for orphan in &orphans {
+                        println!("{orphan}");
+                    }
+                }
+                OutputFormat::Json => print_json(&orphans)?,
+            }
+            Ok(())
+        }
+        Some(Command::RepairNames { dry_run, limit }) => {
+            let pool = db.write.clone();
+            let scraper = Scraper::new(db, ScraperConfig::from(&cli.scrape), vec![])?;
+            let repair = rename::repair_names(&pool, &scraper, limit, dry_run).await?;
+            if dry_run {
+                println!("Dry run, nothing was changed");
+            }
+            print!("{repair}");
+            Ok(())
+        }
+        Some(Command::DriftCheck { sample }) => {
+            let mut config = ScraperConfig::from(&cli.scrape);
+            // cached pages are what was stored, the live site is the point
+            config.page_cache = None;
+            let ids = drift::sample_stored_ids(&db.read, sample).await?;
+            let scraper = Scraper::new(db, config, vec![])?;
+            print!("{}", drift::run(&scraper, &ids).await?);
+            Ok(())
+        }
+        Some(Command::GraphStats { format }) => {
+            let stats = combi::graph::load_graph(&db.read).await?.stats();
+            match format {
+                OutputFormat::Text => print!("{stats}"),
+                OutputFormat::Json => print_json(&stats)?,
+            }
+            Ok(())
+        }
+        Some(Command::YearHistogram { width, format }) => {
+            let histogram = combi::query::year_histogram(&db.read, width).await?;
+            match format {
+                OutputFormat::Text => print!("{histogram}"),
+                OutputFormat::Json => print_json(&histogram)?,
+            }
+            Ok(())
+        }
+        Some(Command::Cycles { format }) => {
+            let cycles = combi::graph::detect_cycles(&db.read).await?;
+            match format {
+                OutputFormat::Text => {
+                    for cycle in &cycles {
+                        let ids: Vec<_> = cycle.iter().map(ToString::to_string).collect();
+                        println!("{}", ids.join(" "));
+                    }
+                }
+                OutputFormat::Json => print_json(&cycles)?,
+            }
+            if !cycles.is_empty() {
+                warn!("Found {} cycles in the advisor relations", cycles.len());
+            }
+            Ok(())
+        }
+        Some(Command::ExportSubtree { id, depth, out }) => {
+            let tree = combi::graph::load_subtree(&db.read, id, depth).await?;
+            match out {
+                Some(path) => {
+                    let file = std::io::BufWriter::new(std::fs::File::create(&path)?);
+                    serde_json::to_writer(file, &tree)?;
+                    info!(
+                        "Wrote {} mathematicians below {id} to {}",
+                        tree.node_count() - 1,
+                        path.display()
+                    );
+                }
+                None => print_json(&tree)?,
+            }
+            Ok(())
+        }
+        Some(Command::RebuildGraduationRecords { dry_run }) => {
+            let repair = combi::repair::rebuild_graduation_records(&db.write, dry_run).await?;
+            if dry_run {
+                println!("Dry run, nothing was changed");
+            }
+            print!("{repair}");
+            Ok(())
+        }
+        Some(Command::Prune { dry_run }) => {
+            let prune = combi::repair::prune(&db.write, dry_run).await?;
+            if dry_run {
+                println!("Dry run, nothing was changed");
+            }
+            print!("{prune}");
+            Ok(())
+        }
+        #[cfg(feature = "geocode")]
+        Some(Command::GeocodeSchools {
+            endpoint,
+            gap,
+            limit,
+            retry_unresolved,
+        }) => {
+            let client = reqwest::Client::builder()
+                .user_agent(DEFAULT_USER_AGENT)
+                .timeout(Duration::from_secs(30))
+                .build()?;
+            let geocoder = combi::geocode::Nominatim::new(client, endpoint);
+            let geocoding =
+                combi::geocode::geocode_schools(&db.write, &geocoder, gap, limit, retry_unresolved)
+                    .await?;
+            print!("{geocoding}");
+            Ok(())
+        }
+        Some(Command::ResolveLocations { table, dry_run }) => {
+            let resolution = resolve_locations(&db.write, table.as_deref(), dry_run).await?;
+            if dry_run {
+                println!("Dry run, nothing was changed");
+            }
+            print!("{resolution}");
+            Ok(())
+        }
+        Some(Command::Runs { format }) => {
+            let runs = combi::query::scrape_runs(&db.read).await?;
+            match format {
+                OutputFormat::Text => {
+                    for run in runs {
+                        let finished = match run.finished_at {
+                            Some(finished) => finished.to_rfc3339(),
+                            None => "unfinished".to_string(),
+                        };
+                        print!(
+                            "{}\t{}\t{}\t{finished}",
+                            run.id,
+                            run.label,
+                            run.started_at.to_rfc3339()
+                        );
+                        match run.stats {
+                            Some(stats) => println!(
+                                "\tscraped {}, unchanged {}, filtered {}, failed {} of which {} missing, downloaded {} bytes",
+                                stats.scraped,
+                                stats.unchanged,
+                                stats.filtered,
+                                stats.failed,
+                                stats.missing,
+                                stats.downloaded
+                            ),
+                            None => println!(),
+                        }
+                    }
+                }
+                OutputFormat::Json => print_json(&runs)?,
+            }
+            Ok(())
+        }
+        Some(Command::SlowestScrapes { n, format }) => {
+            let scrapes = combi::query::slowest_scrapes(&db.read, n).await?;
+            match format {
+                OutputFormat::Text => {
+                    for (id, timings) in scrapes {
+                        println!(
+                            "{id}\t{:?}\tfetch {:?}, parse {:?}, insert {:?}",
+                            timings.total(),
+                            timings.fetch,
+                            timings.parse,
+                            timings.insert
+                        );
+                    }
+                }
+                OutputFormat::Json => {
+                    let scrapes: Vec<_> = scrapes
+                        .iter()
+                        .map(|(id, timings)| {
+                            serde_json::json!({
+                                "id": id,
+                                "total_ms": timings.total().as_millis(),
+                                "fetch_ms": timings.fetch.as_millis(),
+                                "parse_ms": timings.parse.as_millis(),
+                                "insert_ms": timings.insert.as_millis(),
+                            })
+                        })
+                        .collect();
+                    print_json(&scrapes)?;
+                }
+            }
+            Ok(())
+        }
+        Some(Command::RescrapeMissing { field }) => {
+            let config = ScraperConfig {
+                rescrape_missing: Some(field),
+                ..ScraperConfig::from(&cli.scrape)
+            };
+            scrape_all(db, config).await
+        }
+        Some(Command::ScrapeTree {
+            id,
+            follow_descendants,
+        }) => {
+            let config = ScraperConfig {
+                tree_root: Some(id),
+                follow_descendants,
+                ..ScraperConfig::from(&cli.scrape)
+            };
+            scrape_all(db, config).await
+        }
+        Some(Command::ScrapeClosure { max_passes }) => {
+            let config = ScraperConfig {
+                closure_passes: Some(max_passes.get()),
+                ..ScraperConfig::from(&cli.scrape)
+            };
+            scrape_all(db, config).await
+        }
+        Some(Command::CompleteStudents) => {
+            let config = ScraperConfig {
+                complete_students: true,
+                ..ScraperConfig::from(&cli.scrape)
+            };
+            scrape_all(db, config).await
+        }
+        Some(Command::DbInfo { format }) => {
+            let info = combi::info::db_info(&db.read).await?;
+            match format {
+                OutputFormat::Text => print!("{info}"),
+                OutputFormat::Json => print_json(&info.to_json())?,
+            }
+            Ok(())
+        }
+        Some(
+            Command::ConfigCheck
+            | Command::ExportSchema { .. }
+            | Command::SelfTest { .. }
+            | Command::ParseFixtures { .. }
+            | Command::Show { .. }
+            | Command::Qa { .. }
+            | Command::Diff { .. },
+        ) => {
+            unreachable!("handled before connecting")
+        }
+        None => scrape_all(db, ScraperConfig::from(&cli.scrape)).await,
     }
-
-    for task in tasks {
-        let _ = task.await;
-    }
-
-    Ok(())
 }
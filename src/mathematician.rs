@@ -1,11 +1,18 @@
+use std::fmt;
+
 use sqlx::FromRow;
 
+use crate::parser::Advisor;
 use crate::parser::Id;
+use crate::parser::Year;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
 pub struct Dissertation {
     pub title: String,
     pub author: Mathematician,
+    pub year: Option<Year>,
+    /// The advisors, numbered as the page does with 1 for the primary advisor
+    pub advisors: Vec<Advisor>,
 }
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
@@ -14,11 +21,49 @@ pub struct Mathematician {
     pub name: String,
 }
 
+impl fmt::Display for Mathematician {
+    /// `Name (id)`, the id telling apart mathematicians of the same name
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.name, self.id)
+    }
+}
+
+/// A mathematician as searches and lookups list them, with the graduation of their first degree
+/// when there is one to tell apart mathematicians of the same name. `Mathematician` is the author
+/// of a dissertation or graduation record and can't carry the graduation itself without every
+/// record repeating it, so the lists load it into this instead
+#[derive(Debug, PartialEq, Eq, Hash, Clone, serde::Serialize)]
+pub struct Listing {
+    pub id: Id,
+    pub name: String,
+    pub school: Option<String>,
+    pub year: Option<Year>,
+}
+
+impl fmt::Display for Listing {
+    /// `Name (id, School Year)`, leaving out the school or year when there is none
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}", self.name, self.id)?;
+        match (&self.school, self.year) {
+            (Some(school), Some(year)) => write!(f, ", {school} {}", year.get())?,
+            (Some(school), None) => write!(f, ", {school}")?,
+            (None, Some(year)) => write!(f, ", {}", year.get())?,
+            (None, None) => {}
+        }
+        write!(f, ")")
+    }
+}
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
 pub struct School {
     pub name: String,
 }
 
+/// The row of a school in `schools`, which graduation records and locations refer to it by
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct SchoolId(pub i32);
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
 pub struct SchoolLocation {
     pub school: School,
@@ -30,9 +75,59 @@ pub struct Country {
     pub name: String,
 }
 
+/// The row of a country in `countries`, which school locations refer to it by
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, sqlx::Type)]
+#[sqlx(transparent)]
+pub struct CountryId(pub i32);
+
 #[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
 pub struct GraduationRecord {
     pub mathematician: Mathematician,
     pub school: School,
-    pub year: i32,
+    pub year: Year,
+    /// The degree as the page words it, such as "Ph.D."
+    pub degree: Option<String>,
+    /// The place of the degree's line on the page, 1 for the record's own degree
+    pub position: i16,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn listing(school: Option<&str>, year: Option<i16>) -> Listing {
+        Listing {
+            id: Id(92443),
+            name: "Rajesh Pereira".to_string(),
+            school: school.map(str::to_string),
+            year: year.and_then(Year::new),
+        }
+    }
+
+    #[test]
+    fn mathematicians_display_with_their_id() {
+        let mathematician = Mathematician {
+            id: Id(92443),
+            name: "Rajesh Pereira".to_string(),
+        };
+        assert_eq!(mathematician.to_string(), "Rajesh Pereira (92443)");
+    }
+
+    #[test]
+    fn display_includes_the_id_and_whatever_graduation_there_is() {
+        let toronto = Some("University of Toronto");
+        assert_eq!(
+            listing(toronto, Some(2003)).to_string(),
+            "Rajesh Pereira (92443, University of Toronto 2003)"
+        );
+        assert_eq!(
+            listing(toronto, None).to_string(),
+            "Rajesh Pereira (92443, University of Toronto)"
+        );
+        assert_eq!(
+            listing(None, Some(2003)).to_string(),
+            "Rajesh Pereira (92443, 2003)"
+        );
+        assert_eq!(listing(None, None).to_string(), "Rajesh Pereira (92443)");
+    }
 }
@@ -0,0 +1,122 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Mutex;
+
+/// The ids names are stored under, e.g. of schools, shared by every task of a run so that a name
+/// they all store, like a popular school, is only upserted once. Keeps at most `capacity` names,
+/// forgetting the least recently used one to make room, and none with a capacity of 0
+#[derive(Debug)]
+pub struct NameCache<T> {
+    capacity: usize,
+    names: Mutex<Lru<T>>,
+    /// Lookups the cache answered
+    pub hits: AtomicU64,
+    /// Lookups it didn't, which went to the database
+    pub misses: AtomicU64,
+}
+
+#[derive(Debug)]
+struct Lru<T> {
+    /// Every name with its id and when it was last used
+    ids: HashMap<String, (T, u64)>,
+    /// The names by when they were last used, the least recent first
+    by_use: BTreeMap<u64, String>,
+    uses: u64,
+}
+
+impl<T: Copy> NameCache<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            names: Mutex::new(Lru {
+                ids: HashMap::new(),
+                by_use: BTreeMap::new(),
+                uses: 0,
+            }),
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+
+    /// The id of `name` if it's cached, counting it as a hit or a miss
+    pub fn get(&self, name: &str) -> Option<T> {
+        let mut names = self.names.lock().unwrap();
+        let Lru { ids, by_use, uses } = &mut *names;
+        let Some((id, used)) = ids.get_mut(name) else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
+            return None;
+        };
+
+        by_use.remove(used);
+        *uses += 1;
+        *used = *uses;
+        by_use.insert(*uses, name.to_string());
+        self.hits.fetch_add(1, Ordering::Relaxed);
+        Some(*id)
+    }
+
+    /// Caches the id of `name`, which has to be committed already: a cached id is used without
+    /// asking the database
+    pub fn insert(&self, name: &str, id: T) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut names = self.names.lock().unwrap();
+        let Lru { ids, by_use, uses } = &mut *names;
+        *uses += 1;
+        if let Some((_, used)) = ids.insert(name.to_string(), (id, *uses)) {
+            by_use.remove(&used);
+        } else if ids.len() > self.capacity {
+            if let Some((_, oldest)) = by_use.pop_first() {
+                ids.remove(&oldest);
+            }
+        }
+        by_use.insert(*uses, name.to_string());
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.lock().unwrap().ids.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn forgets_the_least_recently_used_name() {
+        let cache = NameCache::new(2);
+        cache.insert("University of Toronto", 1);
+        cache.insert("McGill University", 2);
+        // looking Toronto up makes McGill the least recently used
+        assert_eq!(cache.get("University of Toronto"), Some(1));
+        cache.insert("University of Guelph", 3);
+
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("McGill University"), None);
+        assert_eq!(cache.get("University of Toronto"), Some(1));
+        assert_eq!(cache.get("University of Guelph"), Some(3));
+        assert_eq!(cache.hits.load(Ordering::Relaxed), 3);
+        assert_eq!(cache.misses.load(Ordering::Relaxed), 1);
+
+        // caching a name again only updates it
+        cache.insert("University of Guelph", 4);
+        assert_eq!(cache.len(), 2);
+        assert_eq!(cache.get("University of Guelph"), Some(4));
+        assert_eq!(cache.get("University of Toronto"), Some(1));
+    }
+
+    #[test]
+    fn a_cache_without_capacity_keeps_nothing() {
+        let cache = NameCache::new(0);
+        cache.insert("University of Toronto", 1);
+        assert!(cache.is_empty());
+        assert_eq!(cache.get("University of Toronto"), None);
+    }
+}
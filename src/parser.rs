@@ -1,34 +1,159 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::ops::BitOr;
+
+use chrono::NaiveDate;
 use color_eyre::eyre::eyre;
 use lazy_static::lazy_static;
 use regex::Regex;
+use schemars::JsonSchema;
+use scraper::ElementRef;
 use scraper::Html;
 use scraper::Selector;
+use serde::Deserialize;
+use serde::Serialize;
 use sqlx::prelude::FromRow;
 use tracing::debug;
+use tracing::warn;
+
+use crate::error::InvalidId;
+use crate::error::InvalidYear;
+use crate::error::NoRecord;
+use crate::error::ScraperError;
 
 lazy_static! {
+    static ref MAIN_CONTENT: Selector = Selector::parse("#mainContent").unwrap();
+    static ref BLOCK_MARKERS: Regex = Regex::new(
+        r"(?i)captcha|too many requests|rate limit exceeded|unusual traffic|verify you are human"
+    )
+    .unwrap();
     static ref ID_RE: Regex = Regex::new(r"id\.php\?id=(\d+)").unwrap();
+    static ref PAGE_ID: Regex = Regex::new(r"MGP ID of\s+(\d+)").unwrap();
     static ref NAME: Selector = Selector::parse("h2").unwrap();
     static ref DIV_SPAN: Selector = Selector::parse("div > span").unwrap();
     static ref SPAN: Selector = Selector::parse("span").unwrap();
     static ref ROWS_SELECTOR: Selector = Selector::parse("tr").unwrap();
     static ref CELL_SELECTOR: Selector = Selector::parse("td").unwrap();
     static ref ANCHOR_SELECTOR: Selector = Selector::parse("a").unwrap();
+    static ref DESCENDANTS_LINK: Selector =
+        Selector::parse(r#"a[href*="descendants.php"]"#).unwrap();
     static ref THESIS_SELECTOR: Selector = Selector::parse("#thesisTitle").unwrap();
     static ref COUNTRY_SELECTOR: Selector = Selector::parse("div > img").unwrap();
     static ref TABLE_SECTOR: Selector = Selector::parse("table").unwrap();
+    static ref PARAGRAPH_SELECTOR: Selector = Selector::parse("p").unwrap();
+    static ref ADVISOR_LABEL: Regex = Regex::new(r"Advisor\s*(\d+)?\s*:").unwrap();
+    static ref STUDENT_OF: Regex = Regex::new(r"(?i)\bstudent of\b").unwrap();
+    /// What can stand between the advisors a "Student of" names, e.g. in "A, B and C"
+    static ref ADVISOR_SEPARATOR: Regex = Regex::new(r"^(?:\s|[,;:&]|\band\b|\bof\b)*$").unwrap();
+    static ref THESIS_YEAR: Regex = Regex::new(r"\s*\((\d{3,4})\)$").unwrap();
+    static ref CONTRIBUTOR: Regex =
+        Regex::new(r"^(?:Contributed|Submitted) by:?\s*(.*?)\.?$").unwrap();
+    static ref LAST_UPDATED: Regex = Regex::new(r"^Last updated:?\s*(?:on\s+)?(.*?)\.?$").unwrap();
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow, Copy, sqlx::Type)]
+#[derive(
+    Debug, PartialEq, Eq, Hash, Clone, FromRow, Copy, sqlx::Type, Serialize, Deserialize, JsonSchema,
+)]
+#[sqlx(transparent)]
+#[serde(transparent)]
 pub struct Id(pub i32);
 
-impl Into<i32> for Id {
-    fn into(self) -> i32 {
+impl From<Id> for i32 {
+    fn from(id: Id) -> Self {
+        id.0
+    }
+}
+
+impl From<i32> for Id {
+    fn from(id: i32) -> Self {
+        Self(id)
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl std::str::FromStr for Id {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let id = s.trim().parse::<i32>()?;
+        if id < 1 {
+            return Err(InvalidId(id).into());
+        }
+        Ok(Self(id))
+    }
+}
+
+/// A graduation year, only constructible within a plausible range so that stray numbers on the
+/// page (ids, counts) can't be mistaken for one
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    PartialOrd,
+    Ord,
+    Hash,
+    Clone,
+    Copy,
+    sqlx::Type,
+    Serialize,
+    Deserialize,
+    JsonSchema,
+)]
+#[sqlx(transparent)]
+#[serde(try_from = "i16", into = "i16")]
+#[schemars(extend("minimum" = 800, "maximum" = 2100))]
+pub struct Year(i16);
+
+impl Year {
+    /// Generous bounds around the years that appear in the genealogy
+    pub const MIN: i16 = 800;
+    pub const MAX: i16 = 2100;
+
+    pub fn new(year: i16) -> Option<Self> {
+        (Self::MIN..=Self::MAX)
+            .contains(&year)
+            .then_some(Self(year))
+    }
+
+    pub fn get(self) -> i16 {
         self.0
     }
 }
 
-#[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
+impl TryFrom<i16> for Year {
+    type Error = InvalidYear;
+
+    fn try_from(year: i16) -> Result<Self, Self::Error> {
+        Self::new(year).ok_or(InvalidYear(year))
+    }
+}
+
+impl From<Year> for i16 {
+    fn from(year: Year) -> Self {
+        year.0
+    }
+}
+
+impl From<Year> for i32 {
+    fn from(year: Year) -> Self {
+        year.0.into()
+    }
+}
+
+impl std::str::FromStr for Year {
+    type Err = color_eyre::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(s.parse::<i16>()?.try_into()?)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
 /// A record of a mathematician and their students
 pub struct ScrapeRecord {
     /// The name of the main mathematician
@@ -37,8 +162,20 @@ pub struct ScrapeRecord {
     /// A list of studetns mentored under the main mathematician
     pub students: Vec<Student>,
 
-    /// The title of dissertation of the main mathematician
-    pub dissertation: Option<String>,
+    /// How the page presented the students, which tells an empty list apart from a missing one
+    pub students_section: StudentsSection,
+
+    /// The titles of the main mathematician's dissertations in page order, usually one but more
+    /// for e.g. two doctorates
+    pub dissertations: Vec<String>,
+
+    /// The year the first dissertation was written, when the page gives one apart from the degree
+    /// year
+    pub dissertation_year: Option<Year>,
+
+    /// The advisors of the first dissertation that link to a record, in the order the page numbers
+    /// them
+    pub advisors: Vec<Advisor>,
 
     /// The university or equivalent institution where the main mathematician graduated
     pub school: Option<String>,
@@ -47,14 +184,241 @@ pub struct ScrapeRecord {
     pub country: Option<String>,
 
     /// The year when the main mathematician graduated
-    pub year: Option<i16>,
+    pub year: Option<Year>,
 
-    /// The title of the degree, such as "Ph.D."
+    /// The title of the degree as the page gives it, such as "Ph.D.", "M.A." or "Dr. h.c."
     pub degree: Option<String>,
+
+    /// The degree lines after the first, which the fields above are of, in page order. E.g. the
+    /// master's of a mathematician whose page lists it below their doctorate
+    pub further_degrees: Vec<Degree>,
+
+    /// Who the page credits with contributing the record's data
+    pub contributor: Option<String>,
+
+    /// When the page says the site last updated the record
+    pub source_updated: Option<NaiveDate>,
+}
+
+impl ScrapeRecord {
+    /// How many of the optional fields (school, year, country, dissertation, students) have a
+    /// value, empty strings don't count
+    pub fn populated_fields(&self) -> usize {
+        let present =
+            |field: &Option<String>| field.as_deref().is_some_and(|f| !f.trim().is_empty());
+
+        [
+            present(&self.school),
+            self.year.is_some(),
+            present(&self.country),
+            self.dissertations.iter().any(|d| !d.trim().is_empty()),
+            !self.students.is_empty(),
+        ]
+        .into_iter()
+        .filter(|p| *p)
+        .count()
+    }
+}
+
+impl fmt::Display for ScrapeRecord {
+    /// One field per line, leaving out the ones the page didn't have, then the students indented
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "name:          {}", self.name)?;
+        let fields = [
+            ("degree:        ", self.degree.as_deref()),
+            ("school:        ", self.school.as_deref()),
+            ("country:       ", self.country.as_deref()),
+        ];
+        for (label, value) in fields {
+            if let Some(value) = value {
+                writeln!(f, "{label}{value}")?;
+            }
+        }
+        if let Some(year) = self.year {
+            writeln!(f, "year:          {}", year.get())?;
+        }
+        for further in &self.further_degrees {
+            let parts: Vec<_> = [
+                further.degree.clone(),
+                further.school.clone(),
+                further.country.clone(),
+                further.year.map(|year| year.get().to_string()),
+            ]
+            .into_iter()
+            .flatten()
+            .collect();
+            writeln!(f, "also:          {}", parts.join(", "))?;
+        }
+        for title in &self.dissertations {
+            writeln!(f, "dissertation:  {title}")?;
+        }
+        if let Some(year) = self.dissertation_year {
+            writeln!(f, "written:       {}", year.get())?;
+        }
+        for advisor in &self.advisors {
+            writeln!(
+                f,
+                "advisor {}:     {} ({})",
+                advisor.order, advisor.name, advisor.id
+            )?;
+        }
+        if let Some(contributor) = &self.contributor {
+            writeln!(f, "contributor:   {contributor}")?;
+        }
+        if let Some(updated) = self.source_updated {
+            writeln!(f, "updated:       {updated}")?;
+        }
+
+        writeln!(
+            f,
+            "students:      {} ({:?})",
+            self.students.len(),
+            self.students_section
+        )?;
+        for student in &self.students {
+            write!(f, "    {}", student.name)?;
+            if let Some(id) = student.id {
+                write!(f, " ({id})")?;
+            }
+            if let Some(school) = &student.school {
+                write!(f, ", {school}")?;
+            }
+            if let Some(year) = student.year {
+                write!(f, " {}", year.get())?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/// How a page presents the students of a mathematician
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum StudentsSection {
+    /// A students table with at least one row
+    Listed,
+
+    /// A students table with only the header row
+    EmptyTable,
+
+    /// The page states that no students are known
+    NoneKnown,
+
+    /// Neither a students table nor a statement that there are none, likely a parser miss
+    Missing,
 }
 
-/// A student of a mathematician
-#[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow)]
+impl StudentsSection {
+    /// Whether the page said conclusively who the students are, so the record can be considered
+    /// complete
+    pub fn is_complete(self) -> bool {
+        matches!(self, StudentsSection::Listed | StudentsSection::NoneKnown)
+    }
+}
+
+/// Which fields of a record are parsed from its page and so stored, as a set of flags combined
+/// with `|`. The name is always parsed, there's no record without it
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct ScrapeFields(u8);
+
+impl ScrapeFields {
+    pub const ADVISORS: Self = Self(1);
+    /// The students table, on every one of its pages
+    pub const STUDENTS: Self = Self(1 << 1);
+    /// The degree lines with their title, school and year
+    pub const DEGREES: Self = Self(1 << 2);
+    /// The countries of the degree lines, which are only stored along with their schools
+    pub const COUNTRY: Self = Self(1 << 3);
+    /// The dissertation titles and the year they were written
+    pub const DISSERTATIONS: Self = Self(1 << 4);
+    /// Who contributed the record and when the site last updated it
+    pub const PROVENANCE: Self = Self(1 << 5);
+
+    pub const NONE: Self = Self(0);
+    pub const ALL: Self = Self((1 << 6) - 1);
+
+    /// Whether every field of `other` is in the set
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+
+    /// The flags as a number, e.g. to tell scrapes with different fields apart
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+}
+
+impl fmt::Display for ScrapeFields {
+    /// The fields by name, comma separated
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let names = [
+            (Self::ADVISORS, "advisors"),
+            (Self::STUDENTS, "students"),
+            (Self::DEGREES, "degrees"),
+            (Self::COUNTRY, "country"),
+            (Self::DISSERTATIONS, "dissertations"),
+            (Self::PROVENANCE, "provenance"),
+        ];
+        let names: Vec<_> = names
+            .into_iter()
+            .filter(|&(field, _)| self.contains(field))
+            .map(|(_, name)| name)
+            .collect();
+        match names.as_slice() {
+            [] => write!(f, "only the name"),
+            names => write!(f, "{}", names.join(", ")),
+        }
+    }
+}
+
+impl Default for ScrapeFields {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl BitOr for ScrapeFields {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | other.0)
+    }
+}
+
+/// A degree line of a page, each with a school, country and year of its own
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Default, Serialize, Deserialize, JsonSchema)]
+pub struct Degree {
+    /// The title of the degree as the page gives it
+    pub degree: Option<String>,
+
+    /// The university or equivalent institution that awarded it
+    pub school: Option<String>,
+
+    /// The country of the school
+    pub country: Option<String>,
+
+    /// The year it was awarded
+    pub year: Option<Year>,
+}
+
+/// An advisor of a mathematician's dissertation
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Serialize, Deserialize, JsonSchema)]
+pub struct Advisor {
+    /// The name of the advisor
+    pub name: String,
+
+    /// The id of the advisor as stored in the mathgenealogy database
+    pub id: Id,
+
+    /// The number of the "Advisor N" label, 1 for the primary advisor and for a lone unlabeled one
+    pub order: u32,
+}
+
+/// A student of a mathematician. Students are compared by all their fields, but collecting them
+/// into a `HashSet` loses the page order their ranks are stored in, see
+/// [`dedup_students_preserving_order`] for dropping repeats
+#[derive(Debug, PartialEq, Eq, Hash, Clone, FromRow, Serialize, Deserialize, JsonSchema)]
 pub struct Student {
     /// The name of the student
     pub name: String,
@@ -66,42 +430,406 @@ pub struct Student {
     pub school: Option<String>,
 
     /// The year when the student graduated
-    pub year: Option<i16>,
+    pub year: Option<Year>,
+}
+
+/// Extracts the mathgenealogy id from a record url such as `https://www.mathgenealogy.org/id.php?id=10416`
+pub fn parse_id(url: &str) -> Option<Id> {
+    ID_RE.captures(url)?.get(1)?.as_str().parse().ok()
+}
+
+/// The primary content container of the page, falling back to the whole document when the page
+/// doesn't have one, so the navigation and layout elements around it are never matched
+pub fn main_content(page: &Html) -> ElementRef<'_> {
+    page.select(&MAIN_CONTENT)
+        .next()
+        .unwrap_or_else(|| page.root_element())
+}
+
+/// The part of `body` worth keeping when it didn't parse: the HTML of its main content, or of the
+/// whole page without one, cut to at most `max_bytes` on a character boundary
+pub fn failure_snippet(body: &str, max_bytes: usize) -> String {
+    let page = Html::parse_document(body);
+    let mut html = main_content(&page).html();
+    if html.len() > max_bytes {
+        let mut end = max_bytes;
+        while !html.is_char_boundary(end) {
+            end -= 1;
+        }
+        html.truncate(end);
+    }
+    html
+}
+
+/// Whether the body of a response is a rate limit or CAPTCHA page rather than a record. Every
+/// response is checked before it's parsed, so this looks for the main content and the markers in
+/// the raw body instead of parsing it a second time
+pub fn is_block_page(body: &str) -> bool {
+    !body.contains("mainContent") && BLOCK_MARKERS.is_match(body)
+}
+
+/// Whether `page` is a rate limit or CAPTCHA page. Only a page without the site's layout can be
+/// one, a record's thesis title or name may well mention a CAPTCHA
+fn is_block(page: &Html) -> bool {
+    page.select(&MAIN_CONTENT).next().is_none()
+        && page
+            .root_element()
+            .text()
+            .any(|text| BLOCK_MARKERS.is_match(text))
+}
+
+/// Whether the body of a response is the page the site serves for an id without a record
+pub fn is_not_found_page(body: &str) -> bool {
+    body.contains("You have specified an ID that does not exist in the database")
+}
+
+/// Parses a record straight from the HTML of a page, for callers that don't want to deal with
+/// `scraper::Html` themselves
+///
+/// ```
+/// use combi::parser::parse_record;
+/// use combi::parser::Year;
+///
+/// let record = parse_record(
+///     r#"<div id="mainContent">
+///         <h2>Donald  Ervin Knuth</h2>
+///         <div><span>Ph.D. <span>California Institute of Technology</span> 1963</span></div>
+///     </div>"#,
+/// )
+/// .unwrap();
+///
+/// assert_eq!(record.name, "Donald Ervin Knuth");
+/// assert_eq!(record.school.as_deref(), Some("California Institute of Technology"));
+/// assert_eq!(record.year, Year::new(1963));
+/// assert!(record.students.is_empty());
+/// ```
+pub fn parse_record(html: &str) -> color_eyre::Result<ScrapeRecord> {
+    scrape(&Html::parse_document(html))
 }
 
 pub fn scrape(page: &Html) -> color_eyre::Result<ScrapeRecord> {
-    let mathematician = scrape_mathematician(page)?;
-    let dissertation = scrape_dissertation(page);
-    let students = scrape_students(page)?;
+    scrape_with_warnings(page).map(|(record, _)| record)
+}
+
+/// A field whose markup is on the page but couldn't be parsed, which `scrape` reports the same way
+/// as a field the record doesn't have
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct FieldWarning {
+    pub field: &'static str,
+    pub message: String,
+}
+
+impl FieldWarning {
+    fn new(field: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            field,
+            message: message.into(),
+        }
+    }
+}
+
+/// Like `scrape`, also returning a warning for every optional field that's present on the page
+/// but failed to parse
+pub fn scrape_with_warnings(page: &Html) -> color_eyre::Result<(ScrapeRecord, Vec<FieldWarning>)> {
+    scrape_fields(page, ScrapeFields::ALL)
+}
+
+/// Like `scrape_with_warnings`, parsing only `fields`. The others are left empty without looking
+/// for them on the page, and aren't warned about
+pub fn scrape_fields(
+    page: &Html,
+    fields: ScrapeFields,
+) -> color_eyre::Result<(ScrapeRecord, Vec<FieldWarning>)> {
+    let record = scrape_record(page, fields)?;
+    let warnings = field_warnings(main_content(page), &record, fields);
+    Ok((record, warnings))
+}
 
-    let university = parse_school(page);
-    let year = parse_year(page);
-    let country = parse_country(page);
-    let degree = parse_title(page);
+fn field_warnings(
+    content: ElementRef<'_>,
+    record: &ScrapeRecord,
+    fields: ScrapeFields,
+) -> Vec<FieldWarning> {
+    let mut warnings = vec![];
+
+    let degree = degree_lines(content)
+        .first()
+        .copied()
+        .filter(|_| fields.contains(ScrapeFields::DEGREES));
+    if let Some(degree) = degree {
+        // an empty school span is how the site shows an unknown school
+        if line_spans(degree).all(|span| span.select(&SPAN).next().is_none()) {
+            warnings.push(FieldWarning::new("school", "the degree line has no school"));
+        }
+        if record.year.is_none() {
+            let numbers = line_spans(degree)
+                .flat_map(|span| span.text())
+                .flat_map(str::split_whitespace)
+                .filter(|t| t.chars().all(|c| c.is_ascii_digit()));
+            for number in numbers {
+                warnings.push(FieldWarning::new(
+                    "year",
+                    format!("{number} is not a plausible year"),
+                ));
+            }
+        }
+    }
+
+    let flag = content
+        .select(&COUNTRY_SELECTOR)
+        .next()
+        .filter(|_| fields.contains(ScrapeFields::DEGREES | ScrapeFields::COUNTRY));
+    if let Some(flag) = flag {
+        if record.country.as_deref().is_none_or(str::is_empty) {
+            warnings.push(FieldWarning::new(
+                "country",
+                format!(
+                    "the flag {:?} has no alt text",
+                    flag.attr("src").unwrap_or_default()
+                ),
+            ));
+        }
+    }
+
+    if fields.contains(ScrapeFields::DISSERTATIONS)
+        && content.select(&THESIS_SELECTOR).next().is_none()
+    {
+        warnings.push(FieldWarning::new(
+            "dissertation",
+            "the page has no thesis element",
+        ));
+    }
+
+    if fields.contains(ScrapeFields::PROVENANCE) && record.source_updated.is_none() {
+        if let Some(date) = footer_value(content, &LAST_UPDATED) {
+            warnings.push(FieldWarning::new(
+                "source_updated",
+                format!("{date:?} is not a date"),
+            ));
+        }
+    }
+
+    let table = content
+        .select(&TABLE_SECTOR)
+        .next()
+        .filter(|_| fields.contains(ScrapeFields::STUDENTS));
+    if let Some(table) = table {
+        // first row is the header
+        let rows = table.select(&ROWS_SELECTOR).skip(1).count();
+        let dropped = rows.saturating_sub(record.students.len());
+        if dropped > 0 {
+            warnings.push(FieldWarning::new(
+                "students",
+                format!("{dropped} of {rows} rows could not be parsed"),
+            ));
+        }
+    }
+
+    warnings
+}
+
+fn scrape_record(page: &Html, fields: ScrapeFields) -> color_eyre::Result<ScrapeRecord> {
+    if is_block(page) {
+        return Err(ScraperError::RateLimited.into());
+    }
+    // checked before the name, which the page doesn't have either
+    if page.root_element().text().any(is_not_found_page) {
+        return Err(NoRecord.into());
+    }
+
+    let content = main_content(page);
+
+    let mathematician = scrape_mathematician(content)?;
+    let (dissertations, dissertation_year) = if fields.contains(ScrapeFields::DISSERTATIONS) {
+        (
+            scrape_dissertations(content),
+            parse_dissertation_year(content),
+        )
+    } else {
+        (vec![], None)
+    };
+    let advisors = if fields.contains(ScrapeFields::ADVISORS) {
+        scrape_advisors(content)
+    } else {
+        vec![]
+    };
+    // a students table that isn't looked at is missing as far as the record goes
+    let (students, students_section) = if fields.contains(ScrapeFields::STUDENTS) {
+        (scrape_students(content)?, scrape_students_section(content))
+    } else {
+        (vec![], StudentsSection::Missing)
+    };
+
+    let lines = if fields.contains(ScrapeFields::DEGREES) {
+        degree_lines(content)
+    } else {
+        vec![]
+    };
+    let with_country = fields.contains(ScrapeFields::COUNTRY);
+    let mut degrees = lines
+        .into_iter()
+        .map(|line| parse_degree(line, with_country));
+    let first = degrees.next().unwrap_or_default();
+    let further_degrees = degrees.collect();
+    let (contributor, source_updated) = if fields.contains(ScrapeFields::PROVENANCE) {
+        (parse_contributor(content), parse_source_updated(content))
+    } else {
+        (None, None)
+    };
 
     Ok(ScrapeRecord {
         name: mathematician,
         students,
-        dissertation: dissertation.map(|d| d.to_string()),
-        school: university.map(|s| s.to_string()),
-        country: country.map(|c| c.to_string()),
-        year,
-        degree: degree.map(|d| d.to_string()),
+        students_section,
+        dissertations: dissertations.into_iter().map(str::to_string).collect(),
+        dissertation_year,
+        advisors,
+        school: first.school,
+        country: first.country,
+        year: first.year,
+        degree: first.degree,
+        further_degrees,
+        contributor,
+        source_updated,
     })
 }
 
-pub fn scrape_dissertation(page: &Html) -> Option<&str> {
-    let thesis = page.select(&THESIS_SELECTOR).next()?;
-    let thesis = thesis.text().next()?;
+/// The titles of every thesis on the page, in page order and without repeats
+pub fn scrape_dissertations(content: ElementRef<'_>) -> Vec<&str> {
+    let mut titles = vec![];
+    for thesis in content.select(&THESIS_SELECTOR) {
+        let Some(thesis) = thesis.text().next() else {
+            continue;
+        };
+        match split_thesis_year(thesis.trim()).0 {
+            "" => {}
+            t if titles.contains(&t) => {}
+            t => titles.push(t),
+        }
+    }
+    titles
+}
+
+/// Splits the year a thesis was written in off the end of its title, as some older records give
+/// it like `Title (1931)`. Anything that isn't a plausible year stays part of the title
+fn split_thesis_year(title: &str) -> (&str, Option<Year>) {
+    let year = THESIS_YEAR
+        .captures(title)
+        .and_then(|c| Some((c.get(0)?.start(), c[1].parse::<Year>().ok()?)));
 
-    match thesis.trim() {
-        "" => None,
-        t => Some(t),
+    match year {
+        Some((start, year)) => (&title[..start], Some(year)),
+        None => (title, None),
     }
 }
 
-pub fn scrape_students(page: &Html) -> color_eyre::Result<Vec<Student>> {
-    let students = page.select(&TABLE_SECTOR).next();
+pub fn parse_dissertation_year(content: ElementRef<'_>) -> Option<Year> {
+    let thesis = content.select(&THESIS_SELECTOR).next()?;
+    split_thesis_year(thesis.text().next()?.trim()).1
+}
+
+/// The advisors listed under the dissertation, either as `Advisor: ...` or numbered as
+/// `Advisor 1: ... Advisor 2: ...`, then the ones only named in prose as "Student of ...",
+/// numbered after them. `Advisor: Unknown` gives none
+pub fn scrape_advisors(content: ElementRef<'_>) -> Vec<Advisor> {
+    let mut advisors = labelled_advisors(content);
+    for (name, id) in student_of_advisors(content) {
+        if advisors.iter().any(|a| a.id == id) {
+            continue;
+        }
+        let order = advisors.iter().map(|a| a.order).max().unwrap_or(0) + 1;
+        advisors.push(Advisor { name, id, order });
+    }
+    advisors
+}
+
+fn labelled_advisors(content: ElementRef<'_>) -> Vec<Advisor> {
+    let Some(paragraph) = content.select(&PARAGRAPH_SELECTOR).find(|p| {
+        p.text()
+            .next()
+            .is_some_and(|t| t.trim().starts_with("Advisor"))
+    }) else {
+        return vec![];
+    };
+
+    // each link follows its "Advisor N:" label, or a bare "Advisor:" when there's only one
+    let mut advisors = vec![];
+    let mut label = None;
+    for node in paragraph.descendants() {
+        if let Some(text) = node.value().as_text() {
+            if let Some(captures) = ADVISOR_LABEL.captures(text) {
+                label = captures.get(1).and_then(|n| n.as_str().parse().ok());
+            }
+            continue;
+        }
+        let Some(anchor) = ElementRef::wrap(node).filter(|e| e.value().name() == "a") else {
+            continue;
+        };
+        let Some(id) = anchor.attr("href").and_then(parse_id) else {
+            continue;
+        };
+        let name = anchor.text().collect::<String>();
+
+        advisors.push(Advisor {
+            name: name.split_whitespace().collect::<Vec<_>>().join(" "),
+            id,
+            // unlabeled advisors are numbered by their position
+            order: label.take().unwrap_or(advisors.len() as u32 + 1),
+        });
+    }
+
+    advisors
+}
+
+/// The records linked right after a "Student of" in a paragraph, with their names, in page order.
+/// A mention can name several, "Student of A, B and C", but a link after other prose isn't an
+/// advisor
+fn student_of_advisors(content: ElementRef<'_>) -> Vec<(String, Id)> {
+    let mut advisors = vec![];
+    for paragraph in content.select(&PARAGRAPH_SELECTOR) {
+        if !STUDENT_OF.is_match(&paragraph.text().collect::<String>()) {
+            continue;
+        }
+
+        // whether the links that follow are still part of a mention
+        let mut naming = false;
+        for node in paragraph.descendants() {
+            if let Some(text) = node.value().as_text() {
+                // the name of a link, which was taken with the link
+                let in_link = node
+                    .parent()
+                    .and_then(ElementRef::wrap)
+                    .is_some_and(|parent| parent.value().name() == "a");
+                if in_link {
+                    continue;
+                }
+                match STUDENT_OF.find_iter(text).last() {
+                    Some(mention) => naming = ADVISOR_SEPARATOR.is_match(&text[mention.end()..]),
+                    None => naming &= ADVISOR_SEPARATOR.is_match(text),
+                }
+                continue;
+            }
+            let Some(anchor) = ElementRef::wrap(node).filter(|e| e.value().name() == "a") else {
+                continue;
+            };
+            if !naming {
+                continue;
+            }
+            match anchor.attr("href").and_then(parse_id) {
+                Some(id) => {
+                    let name = anchor.text().collect::<String>();
+                    advisors.push((name.split_whitespace().collect::<Vec<_>>().join(" "), id));
+                }
+                None => naming = false,
+            }
+        }
+    }
+    advisors
+}
+
+/// The students of the page's table, each once and in the order the page lists them
+pub fn scrape_students(content: ElementRef<'_>) -> color_eyre::Result<Vec<Student>> {
+    let students = content.select(&TABLE_SECTOR).next();
 
     let entries = match students {
         None => {
@@ -110,7 +838,7 @@ pub fn scrape_students(page: &Html) -> color_eyre::Result<Vec<Student>> {
         }
         Some(x) => x,
     };
-    let mut students = entries.select(&ROWS_SELECTOR).skip(1); // first row is the header
+    let students = entries.select(&ROWS_SELECTOR).skip(1); // first row is the header
 
     let students: Vec<_> = students
         .filter_map(|row| {
@@ -123,12 +851,12 @@ pub fn scrape_students(page: &Html) -> color_eyre::Result<Vec<Student>> {
 
             let name = parse_name(name.text().next()?);
 
-            fn school(cell: &scraper::ElementRef) -> Option<String> {
-                Some(cell.text().next()?.trim().to_string())
-            }
-            let school = school(&cells.next()?);
+            let school = match joined_text(cells.next()?) {
+                school if school.is_empty() => None,
+                school => Some(school),
+            };
 
-            let year: Option<i16> = cells
+            let year: Option<Year> = cells
                 .next()
                 .and_then(|cell| cell.text().next()?.trim().parse().ok());
 
@@ -141,64 +869,327 @@ pub fn scrape_students(page: &Html) -> color_eyre::Result<Vec<Student>> {
         })
         .collect();
 
-    Ok(students)
+    Ok(dedup_students_preserving_order(students))
 }
 
-fn parse_name(name: &str) -> String {
-    let mut full = String::new();
-    let mut parts = name.split(",");
+/// Drops the repeats of a student listed more than once, by id, keeping the first and otherwise
+/// the order they came in. Students without an id can't be told apart and are all kept
+pub fn dedup_students_preserving_order(students: Vec<Student>) -> Vec<Student> {
+    let mut seen = HashSet::new();
+    students
+        .into_iter()
+        .filter(|student| {
+            let Some(id) = student.id else {
+                return true;
+            };
+            let first = seen.insert(id);
+            if !first {
+                warn!("{} ({id:?}) is listed more than once", student.name);
+            }
+            first
+        })
+        .collect()
+}
 
-    let surname = parts.next();
-    if surname.is_none() {
-        return name.to_string();
+/// The link to the next page of the students table, for advisors whose students don't fit on one.
+/// Only a link reading just "Next" or "Next page", with or without an arrow, is one: a student's
+/// name may start with "Next" too
+pub fn parse_next_students_page(content: ElementRef<'_>) -> Option<&str> {
+    content
+        .select(&ANCHOR_SELECTOR)
+        .find(|a| {
+            let text = a.text().collect::<String>().to_lowercase();
+            let text = text.trim().trim_end_matches(['»', '>']).trim_end();
+            text == "next" || text == "next page"
+        })?
+        .attr("href")
+}
+
+/// The link to the page listing everyone descended from the mathematician, which the site only
+/// shows for mathematicians with descendants
+pub fn parse_descendants_link(content: ElementRef<'_>) -> Option<String> {
+    content
+        .select(&DESCENDANTS_LINK)
+        .next()?
+        .attr("href")
+        .map(str::to_string)
+}
+
+/// The id the page gives as its own, from the note at its end asking to quote it when submitting
+/// students, to cross-check the id the page was requested for
+pub fn parse_page_id(content: ElementRef<'_>) -> Option<Id> {
+    let text: String = content.text().collect();
+    PAGE_ID.captures(&text)?.get(1)?.as_str().parse().ok()
+}
+
+/// The ids linked from a descendants page, each once in the order they're listed
+pub fn scrape_descendants(content: ElementRef<'_>) -> Vec<Id> {
+    let mut seen = HashSet::new();
+    content
+        .select(&ANCHOR_SELECTOR)
+        .filter_map(|a| parse_id(a.attr("href")?))
+        .filter(|id| seen.insert(*id))
+        .collect()
+}
+
+pub fn scrape_students_section(content: ElementRef<'_>) -> StudentsSection {
+    if let Some(table) = content.select(&TABLE_SECTOR).next() {
+        // first row is the header
+        return match table.select(&ROWS_SELECTOR).nth(1) {
+            Some(_) => StudentsSection::Listed,
+            None => StudentsSection::EmptyTable,
+        };
     }
 
-    let surname = surname.unwrap();
+    let none_known = content
+        .select(&PARAGRAPH_SELECTOR)
+        .any(|p| p.text().any(|t| t.contains("No students known")));
 
-    for part in parts {
-        full.push_str(part.trim());
-        full.push(' ');
+    if none_known {
+        StudentsSection::NoneKnown
+    } else {
+        StudentsSection::Missing
     }
-    full.push_str(surname.trim());
+}
+
+/// The generational suffixes a students table lists after the given names, e.g. "Hall, Marshall,
+/// Jr."
+const NAME_SUFFIXES: [&str; 7] = ["Jr.", "Jr", "Sr.", "Sr", "II", "III", "IV"];
 
-    full
+/// A name the students table lists surname first, in display order
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum NameOrder {
+    /// The only way to read it, e.g. "Hall, Marshall, Jr." as "Marshall Hall, Jr."
+    Certain(String),
+    /// A guess, the listing has more commas than a surname, given names and suffixes explain,
+    /// e.g. "Smith, John, Peter"
+    Ambiguous(String),
 }
 
-pub fn scrape_mathematician(page: &Html) -> color_eyre::Result<String> {
-    Ok(page
+impl NameOrder {
+    pub fn name(&self) -> &str {
+        match self {
+            NameOrder::Certain(name) | NameOrder::Ambiguous(name) => name,
+        }
+    }
+}
+
+/// Reorders a name listed as "Surname, Given names" or "Surname, Given names, Suffix" into the
+/// order the page's heading gives it. A name without a comma is already in display order
+pub fn display_order(listed: &str) -> NameOrder {
+    let mut parts: Vec<_> = listed
+        .split(',')
+        .map(str::trim)
+        .filter(|p| !p.is_empty())
+        .collect();
+    let suffixes = parts
+        .iter()
+        .skip(2)
+        .rev()
+        .take_while(|p| NAME_SUFFIXES.contains(p))
+        .count();
+    let suffixes: Vec<_> = parts.split_off(parts.len() - suffixes);
+
+    let name = match parts.split_first() {
+        None => String::new(),
+        Some((surname, [])) => surname.to_string(),
+        Some((surname, given)) => format!("{} {surname}", given.join(" ")),
+    };
+    let name = std::iter::once(name.as_str())
+        .chain(suffixes.iter().copied())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    if parts.len() > 2 {
+        NameOrder::Ambiguous(name)
+    } else {
+        NameOrder::Certain(name)
+    }
+}
+
+fn parse_name(name: &str) -> String {
+    display_order(name).name().to_string()
+}
+
+/// The students of the page's table with their ids as the table lists them, surname first. The
+/// same rows as `scrape_students`, without anything but the name parsed
+pub fn listed_students(content: ElementRef<'_>) -> Vec<(Id, String)> {
+    let Some(table) = content.select(&TABLE_SECTOR).next() else {
+        return vec![];
+    };
+    table
+        .select(&ROWS_SELECTOR)
+        .skip(1)
+        .filter_map(|row| {
+            let name = row.select(&CELL_SELECTOR).next()?;
+            let id = parse_id(name.select(&ANCHOR_SELECTOR).next()?.attr("href")?)?;
+            Some((id, name.text().next()?.to_string()))
+        })
+        .collect()
+}
+
+/// The name in the page's heading, all of its text even when parts of it are wrapped in links or
+/// other markup, with the whitespace collapsed
+pub fn scrape_mathematician(content: ElementRef<'_>) -> color_eyre::Result<String> {
+    let heading = content
         .select(&NAME)
         .next()
-        .ok_or(eyre!("Name not found"))?
-        .text()
-        .next()
-        .ok_or(eyre!("Name not found"))?
-        .trim()
-        .split_whitespace()
-        .collect::<Vec<_>>()
-        .join(" "))
+        .ok_or(eyre!("Name not found"))?;
+    match joined_text(heading) {
+        name if name.is_empty() => Err(eyre!("Name not found")),
+        name => Ok(name),
+    }
+}
+
+/// The divs of the page's degree lines in page order. A line is usually a single span with the
+/// degree, the school in a span of its own and the year, and the country's flag next to it, but
+/// some pages split it into several spans, e.g. the year in a span after the school's, which all
+/// belong to the same degree. The dissertation's div has spans too, but it's no degree line
+fn degree_lines(content: ElementRef<'_>) -> Vec<ElementRef<'_>> {
+    let mut lines: Vec<ElementRef<'_>> = vec![];
+    for span in content.select(&DIV_SPAN) {
+        let Some(div) = span.parent().and_then(ElementRef::wrap) else {
+            continue;
+        };
+        if div.select(&THESIS_SELECTOR).next().is_some() || lines.contains(&div) {
+            continue;
+        }
+        lines.push(div);
+    }
+    lines
+}
+
+/// The spans directly in a degree line's div, which hold all of the degree between them
+fn line_spans(line: ElementRef<'_>) -> impl Iterator<Item = ElementRef<'_>> {
+    line.children()
+        .filter_map(ElementRef::wrap)
+        .filter(|child| child.value().name() == "span")
 }
 
-fn parse_country(page: &Html) -> Option<&str> {
-    let country = page.select(&COUNTRY_SELECTOR).next()?;
+/// Every field of one degree line, all read from the same div so that a school is never paired
+/// with the year of another degree
+fn parse_degree(line: ElementRef<'_>, with_country: bool) -> Degree {
+    Degree {
+        degree: parse_title(line),
+        school: parse_school(line),
+        country: if with_country {
+            parse_country(line)
+        } else {
+            None
+        },
+        year: parse_year(line),
+    }
+}
+
+/// The country of a degree line, from the alt text of the flag in the line's div
+fn parse_country(line: ElementRef<'_>) -> Option<String> {
+    let country = line.select(&COUNTRY_SELECTOR).next()?;
     let country = country.value().attr("alt")?;
-    Some(country)
+    Some(normalize_country(country))
 }
 
-fn parse_title(page: &Html) -> Option<&str> {
-    page.select(&DIV_SPAN).next()?.text().next()
+/// Turns the alt text of a flag, the country's name with the spaces taken out, into its display
+/// name. Splitting before every capital covers most of them, the rest lost lowercase words
+fn normalize_country(alt: &str) -> String {
+    let irregular = match alt {
+        "AntiguaandBarbuda" => Some("Antigua and Barbuda"),
+        "BosniaandHerzegovina" => Some("Bosnia and Herzegovina"),
+        "TrinidadandTobago" => Some("Trinidad and Tobago"),
+        "SaoTomeandPrincipe" => Some("Sao Tome and Principe"),
+        "IsleofMan" => Some("Isle of Man"),
+        _ => None,
+    };
+    if let Some(name) = irregular {
+        return name.to_string();
+    }
+
+    let mut name = String::with_capacity(alt.len() + 2);
+    let mut previous = None;
+    for c in alt.chars() {
+        if c.is_uppercase() && previous.is_some_and(char::is_lowercase) {
+            name.push(' ');
+        }
+        name.push(c);
+        previous = Some(c);
+    }
+    name
 }
 
-fn parse_school(page: &Html) -> Option<&str> {
-    Some(page.select(&DIV_SPAN).next()?.text().skip(1).next()?.trim())
+/// The degree as the page words it, e.g. "Ph.D.", "M.A." or "Dr. h.c." for an honorary one. It's
+/// the text in front of the school, or in front of the year when the school is unknown
+fn parse_title(line: ElementRef<'_>) -> Option<String> {
+    let title = line_spans(line).next()?.text().next()?;
+    let mut words: Vec<_> = title.split_whitespace().collect();
+    if words.last().is_some_and(|w| w.parse::<Year>().is_ok()) {
+        words.pop();
+    }
+
+    match words.join(" ") {
+        degree if degree.is_empty() => None,
+        degree => Some(degree),
+    }
 }
 
-fn parse_year(page: &Html) -> Option<i16> {
-    let phd_section = page.select(&DIV_SPAN).next()?;
-    let texts = phd_section.text();
+/// What follows `label` on a line of one of the page's paragraphs, where the footer credits the
+/// record, e.g. `Contributed by: Jane Doe` or `Last updated: March 14, 2019`
+fn footer_value<'a>(content: ElementRef<'a>, label: &Regex) -> Option<&'a str> {
+    content
+        .select(&PARAGRAPH_SELECTOR)
+        .flat_map(|p| p.text())
+        .find_map(|line| Some(label.captures(line.trim())?.get(1)?.as_str()))
+}
+
+fn parse_contributor(content: ElementRef<'_>) -> Option<String> {
+    match footer_value(content, &CONTRIBUTOR)?.trim() {
+        "" => None,
+        contributor => Some(contributor.to_string()),
+    }
+}
+
+/// The date the record was last updated, given either like `March 14, 2019` or `2019-03-14`
+fn parse_source_updated(content: ElementRef<'_>) -> Option<NaiveDate> {
+    let date = footer_value(content, &LAST_UPDATED)?.trim();
+    ["%B %d, %Y", "%Y-%m-%d", "%d %B %Y"]
+        .into_iter()
+        .find_map(|format| NaiveDate::parse_from_str(date, format).ok())
+}
+
+/// The school of a degree line, the span inside it. A name can be split over links, markup and
+/// footnote markers, so it's all the span's text bar the markers, with the whitespace in between
+/// collapsed. An empty span is an unknown school
+fn parse_school(line: ElementRef<'_>) -> Option<String> {
+    let school = line_spans(line).find_map(|span| span.select(&SPAN).next())?;
+    match joined_text(school) {
+        school if school.is_empty() => None,
+        school => Some(school),
+    }
+}
+
+/// All the text of `element` with every run of whitespace, non-breaking and ideographic spaces
+/// included, made a single space. Footnote markers, the `<sup>`s, are left out
+fn joined_text(element: ElementRef<'_>) -> String {
+    fn push_text(element: ElementRef<'_>, text: &mut String) {
+        for child in element.children() {
+            if let Some(child) = ElementRef::wrap(child) {
+                if child.value().name() != "sup" {
+                    push_text(child, text);
+                }
+            } else if let Some(part) = child.value().as_text() {
+                text.push_str(part);
+            }
+        }
+    }
+
+    let mut text = String::new();
+    push_text(element, &mut text);
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
 
-    texts
+fn parse_year(line: ElementRef<'_>) -> Option<Year> {
+    line_spans(line)
+        .flat_map(|span| span.text())
         .map(|t| t.trim())
-        .filter_map(|t| t.parse::<i16>().ok())
+        .filter_map(|t| t.parse::<Year>().ok())
         .next()
 }
 
@@ -207,22 +1198,159 @@ mod test {
     use super::*;
     use std::fs::read;
 
+    #[test]
+    fn the_not_found_page_is_no_record() {
+        let page = read("not-found.html").unwrap();
+        let page = Html::parse_document(&String::from_utf8(page).unwrap());
+        let e = scrape(&page).unwrap_err();
+        assert_eq!(e.downcast_ref(), Some(&NoRecord));
+    }
+
+    #[test]
+    fn records_print_a_field_per_line() {
+        let record = ScrapeRecord {
+            name: "Rajesh Pereira".to_string(),
+            students: vec![
+                Student {
+                    name: "Tai-Yih Tso".to_string(),
+                    id: Some(Id(235835)),
+                    school: Some("University of Guelph".to_string()),
+                    year: Year::new(2017),
+                },
+                Student {
+                    name: "Nobody Linked".to_string(),
+                    id: None,
+                    school: None,
+                    year: None,
+                },
+            ],
+            students_section: StudentsSection::Listed,
+            dissertations: vec!["Trace Vectors in Matrix Analysis".to_string()],
+            dissertation_year: None,
+            advisors: vec![Advisor {
+                name: "Man-Duen Choi".to_string(),
+                id: Id(15957),
+                order: 1,
+            }],
+            school: Some("University of Toronto".to_string()),
+            country: None,
+            year: Year::new(2003),
+            degree: Some("Ph.D.".to_string()),
+            further_degrees: vec![],
+            contributor: None,
+            source_updated: None,
+        };
+
+        assert_eq!(
+            record.to_string(),
+            "name:          Rajesh Pereira
+degree:        Ph.D.
+school:        University of Toronto
+year:          2003
+dissertation:  Trace Vectors in Matrix Analysis
+advisor 1:     Man-Duen Choi (15957)
+students:      2 (Listed)
+    Tai-Yih Tso (235835), University of Guelph 2017
+    Nobody Linked
+"
+        );
+    }
+
     #[test]
     fn parse_name_works_for_tai() {
         let page = read("Tai-Yih.html").unwrap();
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
-        let name = scrape_mathematician(&page).unwrap();
+        let name = scrape_mathematician(main_content(&page)).unwrap();
         assert_eq!(name, "Tai-Yih Tso");
     }
 
+    #[test]
+    fn parse_name_reorders_real_entries() {
+        let cases = [
+            // hyphenated given names and surnames
+            ("Tso, Tai-Yih", "Tai-Yih Tso"),
+            ("Chen, Pang-Chieh", "Pang-Chieh Chen"),
+            ("Mont-Reynaud, Bernard", "Bernard Mont-Reynaud"),
+            // apostrophes, particles and non-ascii letters
+            (
+                "al-Masihi, Abu Sahl 'Isa ibn Yahya",
+                "Abu Sahl 'Isa ibn Yahya al-Masihi",
+            ),
+            (
+                "ibn Sina, Abu ʿAli al-Husayn",
+                "Abu ʿAli al-Husayn ibn Sina",
+            ),
+            // multi-word surnames
+            ("Trabb Pardo, Luis", "Luis Trabb Pardo"),
+            ("Van Wyk, Christopher", "Christopher Van Wyk"),
+            ("Zabala Salelles, Ignacio", "Ignacio Zabala Salelles"),
+            // already in display order
+            ("Roger Keith Alexander", "Roger Keith Alexander"),
+            // generational suffixes stay last
+            ("Hall, Marshall, Jr.", "Marshall Hall, Jr."),
+            ("Bush, Kenneth Allen, III", "Kenneth Allen Bush, III"),
+            (
+                "Sheffer, Henry Maurice, Jr., II",
+                "Henry Maurice Sheffer, Jr., II",
+            ),
+        ];
+
+        for (listed, expected) in cases {
+            assert_eq!(parse_name(listed), expected, "{listed}");
+            assert!(matches!(display_order(listed), NameOrder::Certain(_)));
+        }
+    }
+
+    #[test]
+    fn names_with_unexplained_commas_are_ambiguous() {
+        assert_eq!(
+            display_order("Smith, John, Peter"),
+            NameOrder::Ambiguous("John Peter Smith".to_string())
+        );
+        assert_eq!(
+            display_order("Jr., Smith, John"),
+            NameOrder::Ambiguous("Smith John Jr.".to_string())
+        );
+        // a suffix can't be all there is after the surname
+        assert_eq!(
+            display_order("Hall, Jr."),
+            NameOrder::Certain("Jr. Hall".to_string())
+        );
+    }
+
+    #[test]
+    fn scrape_mathematician_keeps_names_as_displayed() {
+        let cases = [
+            ("abu.html", "Abu Sahl 'Isa ibn Yahya al-Masihi"),
+            ("rajesh.html", "Rajesh Pereira"),
+            ("knuth.html", "Donald Ervin Knuth"),
+            // the name split over a link and spans
+            ("nested-name.html", "Tai-Yih Tso"),
+        ];
+
+        for (fixture, expected) in cases {
+            let page = String::from_utf8(read(fixture).unwrap()).unwrap();
+            let page = Html::parse_document(&page);
+            assert_eq!(scrape_mathematician(main_content(&page)).unwrap(), expected);
+        }
+
+        // a heading with only empty markup in it has no name
+        let page = Html::parse_document(r#"<div id="mainContent"><h2><span> </span></h2></div>"#);
+        assert!(scrape_mathematician(main_content(&page)).is_err());
+    }
+
+    fn first_degree_line(page: &Html) -> ElementRef<'_> {
+        degree_lines(main_content(page))[0]
+    }
+
     #[test]
     fn parse_year_works_for_knuth() {
         let page = read("knuth.html").unwrap();
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
-        let year = parse_year(&page).unwrap();
-        assert_eq!(year, 1963);
+        let year = parse_year(first_degree_line(&page)).unwrap();
+        assert_eq!(year, Year::new(1963).unwrap());
     }
 
     #[test]
@@ -230,8 +1358,36 @@ mod test {
         let page = read("rajesh.html").unwrap();
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
-        let year = parse_year(&page).unwrap();
-        assert_eq!(year, 2003);
+        let year = parse_year(first_degree_line(&page)).unwrap();
+        assert_eq!(year, Year::new(2003).unwrap());
+    }
+
+    #[test]
+    fn parse_title_keeps_the_degree_as_worded() {
+        let degrees = [
+            ("knuth.html", Some("Ph.D.")),
+            ("honorary.html", Some("Dr. h.c.")),
+            ("masters.html", Some("M.A.")),
+            ("abu.html", None),
+        ];
+
+        for (fixture, degree) in degrees {
+            let page = Html::parse_document(&std::fs::read_to_string(fixture).unwrap());
+            assert_eq!(
+                parse_title(first_degree_line(&page)).as_deref(),
+                degree,
+                "for {fixture}"
+            );
+        }
+
+        // without a school the year follows the degree directly
+        let page = Html::parse_document(
+            r#"<div id="mainContent"><div><span>M.Sc. 1987</span></div></div>"#,
+        );
+        assert_eq!(
+            parse_title(first_degree_line(&page)).as_deref(),
+            Some("M.Sc.")
+        );
     }
 
     #[test]
@@ -239,10 +1395,9 @@ mod test {
         let page = read("knuth.html").unwrap();
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
-        let country = parse_country(&page).unwrap();
+        let country = parse_country(first_degree_line(&page)).unwrap();
 
-        // it's stupid, I know...
-        assert_eq!(country, "UnitedStates");
+        assert_eq!(country, "United States");
     }
 
     #[test]
@@ -250,11 +1405,31 @@ mod test {
         let page = read("rajesh.html").unwrap();
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
-        let country = parse_country(&page).unwrap();
+        let country = parse_country(first_degree_line(&page)).unwrap();
 
         assert_eq!(country, "Canada");
     }
 
+    #[test]
+    fn normalize_country_pins_known_flags() {
+        let flags = [
+            ("UnitedStates", "United States"),
+            ("UnitedKingdom", "United Kingdom"),
+            ("Russia", "Russia"),
+            ("Canada", "Canada"),
+            ("CzechRepublic", "Czech Republic"),
+            ("NewZealand", "New Zealand"),
+            ("SouthAfrica", "South Africa"),
+            ("BosniaandHerzegovina", "Bosnia and Herzegovina"),
+            ("TrinidadandTobago", "Trinidad and Tobago"),
+            ("IsleofMan", "Isle of Man"),
+        ];
+
+        for (alt, name) in flags {
+            assert_eq!(normalize_country(alt), name, "for the flag {alt:?}");
+        }
+    }
+
     #[test]
     fn scrape_rajesh() {
         let page = read("rajesh.html").unwrap();
@@ -264,10 +1439,7 @@ mod test {
 
         assert_eq!(rajesh.name, "Rajesh Pereira");
         assert_eq!(rajesh.school, Some("University of Toronto".to_string()));
-        assert_eq!(
-            rajesh.dissertation,
-            Some("Trace Vectors in Matrix Analysis".to_string())
-        );
+        assert_eq!(rajesh.dissertations, ["Trace Vectors in Matrix Analysis"]);
         assert_eq!(rajesh.country, Some("Canada".to_string()));
     }
 
@@ -279,7 +1451,7 @@ mod test {
         let abu = scrape(&page).unwrap();
 
         assert_eq!(abu.name, "Abu Sahl 'Isa ibn Yahya al-Masihi");
-        assert_eq!(abu.dissertation, None);
+        assert!(abu.dissertations.is_empty());
     }
 
     #[test]
@@ -287,32 +1459,32 @@ mod test {
         let page = read("rajesh.html").unwrap();
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
-        let students = scrape_students(&page).unwrap();
+        let students = scrape_students(main_content(&page)).unwrap();
 
-        let expected = vec![
+        let expected = [
             Student {
                 name: "George Hutchinson".to_string(),
                 id: Some(Id(235835)),
                 school: Some("University of Guelph".to_string()),
-                year: Some(2018),
+                year: Year::new(2018),
             },
             Student {
                 name: "Jeremy Levick".to_string(),
                 id: Some(Id(197636)),
                 school: Some("University of Guelph".to_string()),
-                year: Some(2015),
+                year: Year::new(2015),
             },
             Student {
                 name: "Preeti Mohindru".to_string(),
                 id: Some(Id(190371)),
                 school: Some("University of Guelph".to_string()),
-                year: Some(2014),
+                year: Year::new(2014),
             },
             Student {
                 name: "Jeffrey Tsang".to_string(),
                 id: Some(Id(190372)),
                 school: Some("University of Guelph".to_string()),
-                year: Some(2014),
+                year: Year::new(2014),
             },
         ];
 
@@ -335,8 +1507,8 @@ mod test {
             Some("California Institute of Technology".to_string())
         );
         assert_eq!(
-            knuth.dissertation,
-            Some("Finite Semifields and Projective Planes".to_string())
+            knuth.dissertations,
+            ["Finite Semifields and Projective Planes"]
         );
     }
 
@@ -346,7 +1518,7 @@ mod test {
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
 
-        let uni = parse_school(&page).unwrap();
+        let uni = parse_school(first_degree_line(&page)).unwrap();
         assert_eq!(uni, "California Institute of Technology".to_string(),);
     }
 
@@ -356,7 +1528,661 @@ mod test {
         let page = String::from_utf8(page).unwrap();
         let page = Html::parse_document(&page);
 
-        let uni = parse_school(&page).unwrap();
+        let uni = parse_school(first_degree_line(&page)).unwrap();
         assert_eq!(uni, "University of Toronto");
     }
+
+    #[test]
+    fn failure_snippets_are_the_main_content_and_bounded() {
+        let body = r#"<html><body><div id="sidebar1">Menu</div>
+            <div id="mainContent"><h3>東京帝国大学</h3></div></body></html>"#;
+        assert_eq!(
+            failure_snippet(body, 1000),
+            r#"<div id="mainContent"><h3>東京帝国大学</h3></div>"#
+        );
+        // cut short of the character the limit falls in
+        let snippet = failure_snippet(body, 31);
+        assert_eq!(snippet, r#"<div id="mainContent"><h3>東"#);
+
+        assert_eq!(
+            failure_snippet("", 1000),
+            "<html><head></head><body></body></html>"
+        );
+    }
+
+    #[test]
+    fn school_names_spanning_several_elements_are_complete() {
+        let page = read("multipart-school.html").unwrap();
+        let page = String::from_utf8(page).unwrap();
+        let page = Html::parse_document(&page);
+
+        let record = scrape(&page).unwrap();
+        assert_eq!(
+            record.school.as_deref(),
+            Some("東京帝国大学 (Tokyo Imperial University)")
+        );
+        // the footnote marker is no year either
+        assert_eq!(record.year, Year::new(1945));
+        assert_eq!(record.country.as_deref(), Some("Japan"));
+
+        // an empty span is no school rather than an empty one
+        let page = Html::parse_document(
+            r#"<div id="mainContent"><h2>Abu</h2><div><span>Ph.D. <span></span></span></div></div>"#,
+        );
+        assert_eq!(scrape(&page).unwrap().school, None);
+    }
+
+    #[test]
+    fn scrape_ignores_layout_outside_main_content() {
+        let page = Html::parse_document(
+            r#"<html><body>
+            <div id="header"><h2>Mathematics Genealogy Project</h2></div>
+            <div id="sidebar1"><table><tr><th>Menu</th></tr><tr><td><a href="id.php?id=1">Home</a></td><td>Nav</td></tr></table></div>
+            <div id="mainContent">
+                <h2>Rajesh  Pereira </h2>
+                <div><span>Ph.D. <span>University of Toronto</span> 2003</span></div>
+            </div>
+            </body></html>"#,
+        );
+        let record = scrape(&page).unwrap();
+
+        assert_eq!(record.name, "Rajesh Pereira");
+        assert_eq!(record.school, Some("University of Toronto".to_string()));
+        assert_eq!(record.year, Year::new(2003));
+        assert!(record.students.is_empty());
+    }
+
+    #[test]
+    fn parse_id_works_for_record_urls() {
+        assert_eq!(
+            parse_id("https://www.mathgenealogy.org/id.php?id=10416"),
+            Some(Id(10416))
+        );
+        assert_eq!(parse_id("id.php?id=10416&fChrono=1"), Some(Id(10416)));
+        assert_eq!(parse_id("https://www.mathgenealogy.org/index.php"), None);
+    }
+
+    /// Fixtures whose whole `ScrapeRecord` is pinned by a `<name>.golden.json` next to them
+    const GOLDEN_FIXTURES: [&str; 14] = [
+        "knuth",
+        "rajesh",
+        "abu",
+        "Tai-Yih",
+        "honorary",
+        "masters",
+        "two-dissertations",
+        "provenance",
+        "multipart-school",
+        "nested-name",
+        "two-degrees",
+        "student-of",
+        "split-degrees",
+        "captcha",
+    ];
+
+    /// Compares the scrape of every golden fixture against its committed JSON, run with
+    /// `UPDATE_GOLDEN=1` to regenerate them after an intended parser change
+    #[test]
+    fn scrape_matches_golden_files() {
+        let update = std::env::var_os("UPDATE_GOLDEN").is_some_and(|v| v == "1");
+
+        for fixture in GOLDEN_FIXTURES {
+            let page = read(format!("{fixture}.html")).unwrap();
+            let page = String::from_utf8(page).unwrap();
+            let page = Html::parse_document(&page);
+            let record = scrape(&page).unwrap();
+
+            let actual = serde_json::to_string_pretty(&record).unwrap() + "\n";
+            let golden = format!("{fixture}.golden.json");
+
+            if update {
+                std::fs::write(&golden, &actual).unwrap();
+                continue;
+            }
+
+            let expected = std::fs::read_to_string(&golden)
+                .unwrap_or_else(|e| panic!("{golden} is missing ({e}), run with UPDATE_GOLDEN=1"));
+            assert_eq!(actual, expected, "{fixture} no longer matches {golden}");
+        }
+    }
+
+    #[test]
+    fn scrape_students_drops_repeated_rows() {
+        let page = read("duplicate-students.html").unwrap();
+        let page = String::from_utf8(page).unwrap();
+        let page = Html::parse_document(&page);
+        let students = scrape_students(main_content(&page)).unwrap();
+
+        let ids: Vec<_> = students.iter().filter_map(|s| s.id).collect();
+        assert_eq!(ids, [235835, 197636, 190371, 190372].map(Id));
+        assert_eq!(students[1].name, "Jeremy Levick");
+    }
+
+    #[test]
+    fn scrape_empty_students_table() {
+        let page = read("empty-students.html").unwrap();
+        let page = String::from_utf8(page).unwrap();
+        let page = Html::parse_document(&page);
+        let students = scrape_students(main_content(&page)).unwrap();
+
+        assert!(students.is_empty());
+        assert_eq!(
+            scrape_students_section(main_content(&page)),
+            StudentsSection::EmptyTable
+        );
+    }
+
+    #[test]
+    fn scrape_students_section_for_each_layout() {
+        let cases = [
+            ("rajesh.html", StudentsSection::Listed),
+            ("empty-students.html", StudentsSection::EmptyTable),
+            ("Tai-Yih.html", StudentsSection::NoneKnown),
+            ("missing-students.html", StudentsSection::Missing),
+        ];
+
+        for (fixture, expected) in cases {
+            let page = read(fixture).unwrap();
+            let page = String::from_utf8(page).unwrap();
+            let page = Html::parse_document(&page);
+            let record = scrape(&page).unwrap();
+
+            assert!(record.students.is_empty() || expected == StudentsSection::Listed);
+            assert_eq!(record.students_section, expected, "{fixture}");
+        }
+
+        assert!(StudentsSection::Listed.is_complete());
+        assert!(StudentsSection::NoneKnown.is_complete());
+        assert!(!StudentsSection::EmptyTable.is_complete());
+        assert!(!StudentsSection::Missing.is_complete());
+    }
+
+    #[test]
+    fn scrape_classifies_block_page() {
+        let page = read("blocked.html").unwrap();
+        let page = String::from_utf8(page).unwrap();
+        assert!(is_block_page(&page));
+
+        let page = Html::parse_document(&page);
+        let error = scrape(&page).unwrap_err();
+        assert!(matches!(
+            error.downcast_ref::<ScraperError>(),
+            Some(ScraperError::RateLimited)
+        ));
+    }
+
+    #[test]
+    fn records_are_not_block_pages() {
+        for fixture in [
+            "knuth.html",
+            "rajesh.html",
+            "abu.html",
+            "Tai-Yih.html",
+            "captcha.html",
+        ] {
+            let page = String::from_utf8(read(fixture).unwrap()).unwrap();
+            assert!(!is_block_page(&page), "{fixture}");
+        }
+    }
+
+    #[test]
+    fn ids_convert_from_and_to_numbers() {
+        assert_eq!(Id::from(92443), Id(92443));
+        assert_eq!(i32::from(Id::from(92443)), 92443);
+    }
+
+    #[test]
+    fn ids_display_as_their_number() {
+        assert_eq!(Id(92443).to_string(), "92443");
+        assert_eq!(format!("id.php?id={}", Id(7)), "id.php?id=7");
+    }
+
+    #[test]
+    fn ids_parse_only_from_positive_numbers() {
+        assert_eq!("92443".parse::<Id>().unwrap(), Id(92443));
+        assert_eq!(" 1 ".parse::<Id>().unwrap(), Id(1));
+
+        let error = "0".parse::<Id>().unwrap_err();
+        assert_eq!(error.downcast_ref(), Some(&InvalidId(0)));
+        assert!("-3".parse::<Id>().is_err());
+        assert!("abc".parse::<Id>().is_err());
+        assert!("".parse::<Id>().is_err());
+    }
+
+    #[test]
+    fn year_rejects_out_of_range_values() {
+        assert_eq!(Year::new(Year::MIN).map(Year::get), Some(Year::MIN));
+        assert_eq!(Year::new(Year::MAX).map(Year::get), Some(Year::MAX));
+        assert_eq!(Year::new(Year::MIN - 1), None);
+        assert_eq!(Year::new(Year::MAX + 1), None);
+        assert_eq!(Year::try_from(0), Err(InvalidYear(0)));
+
+        assert!("1963".parse::<Year>().is_ok());
+        assert!("92443".parse::<Year>().is_err());
+        assert!("-5".parse::<Year>().is_err());
+
+        assert_eq!(i32::from(Year::new(1963).unwrap()), 1963);
+        assert!(serde_json::from_str::<Year>("3000").is_err());
+        assert_eq!(serde_json::to_string(&Year::new(1963)).unwrap(), "1963");
+    }
+
+    #[test]
+    fn scrape_students_across_pages() {
+        let pages = [
+            ("id.php?id=92443&page=2", "paginated-2.html"),
+            ("", "paginated-1.html"),
+        ];
+        let fixture = |href: &str| {
+            let (_, file) = pages.iter().find(|(h, _)| *h == href).unwrap();
+            Html::parse_document(&String::from_utf8(read(file).unwrap()).unwrap())
+        };
+
+        let mut page = fixture("");
+        let mut students = vec![];
+        loop {
+            let content = main_content(&page);
+            students.extend(scrape_students(content).unwrap());
+            let Some(next) = parse_next_students_page(content).map(str::to_string) else {
+                break;
+            };
+            page = fixture(&next);
+        }
+
+        let ids: Vec<_> = students.iter().map(|s| s.id.unwrap().0).collect();
+        assert_eq!(ids, [235835, 197636, 190371, 190372]);
+    }
+
+    #[test]
+    fn scrape_full_thesis_metadata() {
+        let page = String::from_utf8(read("thesis-metadata.html").unwrap()).unwrap();
+        let record = parse_record(&page).unwrap();
+
+        assert_eq!(record.dissertations, ["Trace Vectors in Matrix Analysis"]);
+        assert_eq!(record.dissertation_year, Year::new(2002));
+        assert_eq!(record.year, Year::new(2003));
+        assert_eq!(
+            record.advisors,
+            [
+                Advisor {
+                    name: "Man-Duen Choi".to_string(),
+                    id: Id(15957),
+                    order: 1,
+                },
+                Advisor {
+                    name: "Marshall Hall, Jr.".to_string(),
+                    id: Id(6807),
+                    order: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn advisors_take_the_number_of_their_label() {
+        let record = parse_record(
+            r#"<div id="mainContent"><h2>Two Advisors</h2>
+<p>Advisor 2: <a href="id.php?id=6807">Marshall  Hall, Jr.</a><br />Advisor 1: <a href="id.php?id=15957">Man-Duen  Choi</a><br /></p>
+</div>"#,
+        )
+        .unwrap();
+        let orders: Vec<_> = record.advisors.iter().map(|a| (a.id, a.order)).collect();
+        assert_eq!(orders, [(Id(6807), 2), (Id(15957), 1)]);
+
+        let knuth = String::from_utf8(read("knuth.html").unwrap()).unwrap();
+        let knuth = parse_record(&knuth).unwrap();
+        assert_eq!(knuth.advisors[0].order, 1);
+    }
+
+    #[test]
+    fn student_of_in_prose_adds_advisors() {
+        let page = String::from_utf8(read("student-of.html").unwrap()).unwrap();
+        let record = parse_record(&page).unwrap();
+        // Choi is named three times but is one advisor, and the links after the prose moved on
+        // aren't advisors
+        let advisors: Vec<_> = record
+            .advisors
+            .iter()
+            .map(|a| (a.name.as_str(), a.id, a.order))
+            .collect();
+        assert_eq!(
+            advisors,
+            [
+                ("Man-Duen Choi", Id(15957), 1),
+                ("Marshall Hall, Jr.", Id(6807), 2)
+            ]
+        );
+
+        // a page with nothing but the prose
+        let record = parse_record(
+            r#"<div id="mainContent"><h2>Only Prose</h2>
+<p>Student of <a href="id.php?id=6807">Marshall Hall, Jr.</a>, <a href="id.php?id=15957">Man-Duen Choi</a>.
+Later a student of: <a href="id.php?id=10416">Carl Friedrich Gauss</a></p>
+<p>Students of this mathematician: <a href="id.php?id=1">Someone</a></p>
+</div>"#,
+        )
+        .unwrap();
+        let advisors: Vec<_> = record.advisors.iter().map(|a| (a.id, a.order)).collect();
+        assert_eq!(advisors, [(Id(6807), 1), (Id(15957), 2), (Id(10416), 3)]);
+    }
+
+    #[test]
+    fn scrape_title_only_thesis_metadata() {
+        let page = String::from_utf8(read("knuth.html").unwrap()).unwrap();
+        let knuth = parse_record(&page).unwrap();
+        assert_eq!(knuth.dissertation_year, None);
+        assert_eq!(knuth.advisors.len(), 1);
+
+        // no title and an unknown advisor
+        let page = String::from_utf8(read("abu.html").unwrap()).unwrap();
+        let abu = parse_record(&page).unwrap();
+        assert!(abu.dissertations.is_empty());
+        assert_eq!(abu.dissertation_year, None);
+        assert!(abu.advisors.is_empty());
+
+        assert_eq!(
+            split_thesis_year("On Groups (1931)"),
+            ("On Groups", Year::new(1931))
+        );
+        assert_eq!(
+            split_thesis_year("Results (12345)"),
+            ("Results (12345)", None)
+        );
+    }
+
+    #[test]
+    fn scrape_every_dissertation() {
+        let page = String::from_utf8(read("two-dissertations.html").unwrap()).unwrap();
+        let record = parse_record(&page).unwrap();
+
+        assert_eq!(
+            record.dissertations,
+            [
+                "Trace Vectors in Matrix Analysis",
+                "Positive Matrices and Their Traces"
+            ]
+        );
+        // the year and advisors are those of the first
+        assert_eq!(record.dissertation_year, None);
+        assert_eq!(record.advisors.len(), 1);
+        assert_eq!(record.advisors[0].id, Id(15957));
+    }
+
+    #[test]
+    fn scrape_every_degree() {
+        let page = std::fs::read_to_string("two-degrees.html").unwrap();
+        let record = parse_record(&page).unwrap();
+
+        // the first line is the record's own
+        assert_eq!(record.degree.as_deref(), Some("Ph.D."));
+        assert_eq!(record.school.as_deref(), Some("University of Toronto"));
+        assert_eq!(record.country.as_deref(), Some("Canada"));
+        assert_eq!(record.year, Year::new(2003));
+        assert_eq!(
+            record.further_degrees,
+            [Degree {
+                degree: Some("M.Sc.".to_string()),
+                school: Some("Universidade de Lisboa".to_string()),
+                country: Some("Portugal".to_string()),
+                year: Year::new(1999),
+            }]
+        );
+        // the second line's empty dissertation isn't one
+        assert_eq!(record.dissertations, ["Trace Vectors in Matrix Analysis"]);
+    }
+
+    #[test]
+    fn degree_split_across_spans_is_read_per_line() {
+        let page = std::fs::read_to_string("split-degrees.html").unwrap();
+        let (record, warnings) = scrape_with_warnings(&Html::parse_document(&page)).unwrap();
+
+        // each year is in a span after the school's, and still goes with that school
+        assert_eq!(record.degree.as_deref(), Some("Ph.D."));
+        assert_eq!(record.school.as_deref(), Some("University of Toronto"));
+        assert_eq!(record.year, Year::new(2003));
+        assert_eq!(
+            record.further_degrees,
+            [Degree {
+                degree: Some("M.Sc.".to_string()),
+                school: Some("Universidade de Lisboa".to_string()),
+                country: Some("Portugal".to_string()),
+                year: Year::new(1999),
+            }]
+        );
+        assert_eq!(warnings, []);
+    }
+
+    #[test]
+    fn masked_fields_are_not_parsed() {
+        let page = std::fs::read_to_string("two-degrees.html").unwrap();
+        let page = Html::parse_document(&page);
+
+        let graph = ScrapeFields::ADVISORS | ScrapeFields::STUDENTS;
+        let (record, warnings) = scrape_fields(&page, graph).unwrap();
+        assert_eq!(record.name, "Rajesh Pereira");
+        assert_eq!(record.advisors.len(), 1);
+        assert_eq!(record.students.len(), 4);
+        assert_eq!(
+            (record.degree, record.school, record.year),
+            (None, None, None)
+        );
+        assert!(record.further_degrees.is_empty());
+        assert!(record.dissertations.is_empty());
+        assert_eq!(warnings, []);
+
+        let (record, _) = scrape_fields(&page, ScrapeFields::DEGREES).unwrap();
+        assert!(record.advisors.is_empty());
+        assert!(record.students.is_empty());
+        assert_eq!(record.students_section, StudentsSection::Missing);
+        assert_eq!(record.school.as_deref(), Some("University of Toronto"));
+        assert_eq!(record.country, None);
+        assert_eq!(record.further_degrees[0].country, None);
+
+        let (all, _) = scrape_fields(&page, ScrapeFields::ALL).unwrap();
+        assert_eq!(all, scrape(&page).unwrap());
+        assert_eq!(ScrapeFields::default(), ScrapeFields::ALL);
+        assert_eq!(graph.to_string(), "advisors, students");
+    }
+
+    #[test]
+    fn fixtures_parse_without_warnings() {
+        let fixtures = [
+            "knuth.html",
+            "rajesh.html",
+            "abu.html",
+            "Tai-Yih.html",
+            "provenance.html",
+        ];
+        for fixture in fixtures {
+            let page = String::from_utf8(read(fixture).unwrap()).unwrap();
+            let (_, warnings) = scrape_with_warnings(&Html::parse_document(&page)).unwrap();
+            assert_eq!(warnings, [], "{fixture}");
+        }
+    }
+
+    #[test]
+    fn unparsable_fields_are_warned_about() {
+        let page = r#"<html><body><div id="mainContent">
+<h2>Broken  Record</h2>
+<div><span>Ph.D. <span>Somewhere University</span> 92443</span><img src="img/flags/x.gif" /></div>
+<table>
+<tr><th>Name</th><th>School</th><th>Year</th></tr>
+<tr><td><a href="id.php?id=1">Student, One</a></td><td>Somewhere University</td><td>1990</td></tr>
+<tr><td>No link, Two</td><td>Somewhere University</td><td>1991</td></tr>
+</table></div></body></html>"#;
+
+        let (record, warnings) = scrape_with_warnings(&Html::parse_document(page)).unwrap();
+        assert_eq!(record.year, None);
+        assert_eq!(record.country, None);
+
+        let fields: Vec<_> = warnings.iter().map(|w| w.field).collect();
+        assert_eq!(fields, ["year", "country", "dissertation", "students"]);
+        assert_eq!(warnings[0].message, "92443 is not a plausible year");
+        assert_eq!(warnings[3].message, "1 of 2 rows could not be parsed");
+    }
+
+    #[test]
+    fn source_metadata_takes_either_date_format() {
+        let page = String::from_utf8(read("provenance.html").unwrap()).unwrap();
+        let with_date = |date: &str| {
+            let page = page.replace("March 14, 2019", date);
+            scrape_with_warnings(&Html::parse_document(&page)).unwrap()
+        };
+
+        let (record, _) = with_date("2019-03-14");
+        assert_eq!(record.contributor.as_deref(), Some("Chi-Kwong Li"));
+        assert_eq!(record.source_updated, NaiveDate::from_ymd_opt(2019, 3, 14));
+
+        let (record, warnings) = with_date("sometime in spring");
+        assert_eq!(record.source_updated, None);
+        assert_eq!(
+            warnings,
+            [FieldWarning::new(
+                "source_updated",
+                "\"sometime in spring\" is not a date"
+            )]
+        );
+
+        let page = String::from_utf8(read("knuth.html").unwrap()).unwrap();
+        let knuth = parse_record(&page).unwrap();
+        assert_eq!((knuth.contributor, knuth.source_updated), (None, None));
+    }
+
+    #[test]
+    fn scrape_students_keeps_the_page_order() {
+        let page = String::from_utf8(read("knuth.html").unwrap()).unwrap();
+
+        // the ids in the order they appear in the students table of the raw html
+        let table = &page[page.find("<table").unwrap()..];
+        let table = &table[..table.find("</table>").unwrap()];
+        let expected: Vec<_> = ID_RE
+            .captures_iter(table)
+            .map(|c| c[1].parse::<i32>().unwrap())
+            .collect();
+
+        let record = parse_record(&page).unwrap();
+        let ids: Vec<_> = record.students.iter().map(|s| s.id.unwrap().0).collect();
+        assert!(ids.len() > 1);
+        assert_eq!(ids, expected);
+    }
+
+    #[test]
+    fn deduplicated_students_keep_a_stable_order() {
+        let page = String::from_utf8(read("knuth.html").unwrap()).unwrap();
+        let students = parse_record(&page).unwrap().students;
+        assert_eq!(parse_record(&page).unwrap().students, students);
+
+        // the same students again, say from a later page, are dropped without reordering
+        let mut unnamed = students[0].clone();
+        unnamed.id = None;
+        let listed: Vec<_> = [unnamed.clone()]
+            .into_iter()
+            .chain(students.iter().cloned().rev())
+            .chain(students.iter().cloned())
+            .chain([unnamed.clone()])
+            .collect();
+        let mut expected: Vec<_> = students.iter().cloned().rev().collect();
+        expected.insert(0, unnamed.clone());
+        expected.push(unnamed);
+        assert_eq!(dedup_students_preserving_order(listed), expected);
+    }
+
+    #[test]
+    fn finds_the_descendants_link() {
+        let page = Html::parse_document(
+            r#"<div id="mainContent"><h2>Rajesh Pereira</h2>
+<p>According to our current on-line database, Rajesh Pereira has 4 students and
+<a href="descendants.php?id=92443">4 descendants</a>.</p>
+<p><a href="submit-data.php?id=92443&amp;edit=0">update form</a></p></div>"#,
+        );
+        assert_eq!(
+            parse_descendants_link(main_content(&page)).as_deref(),
+            Some("descendants.php?id=92443")
+        );
+
+        // the saved pages predate the link
+        let page = String::from_utf8(read("rajesh.html").unwrap()).unwrap();
+        let page = Html::parse_document(&page);
+        assert_eq!(parse_descendants_link(main_content(&page)), None);
+    }
+
+    #[test]
+    fn descendants_are_listed_once_each() {
+        let page = Html::parse_document(
+            r#"<div id="mainContent"><h2>Descendants of Rajesh Pereira</h2>
+<ul><li><a href="id.php?id=235835">George Hutchinson</a> University of Guelph (2018)
+    <ul><li><a href="id.php?id=999">Grand Student</a></li></ul></li>
+<li><a href="id.php?id=190371">Third Student</a></li>
+<li><a href="id.php?id=235835">George Hutchinson</a></li></ul>
+<p><a href="submit-data.php?id=NEW&amp;edit=0">new data form</a></p></div>"#,
+        );
+        assert_eq!(
+            scrape_descendants(main_content(&page)),
+            [Id(235835), Id(999), Id(190371)]
+        );
+    }
+
+    #[test]
+    fn pages_give_their_own_id() {
+        for (fixture, id) in [("knuth.html", 10416), ("rajesh.html", 92443)] {
+            let page = String::from_utf8(read(fixture).unwrap()).unwrap();
+            let page = Html::parse_document(&page);
+            assert_eq!(
+                parse_page_id(main_content(&page)),
+                Some(Id(id)),
+                "{fixture}"
+            );
+        }
+
+        let page = Html::parse_document(r#"<div id="mainContent"><h2>Nobody</h2></div>"#);
+        assert_eq!(parse_page_id(main_content(&page)), None);
+    }
+
+    #[test]
+    fn single_page_records_have_no_next_students_page() {
+        for fixture in ["knuth.html", "rajesh.html", "abu.html", "Tai-Yih.html"] {
+            let page = String::from_utf8(read(fixture).unwrap()).unwrap();
+            let page = Html::parse_document(&page);
+            assert_eq!(
+                parse_next_students_page(main_content(&page)),
+                None,
+                "{fixture}"
+            );
+        }
+    }
+
+    #[test]
+    fn students_named_next_are_not_a_next_page() {
+        let page = Html::parse_document(
+            r#"<div id="mainContent"><table>
+                <tr><th>Name</th></tr>
+                <tr><td><a href="id.php?id=1">Nextel, Maria</a></td></tr>
+                <tr><td><a href="id.php?id=2">Next, Jonathan</a></td></tr>
+            </table></div>"#,
+        );
+        assert_eq!(parse_next_students_page(main_content(&page)), None);
+
+        for link in ["Next", "next &raquo;", "Next page &raquo;", " Next page "] {
+            let page = Html::parse_document(&format!(
+                r#"<div id="mainContent"><a href="id.php?id=1&amp;page=2">{link}</a></div>"#
+            ));
+            assert_eq!(
+                parse_next_students_page(main_content(&page)),
+                Some("id.php?id=1&page=2"),
+                "{link}"
+            );
+        }
+    }
+
+    #[test]
+    fn populated_fields_counts_optional_fields() {
+        let page = String::from_utf8(read("knuth.html").unwrap()).unwrap();
+        assert_eq!(parse_record(&page).unwrap().populated_fields(), 5);
+
+        // abu only has a student, the empty school doesn't count
+        let page = String::from_utf8(read("abu.html").unwrap()).unwrap();
+        assert_eq!(parse_record(&page).unwrap().populated_fields(), 1);
+
+        let record = parse_record(r#"<div id="mainContent"><h2>Stub</h2></div>"#).unwrap();
+        assert_eq!(record.populated_fields(), 0);
+    }
 }
@@ -0,0 +1,180 @@
+use std::fmt;
+
+use reqwest::Url;
+
+use crate::parser::Id;
+use crate::parser::ScrapeRecord;
+use crate::scrape::Scraper;
+use crate::source::GenealogySource;
+
+/// How a field the page didn't have is shown, so that a missing field stands out as much as a
+/// wrong one
+const MISSING: &str = "(not found)";
+
+/// The fields parsed from a page, one labelled line each and in page order, for checking them by
+/// eye against the page open in a browser
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Report {
+    /// The url the page was finally served from, which is the one to open
+    pub url: Url,
+    /// Every field with its label, the fields the page didn't have included
+    pub fields: Vec<(String, String)>,
+}
+
+impl Report {
+    /// Lays out `record`, parsed from the page at `url`, with the url of every advisor and
+    /// student that links to a record so that those can be followed too
+    pub fn new(url: Url, record: &ScrapeRecord, source: &dyn GenealogySource) -> Self {
+        let optional = |value: Option<String>| value.unwrap_or_else(|| MISSING.to_string());
+        let link = |name: &str, id: Option<Id>| match id {
+            Some(id) => format!("{name}  <{}>", source.url_for_id(id)),
+            None => name.to_string(),
+        };
+
+        let mut fields = vec![
+            ("name".to_string(), record.name.clone()),
+            ("degree".to_string(), optional(record.degree.clone())),
+            ("school".to_string(), optional(record.school.clone())),
+            ("country".to_string(), optional(record.country.clone())),
+            (
+                "year".to_string(),
+                optional(record.year.map(|year| year.get().to_string())),
+            ),
+        ];
+        for (n, further) in record.further_degrees.iter().enumerate() {
+            let parts: Vec<_> = [
+                further.degree.clone(),
+                further.school.clone(),
+                further.country.clone(),
+                further.year.map(|year| year.get().to_string()),
+            ]
+            .into_iter()
+            .map(optional)
+            .collect();
+            fields.push((format!("degree {}", n + 2), parts.join(", ")));
+        }
+        if record.dissertations.is_empty() {
+            fields.push(("dissertation".to_string(), MISSING.to_string()));
+        }
+        for title in &record.dissertations {
+            fields.push(("dissertation".to_string(), title.clone()));
+        }
+        fields.push((
+            "written".to_string(),
+            optional(record.dissertation_year.map(|year| year.get().to_string())),
+        ));
+        if record.advisors.is_empty() {
+            fields.push(("advisor".to_string(), MISSING.to_string()));
+        }
+        for advisor in &record.advisors {
+            fields.push((
+                format!("advisor {}", advisor.order),
+                link(&advisor.name, Some(advisor.id)),
+            ));
+        }
+        fields.push((
+            "contributor".to_string(),
+            optional(record.contributor.clone()),
+        ));
+        fields.push((
+            "updated".to_string(),
+            optional(record.source_updated.map(|date| date.to_string())),
+        ));
+        fields.push((
+            "students".to_string(),
+            format!("{} ({:?})", record.students.len(), record.students_section),
+        ));
+        for (n, student) in record.students.iter().enumerate() {
+            let school = student.school.as_deref().unwrap_or(MISSING);
+            let year = student
+                .year
+                .map_or(MISSING.to_string(), |year| year.get().to_string());
+            fields.push((
+                format!("student {}", n + 1),
+                link(&format!("{}, {school}, {year}", student.name), student.id),
+            ));
+        }
+
+        Self { url, fields }
+    }
+}
+
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self.fields.iter().map(|(label, _)| label.len()).max();
+        let width = width.unwrap_or(0).max("page".len());
+        let rule = "=".repeat(72);
+
+        writeln!(f, "{rule}")?;
+        writeln!(f, "{:width$}  {}", "page", self.url)?;
+        writeln!(f, "{rule}")?;
+        for (label, value) in &self.fields {
+            writeln!(f, "{label:width$}  {value}")?;
+        }
+        writeln!(f, "{rule}")
+    }
+}
+
+/// Fetches and parses the record of `id`, every page of its students included, the same way a
+/// scrape does. Nothing is written anywhere
+pub async fn run(scraper: &Scraper, id: Id) -> color_eyre::Result<Report> {
+    let (record, url) = scraper.fetch_record(id).await?;
+    Ok(Report::new(url, &record, scraper.source.as_ref()))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scrape::test::fixture;
+    use crate::scrape::test::scraper;
+    use crate::scrape::Databases;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+
+    #[tokio::test]
+    async fn labels_every_field_next_to_the_url() {
+        let server = MockServer::start().await;
+        Mock::given(path("/id.php"))
+            .and(query_param("id", "1"))
+            .respond_with(fixture("two-degrees.html"))
+            .mount(&server)
+            .await;
+        let scraper = scraper(Databases::unconnected().write, &server);
+
+        let report = run(&scraper, Id(1)).await.unwrap();
+        assert_eq!(report.url.as_str(), format!("{}/id.php?id=1", server.uri()));
+        let field = |label: &str| {
+            report
+                .fields
+                .iter()
+                .find(|(l, _)| l == label)
+                .map(|(_, value)| value.as_str())
+        };
+        assert_eq!(
+            field("degree 2"),
+            Some("M.Sc., Universidade de Lisboa, Portugal, 1999")
+        );
+        assert_eq!(field("written"), Some(MISSING));
+        assert_eq!(
+            field("student 1"),
+            Some(
+                format!(
+                    "George Hutchinson, University of Guelph, 2018  <{}/id.php?id=235835>",
+                    server.uri()
+                )
+                .as_str()
+            )
+        );
+
+        // the url comes first, under a rule of its own
+        let printed = report.to_string();
+        let page = printed.lines().nth(1).unwrap();
+        assert!(page.starts_with("page "));
+        assert!(page.ends_with(&format!(" {}/id.php?id=1", server.uri())));
+
+        // nothing was connected to, let alone written
+        assert_eq!(scraper.db.write.size(), 0);
+    }
+}
@@ -0,0 +1,715 @@
+use std::collections::HashSet;
+use std::fmt;
+use std::time::Duration;
+
+use chrono::DateTime;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use sqlx::PgPool;
+
+use crate::mathematician::Listing;
+use crate::parser::Id;
+use crate::parser::ScrapeFields;
+use crate::parser::Year;
+
+/// A field of a scraped record that can be missing from the database
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum MissingField {
+    School,
+    Year,
+    Country,
+    Dissertation,
+}
+
+/// The mathematicians stored without `field`, in id order. Schools and years are only stored
+/// together as a graduation record and countries through the school's location, so missing
+/// either of them also counts as missing the country
+pub async fn ids_missing_field(pool: &PgPool, field: MissingField) -> color_eyre::Result<Vec<Id>> {
+    let ids = match field {
+        MissingField::School | MissingField::Year => {
+            sqlx::query_scalar!(
+                r"SELECT id FROM mathematicians m
+                WHERE NOT EXISTS (SELECT 1 FROM graduation_records g WHERE g.mathematician = m.id)
+                ORDER BY id;"
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        MissingField::Country => {
+            sqlx::query_scalar!(
+                r"SELECT id FROM mathematicians m
+                WHERE NOT EXISTS (
+                    SELECT 1 FROM graduation_records g
+                    JOIN school_locations l ON l.school = g.school
+                    WHERE g.mathematician = m.id
+                )
+                ORDER BY id;"
+            )
+            .fetch_all(pool)
+            .await?
+        }
+        MissingField::Dissertation => {
+            sqlx::query_scalar!(
+                r"SELECT id FROM mathematicians m
+                WHERE NOT EXISTS (SELECT 1 FROM dissertations d WHERE d.author = m.id)
+                ORDER BY id;"
+            )
+            .fetch_all(pool)
+            .await?
+        }
+    };
+
+    Ok(ids.into_iter().map(Id).collect())
+}
+
+/// The primary advisor of `id`'s dissertation, the one the page labels "Advisor 1" or its only
+/// advisor, `None` without a stored one
+pub async fn primary_advisor(pool: &PgPool, id: Id) -> color_eyre::Result<Option<Id>> {
+    let advisor = sqlx::query_scalar!(
+        r#"SELECT advisor AS "advisor: Id" FROM dissertation_advisors WHERE author = $1
+        ORDER BY rank, advisor LIMIT 1;"#,
+        id as Id,
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    Ok(advisor)
+}
+
+/// A school's name reduced to what tells schools apart, so that spellings of the same school
+/// compare equal: case, accents, punctuation, a leading "The", "&" for "and" and the common
+/// abbreviations don't count
+pub fn normalize_school(name: &str) -> String {
+    let folded: String = name
+        .to_lowercase()
+        .replace('&', " and ")
+        .chars()
+        .map(|c| match c {
+            'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+            'ç' => 'c',
+            'è' | 'é' | 'ê' | 'ë' => 'e',
+            'ì' | 'í' | 'î' | 'ï' => 'i',
+            'ñ' => 'n',
+            'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+            'ù' | 'ú' | 'û' | 'ü' => 'u',
+            'ý' | 'ÿ' => 'y',
+            c if c.is_alphanumeric() => c,
+            _ => ' ',
+        })
+        .collect();
+
+    let mut words: Vec<&str> = folded.split_whitespace().collect();
+    if words.first() == Some(&"the") {
+        words.remove(0);
+    }
+    let words: Vec<&str> = words
+        .into_iter()
+        .map(|word| match word {
+            "univ" => "university",
+            "inst" => "institute",
+            "coll" => "college",
+            word => word,
+        })
+        .collect();
+    words.join(" ")
+}
+
+/// Everyone who graduated from `school`, or any stored spelling of it that [`normalize_school`]
+/// takes as the same, earliest graduation first. Each comes with the school as stored and the
+/// year, once even with several degrees from there
+pub async fn mathematicians_at_school(
+    pool: &PgPool,
+    school: &str,
+) -> color_eyre::Result<Vec<Listing>> {
+    let wanted = normalize_school(school);
+    let schools: Vec<i32> = sqlx::query!("SELECT id, name FROM schools;")
+        .fetch_all(pool)
+        .await?
+        .into_iter()
+        .filter(|school| normalize_school(&school.name) == wanted)
+        .map(|school| school.id)
+        .collect();
+
+    let rows = sqlx::query!(
+        r#"SELECT id AS "id!", name AS "name!", school AS "school!", year AS "year!" FROM (
+            SELECT DISTINCT ON (m.id) m.id, m.name, s.name AS school, g.year
+            FROM graduation_records g
+            JOIN mathematicians m ON m.id = g.mathematician
+            JOIN schools s ON s.id = g.school
+            WHERE g.school = ANY($1)
+            ORDER BY m.id, g.year, s.name
+        ) t
+        ORDER BY year, name, id;"#,
+        &schools,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Listing {
+            id: Id(row.id),
+            name: row.name,
+            school: Some(row.school),
+            year: Year::try_from(row.year).ok(),
+        })
+        .collect())
+}
+
+/// Everyone stored on neither side of an advisor relation, by id, with the graduation of their
+/// first degree when there is one. Often records whose scrape stored the mathematician but not their
+/// connections, worth scraping again
+pub async fn orphans(pool: &PgPool) -> color_eyre::Result<Vec<Listing>> {
+    let rows = sqlx::query!(
+        r#"SELECT m.id, m.name, g.school AS "school?", g.year AS "year?"
+        FROM mathematicians m
+        LEFT JOIN LATERAL (
+            SELECT s.name AS school, g.year
+            FROM graduation_records g
+            JOIN schools s ON s.id = g.school
+            WHERE g.mathematician = m.id
+            ORDER BY g.position, g.year, s.name
+            LIMIT 1
+        ) g ON true
+        WHERE NOT EXISTS(SELECT 1 FROM advisor_relations r WHERE r.advisor = m.id)
+            AND NOT EXISTS(SELECT 1 FROM advisor_relations r WHERE r.advisee = m.id)
+        ORDER BY m.id;"#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Listing {
+            id: Id(row.id),
+            name: row.name,
+            school: row.school,
+            year: row.year.and_then(|year| Year::try_from(year).ok()),
+        })
+        .collect())
+}
+
+/// How many mathematicians first graduated in each bucket of years, to see at a glance when the
+/// stored genealogy lives and to spot implausible spikes such as ids parsed as years
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct YearHistogram {
+    /// Years per bucket
+    pub width: u16,
+    /// The first year of each bucket with anyone in it, earliest first, with how many
+    pub buckets: Vec<(i16, i64)>,
+    /// Mathematicians stored without any graduation
+    pub unknown: i64,
+}
+
+impl fmt::Display for YearHistogram {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        /// The longest bar, which the largest bucket gets
+        const BAR: i64 = 50;
+
+        let largest = self.buckets.iter().map(|&(_, n)| n).max().unwrap_or(0);
+        let digits = largest.max(self.unknown).to_string().len();
+        for &(start, n) in &self.buckets {
+            let end = i32::from(start) + i32::from(self.width) - 1;
+            let bar = "#".repeat(((n * BAR + largest - 1) / largest) as usize);
+            writeln!(f, "{start}-{end:<4}  {n:>digits$}  {bar}")?;
+        }
+        writeln!(f, "{:9}  {:>digits$}", "unknown", self.unknown)
+    }
+}
+
+/// Counts the stored mathematicians by their earliest graduation year in buckets of `width`
+/// years starting at multiples of it, those without a graduation apart
+pub async fn year_histogram(pool: &PgPool, width: u16) -> color_eyre::Result<YearHistogram> {
+    let rows = sqlx::query!(
+        r#"SELECT (g.year / $1) * $1 AS "bucket: i32", count(*) AS "count!"
+        FROM mathematicians m
+        LEFT JOIN LATERAL (
+            SELECT min(g.year)::integer AS year FROM graduation_records g
+            WHERE g.mathematician = m.id
+        ) g ON true
+        GROUP BY 1
+        ORDER BY 1 NULLS LAST;"#,
+        i32::from(width),
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let mut histogram = YearHistogram {
+        width,
+        buckets: vec![],
+        unknown: 0,
+    };
+    for row in rows {
+        match row.bucket {
+            Some(bucket) => histogram.buckets.push((i16::try_from(bucket)?, row.count)),
+            None => histogram.unknown = row.count,
+        }
+    }
+    Ok(histogram)
+}
+
+/// Every id stored with all of `fields`, loaded once so the scan doesn't need a query per id to
+/// skip them. Records whose chunked insert was interrupted aren't stored yet, the scan resumes
+/// them, and neither are records a crawl for fewer fields stored. Ids known to redirect to another
+/// record are skipped too, their record is stored under its canonical id. The rows are collected
+/// before the set is built at its full size, which `cargo bench --bench ids` measured as faster
+/// than adding each id as it's streamed or read with `COPY`
+pub async fn known_ids(pool: &PgPool, fields: ScrapeFields) -> color_eyre::Result<HashSet<Id>> {
+    Ok(sqlx::query_scalar!(
+        r#"SELECT id AS "id!" FROM mathematicians
+        WHERE id NOT IN (SELECT advisor FROM scrape_progress)
+            AND (scraped_fields IS NULL OR scraped_fields & $1 = $1)
+        UNION ALL SELECT alias FROM canonical_id_aliases;"#,
+        i16::from(fields.bits()),
+    )
+    .fetch_all(pool)
+    .await?
+    .into_iter()
+    .map(Id)
+    .collect())
+}
+
+/// The words of a name search as a full text query matching names with a word starting with each
+/// of them, in any order. Punctuation separates words like it does in the stored names, so
+/// "Pereira, Rajesh" is the words of "Rajesh Pereira". `None` when the search has no words
+pub fn name_search_query(search: &str) -> Option<String> {
+    let words: Vec<String> = search
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(|word| format!("{}:*", word.to_lowercase()))
+        .collect();
+    (!words.is_empty()).then(|| words.join(" & "))
+}
+
+/// Up to `limit` mathematicians whose name has every word of `search` in any order, or the start
+/// of it, by name, each with the graduation of their first degree when there is one. Names are
+/// stored given names first, so this is how to find someone by surname or by a "Surname, Given"
+/// search
+pub async fn search_names(
+    pool: &PgPool,
+    search: &str,
+    limit: i64,
+) -> color_eyre::Result<Vec<Listing>> {
+    let query = name_search_query(search).ok_or(eyre!("The search {search:?} has no words"))?;
+    let rows = sqlx::query!(
+        r#"SELECT m.id, m.name, g.school AS "school?", g.year AS "year?"
+        FROM mathematicians m
+        LEFT JOIN LATERAL (
+            SELECT s.name AS school, g.year
+            FROM graduation_records g
+            JOIN schools s ON s.id = g.school
+            WHERE g.mathematician = m.id
+            ORDER BY g.position, g.year, s.name
+            LIMIT 1
+        ) g ON true
+        WHERE to_tsvector('simple', m.name) @@ to_tsquery('simple', $1)
+        ORDER BY m.name, m.id
+        LIMIT $2;"#,
+        query,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(rows
+        .into_iter()
+        .map(|row| Listing {
+            id: Id(row.id),
+            name: row.name,
+            school: row.school,
+            year: row.year.and_then(|year| Year::try_from(year).ok()),
+        })
+        .collect())
+}
+
+/// How long the phases of a scrape took. Fetching covers the record's page and its students',
+/// parsing includes following the further pages of a long students table
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct ScrapeTimings {
+    pub fetch: Duration,
+    pub parse: Duration,
+    pub insert: Duration,
+}
+
+impl ScrapeTimings {
+    pub fn total(&self) -> Duration {
+        self.fetch + self.parse + self.insert
+    }
+}
+
+/// The `n` ids whose last recorded scrape took longest, slowest first
+pub async fn slowest_scrapes(
+    pool: &PgPool,
+    n: i64,
+) -> color_eyre::Result<Vec<(Id, ScrapeTimings)>> {
+    let rows = sqlx::query!(
+        r"SELECT id, fetch_ms, parse_ms, insert_ms FROM scrape_timings
+        ORDER BY fetch_ms + parse_ms + insert_ms DESC, id
+        LIMIT $1;",
+        n,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let ms = |ms: i32| Duration::from_millis(ms.max(0) as u64);
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let timings = ScrapeTimings {
+                fetch: ms(row.fetch_ms),
+                parse: ms(row.parse_ms),
+                insert: ms(row.insert_ms),
+            };
+            (Id(row.id), timings)
+        })
+        .collect())
+}
+
+/// What a scrape run did, as summarized at its end
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, serde::Serialize)]
+pub struct RunStats {
+    pub scraped: u64,
+    /// Pages that were the same as when they were last scraped
+    pub unchanged: u64,
+    /// Records dropped by the filters
+    pub filtered: u64,
+    /// Ids whose scrape failed, the missing ones included
+    pub failed: u64,
+    /// Ids the site has no record for
+    pub missing: u64,
+    /// Bytes of the pages fetched, after decompression
+    pub downloaded: u64,
+}
+
+/// A scrape run recorded with `--run-label`
+#[derive(Debug, PartialEq, Eq, Clone, serde::Serialize)]
+pub struct ScrapeRun {
+    pub id: i32,
+    pub label: String,
+    pub started_at: DateTime<Utc>,
+    /// `None` while the run goes on, or when it never finished
+    pub finished_at: Option<DateTime<Utc>>,
+    pub stats: Option<RunStats>,
+}
+
+/// Every recorded scrape run, the latest first
+pub async fn scrape_runs(pool: &PgPool) -> color_eyre::Result<Vec<ScrapeRun>> {
+    let rows = sqlx::query!(
+        r"SELECT id, label, started_at, finished_at, scraped, unchanged, filtered, failed, missing,
+            downloaded
+        FROM scrape_runs
+        ORDER BY started_at DESC, id DESC;"
+    )
+    .fetch_all(pool)
+    .await?;
+
+    let count = |n: i64| n.max(0) as u64;
+    Ok(rows
+        .into_iter()
+        .map(|row| {
+            let stats = row.finished_at.map(|_| RunStats {
+                scraped: count(row.scraped.unwrap_or_default()),
+                unchanged: count(row.unchanged.unwrap_or_default()),
+                filtered: count(row.filtered.unwrap_or_default()),
+                failed: count(row.failed.unwrap_or_default()),
+                missing: count(row.missing.unwrap_or_default()),
+                downloaded: count(row.downloaded.unwrap_or_default()),
+            });
+            ScrapeRun {
+                id: row.id,
+                label: row.label,
+                started_at: row.started_at,
+                finished_at: row.finished_at,
+                stats,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use sqlx::Executor;
+
+    #[sqlx::test]
+    async fn ids_round_trip_through_the_database(pool: PgPool) {
+        sqlx::query!(
+            "INSERT INTO mathematicians(id, name) VALUES ($1, 'Rajesh Pereira');",
+            Id(258) as Id,
+        )
+        .execute(&pool)
+        .await
+        .unwrap();
+
+        let id = sqlx::query_scalar!(
+            r#"SELECT id AS "id: Id" FROM mathematicians WHERE id = $1;"#,
+            Id(258) as Id,
+        )
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+        assert_eq!(id, Id(258));
+
+        // the runtime checked queries bind and decode it too
+        let id: Id = sqlx::query_scalar("SELECT id FROM mathematicians WHERE id = $1;")
+            .bind(Id(258))
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(id, Id(258));
+    }
+
+    #[sqlx::test]
+    async fn finds_ids_missing_each_field(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES (1, 'Complete'), (2, 'No Country'), (3, 'Bare');
+            INSERT INTO schools(name) VALUES ('University of Toronto'), ('Nowhere University');
+            INSERT INTO countries(name) VALUES ('Canada');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES (1, 1, 2003), (2, 2, 1990);
+            INSERT INTO school_locations(school, country) VALUES (1, 1);
+            INSERT INTO dissertations(title, author) VALUES ('Trace Vectors in Matrix Analysis', 1);",
+        )
+        .await
+        .unwrap();
+
+        let missing = |field| ids_missing_field(&pool, field);
+        assert_eq!(missing(MissingField::School).await.unwrap(), [Id(3)]);
+        assert_eq!(missing(MissingField::Year).await.unwrap(), [Id(3)]);
+        assert_eq!(
+            missing(MissingField::Country).await.unwrap(),
+            [Id(2), Id(3)]
+        );
+        assert_eq!(
+            missing(MissingField::Dissertation).await.unwrap(),
+            [Id(2), Id(3)]
+        );
+    }
+
+    #[sqlx::test]
+    async fn primary_advisor_is_advisor_1(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES (1, 'Rajesh Pereira');
+            INSERT INTO dissertations(title, author) VALUES ('Trace Vectors', 1);
+            INSERT INTO dissertation_advisors(title, author, advisor, rank) VALUES
+                ('Trace Vectors', 1, 6807, 2), ('Trace Vectors', 1, 15957, 1);",
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            primary_advisor(&pool, Id(1)).await.unwrap(),
+            Some(Id(15957))
+        );
+        assert_eq!(primary_advisor(&pool, Id(2)).await.unwrap(), None);
+    }
+
+    #[test]
+    fn spellings_of_a_school_normalize_the_same() {
+        let toronto = normalize_school("University of Toronto");
+        assert_eq!(toronto, "university of toronto");
+        for spelling in [
+            "The University of Toronto",
+            "University Of  Toronto.",
+            "Univ. of Toronto",
+            "university-of-toronto",
+        ] {
+            assert_eq!(normalize_school(spelling), toronto, "for {spelling:?}");
+        }
+        assert_eq!(
+            normalize_school("Université de Montréal"),
+            normalize_school("Universite de Montreal")
+        );
+        assert_eq!(
+            normalize_school("Texas A&M University"),
+            "texas a and m university"
+        );
+        assert_ne!(normalize_school("Toronto Metropolitan University"), toronto);
+    }
+
+    #[sqlx::test]
+    async fn mathematicians_at_school_match_every_spelling(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES
+                (1, 'Early'), (2, 'Late'), (3, 'Elsewhere'), (4, 'Twice');
+            INSERT INTO schools(name) VALUES
+                ('University of Toronto'), ('The University of Toronto'),
+                ('McGill University');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES
+                (1, 2, 1950), (2, 1, 2003), (3, 3, 1990), (4, 1, 1999), (4, 1, 1995);",
+        )
+        .await
+        .unwrap();
+
+        let found: Vec<_> = mathematicians_at_school(&pool, "university of toronto")
+            .await
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            [
+                "Early (1, The University of Toronto 1950)",
+                "Twice (4, University of Toronto 1995)",
+                "Late (2, University of Toronto 2003)",
+            ]
+        );
+        assert!(mathematicians_at_school(&pool, "Harvard University")
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[sqlx::test]
+    async fn orphans_have_neither_advisors_nor_students(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES
+                (1, 'Advisor'), (2, 'Student'), (3, 'Alone'), (4, 'Graduated alone');
+            INSERT INTO advisor_relations(advisor, advisee) VALUES (1, 2);
+            INSERT INTO schools(name) VALUES ('McGill University');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES
+                (4, 1, 1990), (4, 1, 1985), (1, 1, 1960);",
+        )
+        .await
+        .unwrap();
+
+        let found: Vec<_> = orphans(&pool)
+            .await
+            .unwrap()
+            .iter()
+            .map(ToString::to_string)
+            .collect();
+        assert_eq!(
+            found,
+            ["Alone (3)", "Graduated alone (4, McGill University 1985)"]
+        );
+    }
+
+    #[sqlx::test]
+    async fn years_are_counted_by_earliest_graduation(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES
+                (1, 'Euler'), (2, 'Gauss'), (3, 'Hilbert'), (4, 'Noether'), (5, 'Unknown');
+            INSERT INTO schools(name) VALUES ('Universität Basel');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES
+                (1, 1, 1726), (2, 1, 1799), (3, 1, 1885), (3, 1, 1862), (4, 1, 1907);",
+        )
+        .await
+        .unwrap();
+
+        let decades = year_histogram(&pool, 10).await.unwrap();
+        assert_eq!(
+            decades.buckets,
+            [(1720, 1), (1790, 1), (1860, 1), (1900, 1)]
+        );
+        assert_eq!(decades.unknown, 1);
+
+        let centuries = year_histogram(&pool, 100).await.unwrap();
+        assert_eq!(centuries.buckets, [(1700, 2), (1800, 1), (1900, 1)]);
+        assert_eq!(
+            centuries.to_string(),
+            "1700-1799  2  ##################################################
+1800-1899  1  #########################
+1900-1999  1  #########################
+unknown    1
+"
+        );
+    }
+
+    #[sqlx::test]
+    async fn known_ids_are_every_id_whose_insert_finished(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name)
+                SELECT id, 'Mathematician ' || id FROM generate_series(1, 50000) id;
+            INSERT INTO mathematicians(id, name) VALUES (2147483647, 'Last');
+            INSERT INTO scrape_progress(advisor, students_committed, students_total)
+                VALUES (7, 100, 1000);
+            INSERT INTO canonical_id_aliases(alias, canonical) VALUES (60000, 1);",
+        )
+        .await
+        .unwrap();
+
+        let known = known_ids(&pool, ScrapeFields::ALL).await.unwrap();
+        assert_eq!(known.len(), 50001);
+        assert!(known.contains(&Id(60000)));
+        assert!(known.contains(&Id(1)));
+        assert!(known.contains(&Id(50000)));
+        assert!(known.contains(&Id(i32::MAX)));
+        assert!(!known.contains(&Id(7)));
+        assert!(!known.contains(&Id(50001)));
+    }
+
+    #[test]
+    fn name_searches_are_split_into_words() {
+        assert_eq!(
+            name_search_query("Pereira, Rajesh").as_deref(),
+            Some("pereira:* & rajesh:*")
+        );
+        assert_eq!(
+            name_search_query("O'Connor").as_deref(),
+            Some("o:* & connor:*")
+        );
+        assert_eq!(name_search_query(" , ' "), None);
+    }
+
+    #[sqlx::test]
+    async fn names_are_found_by_their_words_in_any_order(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES
+                (258, 'Rajesh Pereira'), (2, 'Rajesh Kumar'), (3, 'Carlos Pereira Santos'),
+                (4, 'Jean-Pierre Serre'), (5, 'Kurt Gödel');
+            INSERT INTO schools(name) VALUES ('University of Toronto');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES (258, 1, 2003);",
+        )
+        .await
+        .unwrap();
+
+        let search = |search: &'static str| {
+            let pool = pool.clone();
+            async move {
+                search_names(&pool, search, 10)
+                    .await
+                    .unwrap()
+                    .into_iter()
+                    .map(|mathematician| mathematician.id.0)
+                    .collect::<Vec<_>>()
+            }
+        };
+        // surname only
+        assert_eq!(search("Pereira").await, [3, 258]);
+        // given name only
+        assert_eq!(search("rajesh").await, [2, 258]);
+        // reversed, the way the site lists names
+        assert_eq!(search("Pereira, Rajesh").await, [258]);
+        assert_eq!(search("Rajesh Pereira").await, [258]);
+        // the start of a word, and either half of a hyphenated one
+        assert_eq!(search("Per Raj").await, [258]);
+        assert_eq!(search("Serre, Pierre").await, [4]);
+        assert_eq!(search("Gödel").await, [5]);
+        assert!(search("Pereira Kumar").await.is_empty());
+
+        let found = search_names(&pool, "Pereira, Rajesh", 10).await.unwrap();
+        assert_eq!(
+            found[0].to_string(),
+            "Rajesh Pereira (258, University of Toronto 2003)"
+        );
+        assert!(search_names(&pool, "--", 10).await.is_err());
+    }
+
+    #[sqlx::test]
+    async fn slowest_scrapes_come_first(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO scrape_timings(id, fetch_ms, parse_ms, insert_ms) VALUES
+                (1, 100, 10, 10), (2, 2000, 50, 300), (3, 10, 500, 10);",
+        )
+        .await
+        .unwrap();
+
+        let slowest = slowest_scrapes(&pool, 2).await.unwrap();
+        let ids: Vec<_> = slowest.iter().map(|(id, _)| *id).collect();
+        assert_eq!(ids, [Id(2), Id(3)]);
+        assert_eq!(slowest[0].1.total(), Duration::from_millis(2350));
+        assert_eq!(slowest[1].1.parse, Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,325 @@
+use std::collections::HashMap;
+
+use futures::stream;
+use futures::Stream;
+use futures::StreamExt;
+use futures::TryStreamExt;
+use sqlx::PgPool;
+use tracing::warn;
+
+use crate::parser::Advisor;
+use crate::parser::Degree;
+use crate::parser::Id;
+use crate::parser::ScrapeRecord;
+use crate::parser::Student;
+use crate::parser::StudentsSection;
+use crate::parser::Year;
+
+/// How many mathematicians are read per query
+const PAGE_SIZE: i64 = 500;
+
+/// Every stored mathematician as a record rebuilt from the database, in id order. Only a page of
+/// them is held in memory at a time, fetched with keyset pagination on the id so rows written
+/// while the stream is consumed neither shift nor repeat the ones after them.
+///
+/// The database doesn't keep everything a page had: students without an id are lost, degree lines
+/// without a school or year are lost apart from the first one's title, and records without
+/// students come back as [`StudentsSection::NoneKnown`]. Mathematicians only stored as someone's
+/// student come back with just their name
+pub fn stream_records(
+    pool: &PgPool,
+) -> impl Stream<Item = color_eyre::Result<(Id, ScrapeRecord)>> + '_ {
+    stream_pages(pool, PAGE_SIZE)
+}
+
+fn stream_pages(
+    pool: &PgPool,
+    page_size: i64,
+) -> impl Stream<Item = color_eyre::Result<(Id, ScrapeRecord)>> + '_ {
+    stream::try_unfold(Some(i32::MIN), move |after| {
+        next_page(pool, after, page_size)
+    })
+    .map_ok(|page| stream::iter(page).map(Ok))
+    .try_flatten()
+}
+
+/// The page after `after` along with the id the following one starts after, `None` once the
+/// page before was the last
+async fn next_page(
+    pool: &PgPool,
+    after: Option<i32>,
+    page_size: i64,
+) -> color_eyre::Result<Option<(Vec<(Id, ScrapeRecord)>, Option<i32>)>> {
+    let Some(after) = after else {
+        return Ok(None);
+    };
+    let page = fetch_page(pool, after, page_size).await?;
+    let next = match page.last() {
+        Some((id, _)) if page.len() as i64 == page_size => Some(id.0),
+        _ => None,
+    };
+    Ok(Some((page, next)))
+}
+
+/// The records of the first `page_size` mathematicians with an id above `after`
+async fn fetch_page(
+    pool: &PgPool,
+    after: i32,
+    page_size: i64,
+) -> color_eyre::Result<Vec<(Id, ScrapeRecord)>> {
+    let mathematicians = sqlx::query!(
+        r"SELECT id, name, degree, contributor, source_updated FROM mathematicians
+        WHERE id > $1 ORDER BY id LIMIT $2;",
+        after,
+        page_size,
+    )
+    .fetch_all(pool)
+    .await?;
+    let ids: Vec<i32> = mathematicians.iter().map(|m| m.id).collect();
+
+    let mut records: HashMap<i32, ScrapeRecord> = mathematicians
+        .into_iter()
+        .map(|m| {
+            let record = ScrapeRecord {
+                name: m.name,
+                students: vec![],
+                students_section: StudentsSection::NoneKnown,
+                dissertations: vec![],
+                dissertation_year: None,
+                advisors: vec![],
+                school: None,
+                country: None,
+                year: None,
+                degree: m.degree,
+                further_degrees: vec![],
+                contributor: m.contributor,
+                source_updated: m.source_updated,
+            };
+            (m.id, record)
+        })
+        .collect();
+
+    // the graduation records in the order of their lines, the first leading and the rest the
+    // further degrees. A line a re-scrape left an old record of is read once, from the record
+    // `rebuild-graduation-records` would keep
+    let graduations = sqlx::query!(
+        r#"SELECT DISTINCT ON (g.mathematician, g.position) g.mathematician,
+            s.name AS school, g.year, g.degree, c.name AS "country?"
+        FROM graduation_records g
+        JOIN schools s ON s.id = g.school
+        LEFT JOIN school_locations l ON l.school = g.school
+        LEFT JOIN countries c ON c.id = l.country
+        WHERE g.mathematician = ANY($1)
+        ORDER BY g.mathematician, g.position, g.year, s.name, c.name;"#,
+        &ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for graduation in graduations {
+        let record = records.get_mut(&graduation.mathematician).unwrap();
+        let year = stored_year(graduation.mathematician, graduation.year);
+        if record.school.is_some() {
+            record.further_degrees.push(Degree {
+                degree: graduation.degree,
+                school: Some(graduation.school),
+                country: graduation.country,
+                year,
+            });
+            continue;
+        }
+        record.school = Some(graduation.school);
+        record.year = year;
+        record.country = graduation.country;
+    }
+
+    // the page order isn't stored, but only the first dissertation is stored with advisors, so
+    // that one leads and gives the record its dissertation year
+    let dissertations = sqlx::query!(
+        r#"SELECT author, title, year,
+            EXISTS(
+                SELECT 1 FROM dissertation_advisors a WHERE a.title = d.title AND a.author = d.author
+            ) AS "advised!"
+        FROM dissertations d
+        WHERE author = ANY($1)
+        ORDER BY author, 4 DESC, title;"#,
+        &ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for dissertation in dissertations {
+        let record = records.get_mut(&dissertation.author).unwrap();
+        if record.dissertations.is_empty() {
+            record.dissertation_year = dissertation
+                .year
+                .and_then(|year| stored_year(dissertation.author, year));
+        }
+        record.dissertations.push(dissertation.title);
+    }
+
+    let advisors = sqlx::query!(
+        r"SELECT d.author, d.title, d.advisor, d.rank, m.name FROM dissertation_advisors d
+        JOIN mathematicians m ON m.id = d.advisor
+        WHERE d.author = ANY($1)
+        ORDER BY d.author, d.rank;",
+        &ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for advisor in advisors {
+        let record = records.get_mut(&advisor.author).unwrap();
+        if record.dissertations.first() == Some(&advisor.title) {
+            record.advisors.push(Advisor {
+                name: advisor.name,
+                id: Id(advisor.advisor),
+                order: advisor.rank as u32,
+            });
+        }
+    }
+
+    let students = sqlx::query!(
+        r#"SELECT r.advisor, r.advisee, m.name, g.school AS "school?", g.year AS "year?"
+        FROM advisor_relations r
+        JOIN mathematicians m ON m.id = r.advisee
+        LEFT JOIN LATERAL (
+            SELECT s.name AS school, g.year FROM graduation_records g
+            JOIN schools s ON s.id = g.school
+            WHERE g.mathematician = r.advisee
+            ORDER BY g.position, g.year, s.name
+            LIMIT 1
+        ) g ON true
+        WHERE r.advisor = ANY($1)
+        ORDER BY r.advisor, r.rank NULLS LAST, m.name;"#,
+        &ids,
+    )
+    .fetch_all(pool)
+    .await?;
+    for student in students {
+        let record = records.get_mut(&student.advisor).unwrap();
+        record.students.push(Student {
+            name: student.name,
+            id: Some(Id(student.advisee)),
+            school: student.school,
+            year: student
+                .year
+                .and_then(|year| stored_year(student.advisee, year)),
+        });
+        record.students_section = StudentsSection::Listed;
+    }
+
+    let mut page: Vec<_> = records
+        .into_iter()
+        .map(|(id, record)| (Id(id), record))
+        .collect();
+    page.sort_by_key(|(id, _)| id.0);
+    Ok(page)
+}
+
+/// A year read back from the database, `None` with a warning when it isn't plausible, which only
+/// a row written by something other than the scraper can be
+fn stored_year(id: i32, year: i16) -> Option<Year> {
+    Year::try_from(year)
+        .inspect_err(|e| warn!("Ignoring the year stored for {id}: {e}"))
+        .ok()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use sqlx::Executor;
+
+    #[sqlx::test]
+    async fn streams_rebuilt_records_across_pages(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name, degree) VALUES
+                (1, 'Advisor', 'Ph.D.'), (2, 'First Student', NULL), (3, 'Second Student', NULL),
+                (4, 'Loner', NULL);
+            INSERT INTO advisor_relations(advisor, advisee, rank) VALUES (1, 3, 1), (1, 2, 2);
+            INSERT INTO schools(name) VALUES ('University of Toronto'), ('McGill University');
+            INSERT INTO countries(name) VALUES ('Canada');
+            INSERT INTO graduation_records(mathematician, school, year, degree) VALUES
+                (1, 1, 2003, 'Ph.D.'), (3, 2, 2010, NULL);
+            INSERT INTO school_locations(school, country) VALUES (1, 1);
+            INSERT INTO dissertations(title, author, year) VALUES
+                ('Trace Vectors', 1, 2002), ('A Masters Thesis', 1, 1998);
+            INSERT INTO dissertation_advisors(title, author, advisor, rank) VALUES
+                ('Trace Vectors', 1, 4, 1);",
+        )
+        .await
+        .unwrap();
+
+        // a page size of 3 makes the last record come from a second page
+        let records: Vec<_> = stream_pages(&pool, 3).try_collect().await.unwrap();
+        let ids: Vec<_> = records.iter().map(|(id, _)| id.0).collect();
+        assert_eq!(ids, [1, 2, 3, 4]);
+
+        let advisor = &records[0].1;
+        assert_eq!(advisor.name, "Advisor");
+        assert_eq!(advisor.school.as_deref(), Some("University of Toronto"));
+        assert_eq!(advisor.country.as_deref(), Some("Canada"));
+        assert_eq!(advisor.year, Year::new(2003));
+        assert_eq!(advisor.degree.as_deref(), Some("Ph.D."));
+        // the dissertation with advisors leads, though another sorts before it
+        assert_eq!(advisor.dissertations, ["Trace Vectors", "A Masters Thesis"]);
+        assert_eq!(advisor.dissertation_year, Year::new(2002));
+        assert_eq!(
+            advisor.advisors,
+            [Advisor {
+                name: "Loner".to_string(),
+                id: Id(4),
+                order: 1,
+            }]
+        );
+        assert_eq!(advisor.students_section, StudentsSection::Listed);
+        assert_eq!(
+            advisor.students,
+            [
+                Student {
+                    name: "Second Student".to_string(),
+                    id: Some(Id(3)),
+                    school: Some("McGill University".to_string()),
+                    year: Year::new(2010),
+                },
+                Student {
+                    name: "First Student".to_string(),
+                    id: Some(Id(2)),
+                    school: None,
+                    year: None,
+                },
+            ]
+        );
+
+        let loner = &records[3].1;
+        assert_eq!(loner.name, "Loner");
+        assert!(loner.students.is_empty());
+        assert_eq!(loner.students_section, StudentsSection::NoneKnown);
+        assert_eq!(loner.school, None);
+    }
+
+    #[sqlx::test]
+    async fn implausible_stored_years_are_skipped(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES (1, 'Latest'), (2, 'Too Late');
+            INSERT INTO schools(name) VALUES ('University of Toronto');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES (1, 1, 2100), (2, 1, 2101);
+            INSERT INTO dissertations(title, author, year) VALUES ('Far Future', 2, 32767);",
+        )
+        .await
+        .unwrap();
+
+        let records: Vec<_> = stream_records(&pool).try_collect().await.unwrap();
+        assert_eq!(records[0].1.year, Year::new(Year::MAX));
+        // the rest of the record survives the bad years
+        let too_late = &records[1].1;
+        assert_eq!(too_late.year, None);
+        assert_eq!(too_late.school.as_deref(), Some("University of Toronto"));
+        assert_eq!(too_late.dissertations, ["Far Future"]);
+        assert_eq!(too_late.dissertation_year, None);
+    }
+
+    #[sqlx::test]
+    async fn an_empty_database_streams_nothing(pool: PgPool) {
+        let records: Vec<_> = stream_records(&pool).try_collect().await.unwrap();
+        assert!(records.is_empty());
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
+use scraper::Html;
+use sqlx::PgPool;
+use tracing::info;
+use tracing::warn;
+
+use crate::parser;
+use crate::parser::Id;
+use crate::parser::NameOrder;
+use crate::scrape::Scraper;
+use crate::scrape::MAX_STUDENT_PAGES;
+
+/// Names the old `parse_name` got wrong: it put everything after the first comma before the
+/// surname, so "Hall, Marshall, Jr." was stored as "Marshall Jr. Hall", a suffix followed by more
+/// of the name
+const MISORDERED: &str = r"\m(Jr\.?|Sr\.?|II|III|IV)\s+\S";
+
+/// What [`repair_names`] changed, or would have changed on a dry run
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct NameRepair {
+    pub dry_run: bool,
+    /// The ids renamed, with their stored and their corrected name
+    pub renamed: Vec<(Id, String, String)>,
+    /// Names the corrected parser reads the same way
+    pub unchanged: usize,
+    /// Names left for a human to check, with why
+    pub review: Vec<(Id, String, String)>,
+}
+
+impl fmt::Display for NameRepair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (id, before, after) in &self.renamed {
+            writeln!(f, "- {id} {before}")?;
+            writeln!(f, "+ {id} {after}")?;
+        }
+        for (id, name, reason) in &self.review {
+            writeln!(f, "review {id} {name}: {reason}")?;
+        }
+        writeln!(
+            f,
+            "{} {}, {} unchanged, {} to review",
+            if self.dry_run {
+                "would rename"
+            } else {
+                "renamed"
+            },
+            self.renamed.len(),
+            self.unchanged,
+            self.review.len()
+        )
+    }
+}
+
+/// Corrects up to `limit` stored names that look misordered by the old `parse_name`. Each is
+/// listed again from the students table of an advisor whose page it was stored from, fetched like
+/// a scrape and so from the page cache when there is one, and reordered by the corrected parser.
+/// A name that can't be read unambiguously, or that no fetched table lists, is left for review. A
+/// dry run only reports the changes
+pub async fn repair_names(
+    pool: &PgPool,
+    scraper: &Scraper,
+    limit: Option<i64>,
+    dry_run: bool,
+) -> color_eyre::Result<NameRepair> {
+    // a rank means the relation was stored from the advisor's students table
+    let candidates = sqlx::query!(
+        r#"SELECT m.id AS "id: Id", m.name,
+            (SELECT min(r.advisor) FROM advisor_relations r
+                WHERE r.advisee = m.id AND r.rank IS NOT NULL) AS "advisor: Id"
+        FROM mathematicians m
+        WHERE m.name ~ $1
+        ORDER BY m.id
+        LIMIT $2;"#,
+        MISORDERED,
+        limit,
+    )
+    .fetch_all(pool)
+    .await?;
+    info!("Checking {} names", candidates.len());
+
+    let mut repair = NameRepair {
+        dry_run,
+        ..NameRepair::default()
+    };
+    // by advisor id, so that each page is fetched once and in order
+    let mut by_advisor: BTreeMap<i32, Vec<(Id, String)>> = BTreeMap::new();
+    for candidate in candidates {
+        match candidate.advisor {
+            Some(advisor) => by_advisor
+                .entry(advisor.0)
+                .or_default()
+                .push((candidate.id, candidate.name)),
+            None => repair.review.push((
+                candidate.id,
+                candidate.name,
+                "not stored from any advisor's students table".to_string(),
+            )),
+        }
+    }
+
+    let mut mark = scraper.pacer.mark();
+    for (advisor, students) in by_advisor {
+        let advisor = Id(advisor);
+        scraper.pacer.wait(mark, || true).await;
+        mark = scraper.pacer.mark();
+
+        let listed = match listed_students(scraper, advisor).await {
+            Ok(listed) => listed,
+            Err(e) => {
+                warn!("Failed to fetch the students of {advisor}: {e}");
+                for (id, name) in students {
+                    let reason = format!("the page of advisor {advisor} failed: {e}");
+                    repair.review.push((id, name, reason));
+                }
+                continue;
+            }
+        };
+
+        for (id, name) in students {
+            let Some((_, listing)) = listed.iter().find(|(student, _)| *student == id) else {
+                let reason = format!("not listed by advisor {advisor} anymore");
+                repair.review.push((id, name, reason));
+                continue;
+            };
+            let corrected = match parser::display_order(listing) {
+                NameOrder::Certain(corrected) => corrected,
+                NameOrder::Ambiguous(_) => {
+                    let reason =
+                        format!("listed as {listing:?}, which can't be reordered for sure");
+                    repair.review.push((id, name, reason));
+                    continue;
+                }
+            };
+            if corrected == name {
+                repair.unchanged += 1;
+                continue;
+            }
+
+            if !dry_run {
+                sqlx::query!(
+                    "UPDATE mathematicians SET name = $2 WHERE id = $1;",
+                    id as Id,
+                    corrected,
+                )
+                .execute(pool)
+                .await?;
+            }
+            repair.renamed.push((id, name, corrected));
+        }
+    }
+
+    Ok(repair)
+}
+
+/// Every student the students table of `advisor` lists, on all of its pages, as listed
+async fn listed_students(scraper: &Scraper, advisor: Id) -> color_eyre::Result<Vec<(Id, String)>> {
+    let (body, mut url) = scraper.fetch_first_page(advisor).await?;
+    let mut page = Html::parse_document(&body);
+    let mut listed = vec![];
+    for _ in 0..MAX_STUDENT_PAGES {
+        let content = parser::main_content(&page);
+        listed.extend(parser::listed_students(content));
+        let Some(next) = parser::parse_next_students_page(content) else {
+            return Ok(listed);
+        };
+        let next = url.join(next)?;
+        (page, url) = scraper.get_page(next.as_str()).await?;
+    }
+
+    warn!("Stopped following students pages after {MAX_STUDENT_PAGES} pages");
+    Ok(listed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scrape::test::scraper;
+    use sqlx::Executor;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    const STUDENTS: &str = r#"<div id="mainContent"><h2>Man-Duen Choi</h2>
+<table>
+<tr><th>Name</th><th>School</th><th>Year</th><th>Descendants</th></tr>
+<tr><td><a href="id.php?id=6807">Hall, Marshall, Jr.</a></td><td>Yale University</td><td>1936</td><td></td></tr>
+<tr><td><a href="id.php?id=2">Smith, John, Jr. Peter</a></td><td>Yale University</td><td>1937</td><td></td></tr>
+<tr><td><a href="id.php?id=3">Bush, Kenneth, III</a></td><td>Yale University</td><td>1938</td><td></td></tr>
+</table></div>"#;
+
+    async fn stored(pool: &PgPool) -> Vec<(i32, String)> {
+        sqlx::query_as("SELECT id, name FROM mathematicians ORDER BY id")
+            .fetch_all(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn misordered_names_are_listed_again(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians (id, name) VALUES
+                (1, 'Man-Duen Choi'),
+                (6807, 'Marshall Jr. Hall'),
+                (2, 'John Jr. Peter Smith'),
+                (3, 'Kenneth Bush, III'),
+                (4, 'Someone Jr. Else'),
+                (5, 'Roger Keith Alexander');
+            INSERT INTO advisor_relations (advisor, advisee, rank) VALUES
+                (1, 6807, 1), (1, 2, 2), (1, 3, 3);
+            INSERT INTO advisor_relations (advisor, advisee) VALUES (1, 4);",
+        )
+        .await
+        .unwrap();
+        let server = MockServer::start().await;
+        Mock::given(path("/id.php"))
+            .and(query_param("id", "1"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(STUDENTS))
+            .mount(&server)
+            .await;
+        let scraper = scraper(pool.clone(), &server);
+
+        let before = stored(&pool).await;
+        let preview = repair_names(&pool, &scraper, None, true).await.unwrap();
+        assert_eq!(stored(&pool).await, before);
+        assert_eq!(
+            preview.renamed,
+            [(
+                Id(6807),
+                "Marshall Jr. Hall".to_string(),
+                "Marshall Hall, Jr.".to_string()
+            )]
+        );
+        // Bush was stored correctly and doesn't look misordered
+        assert_eq!(preview.unchanged, 0);
+        let review: Vec<_> = preview.review.iter().map(|(id, ..)| *id).collect();
+        assert_eq!(review, [Id(4), Id(2)]);
+        assert!(preview
+            .to_string()
+            .starts_with("- 6807 Marshall Jr. Hall\n+ 6807 Marshall Hall, Jr.\n"));
+        assert!(preview
+            .to_string()
+            .ends_with("would rename 1, 0 unchanged, 2 to review\n"));
+
+        let repair = repair_names(&pool, &scraper, None, false).await.unwrap();
+        assert_eq!(repair.renamed, preview.renamed);
+        let name: String = sqlx::query_scalar("SELECT name FROM mathematicians WHERE id = 6807")
+            .fetch_one(&pool)
+            .await
+            .unwrap();
+        assert_eq!(name, "Marshall Hall, Jr.");
+
+        // a corrected name no longer looks misordered
+        let again = repair_names(&pool, &scraper, None, false).await.unwrap();
+        assert!(again.renamed.is_empty());
+    }
+}
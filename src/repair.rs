@@ -0,0 +1,436 @@
+use std::fmt;
+
+use sqlx::PgPool;
+
+/// What [`rebuild_graduation_records`] removed, or would have removed on a dry run
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct GraduationRepair {
+    /// Records beyond the one kept for each line of a mathematician's degrees
+    pub superseded_records: u64,
+    /// Records of ids that aren't stored as mathematicians
+    pub orphaned_records: u64,
+    /// Schools no record or location refers to anymore
+    pub unused_schools: u64,
+    /// Countries no location refers to anymore
+    pub unused_countries: u64,
+}
+
+impl fmt::Display for GraduationRepair {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "superseded records: {}", self.superseded_records)?;
+        writeln!(f, "orphaned records:   {}", self.orphaned_records)?;
+        writeln!(f, "unused schools:     {}", self.unused_schools)?;
+        writeln!(f, "unused countries:   {}", self.unused_countries)
+    }
+}
+
+/// Brings `graduation_records` back to one record per stored mathematician and degree line. A page
+/// has a line per degree, but the records were only ever added to, so a re-scrape that found a
+/// corrected school or year left the old record next to the new one in the same place. The record
+/// kept of a line is the earliest by year and then school name, the one a rebuilt record is read
+/// from, and schools and countries left unused afterwards are dropped. Everything happens in one
+/// transaction, which a dry run rolls back after counting
+pub async fn rebuild_graduation_records(
+    pool: &PgPool,
+    dry_run: bool,
+) -> color_eyre::Result<GraduationRepair> {
+    let mut transaction = pool.begin().await?;
+
+    let orphaned_records = sqlx::query!(
+        r"DELETE FROM graduation_records g
+        WHERE NOT EXISTS(SELECT 1 FROM mathematicians m WHERE m.id = g.mathematician);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let superseded_records = sqlx::query!(
+        r"DELETE FROM graduation_records g
+        USING (
+            SELECT g.mathematician, g.school, g.year,
+                row_number() OVER (
+                    PARTITION BY g.mathematician, g.position ORDER BY g.year, s.name
+                ) AS n
+            FROM graduation_records g
+            JOIN schools s ON s.id = g.school
+        ) ranked
+        WHERE ranked.n > 1
+            AND (g.mathematician, g.school, g.year)
+                = (ranked.mathematician, ranked.school, ranked.year);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let unused_schools = sqlx::query!(
+        r"DELETE FROM schools s
+        WHERE NOT EXISTS(SELECT 1 FROM graduation_records g WHERE g.school = s.id)
+            AND NOT EXISTS(SELECT 1 FROM school_locations l WHERE l.school = s.id);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let unused_countries = sqlx::query!(
+        r"DELETE FROM countries c
+        WHERE NOT EXISTS(SELECT 1 FROM school_locations l WHERE l.country = c.id);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    if dry_run {
+        transaction.rollback().await?;
+    } else {
+        transaction.commit().await?;
+    }
+
+    Ok(GraduationRepair {
+        superseded_records,
+        orphaned_records,
+        unused_schools,
+        unused_countries,
+    })
+}
+
+/// What [`prune`] removed and added, or would have on a dry run
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Prune {
+    /// Relations neither end of which is stored
+    pub relations: u64,
+    pub graduation_records: u64,
+    pub dissertations: u64,
+    /// Advisors of dissertations that aren't stored
+    pub dissertation_advisors: u64,
+    /// Notes of students fetched for records that are stored or were merged into another id
+    pub fetched_students: u64,
+    /// The names of the foreign keys that were validated, since nothing dangles anymore
+    pub foreign_keys: Vec<String>,
+}
+
+impl fmt::Display for Prune {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "relations:             {}", self.relations)?;
+        writeln!(f, "graduation records:    {}", self.graduation_records)?;
+        writeln!(f, "dissertations:         {}", self.dissertations)?;
+        writeln!(f, "dissertation advisors: {}", self.dissertation_advisors)?;
+        writeln!(f, "fetched students:      {}", self.fetched_students)?;
+        for name in &self.foreign_keys {
+            writeln!(f, "validated foreign key: {name}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Deletes the rows left dangling by mathematicians that were deleted, e.g. by a merge: their
+/// graduation records and dissertations, the advisors of those dissertations, and the relations
+/// with neither end stored. A relation with one end stored is kept, since an advisor named on a
+/// stored page is never stored before their own page is scraped. The notes of students fetched
+/// for a record go too once the record is stored, or merged into another id, since neither is
+/// resumed. The foreign keys that delete graduation records and dissertations along with their
+/// mathematician are added without checking the rows that dangled before, and once those are
+/// pruned the keys are validated. Everything happens in one transaction, which a dry run rolls
+/// back after counting
+pub async fn prune(pool: &PgPool, dry_run: bool) -> color_eyre::Result<Prune> {
+    let mut transaction = pool.begin().await?;
+
+    let relations = sqlx::query!(
+        r"DELETE FROM advisor_relations r
+        WHERE NOT EXISTS(SELECT 1 FROM mathematicians m WHERE m.id = r.advisor)
+            AND NOT EXISTS(SELECT 1 FROM mathematicians m WHERE m.id = r.advisee);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let graduation_records = sqlx::query!(
+        r"DELETE FROM graduation_records g
+        WHERE NOT EXISTS(SELECT 1 FROM mathematicians m WHERE m.id = g.mathematician);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    // before their dissertations, which would take them along uncounted
+    let dissertation_advisors = sqlx::query!(
+        r"DELETE FROM dissertation_advisors a
+        WHERE NOT EXISTS(
+            SELECT 1 FROM dissertations d JOIN mathematicians m ON m.id = d.author
+            WHERE d.title = a.title AND d.author = a.author
+        );"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let dissertations = sqlx::query!(
+        r"DELETE FROM dissertations d
+        WHERE NOT EXISTS(SELECT 1 FROM mathematicians m WHERE m.id = d.author);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    // a note only matters until its record is stored, and a merged id is stored under another one
+    let fetched_students = sqlx::query!(
+        r"DELETE FROM fetched_students f
+        WHERE EXISTS(SELECT 1 FROM mathematicians m WHERE m.id = f.advisor)
+            OR EXISTS(SELECT 1 FROM canonical_id_aliases a WHERE a.alias = f.advisor);"
+    )
+    .execute(&mut *transaction)
+    .await?
+    .rows_affected();
+
+    let unvalidated = sqlx::query!(
+        r#"SELECT conrelid::regclass::text AS "table!", conname::text AS "name!"
+        FROM pg_constraint WHERE contype = 'f' AND NOT convalidated ORDER BY conname;"#
+    )
+    .fetch_all(&mut *transaction)
+    .await?;
+    let mut foreign_keys = vec![];
+    for key in unvalidated {
+        sqlx::query(&format!(
+            "ALTER TABLE {} VALIDATE CONSTRAINT {};",
+            key.table, key.name
+        ))
+        .execute(&mut *transaction)
+        .await?;
+        foreign_keys.push(key.name);
+    }
+
+    if dry_run {
+        transaction.rollback().await?;
+    } else {
+        transaction.commit().await?;
+    }
+
+    Ok(Prune {
+        relations,
+        graduation_records,
+        dissertations,
+        dissertation_advisors,
+        fetched_students,
+        foreign_keys,
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use sqlx::Executor;
+
+    /// Runs `statements` on a database from before the foreign keys, which may leave rows dangling,
+    /// and adds the keys the way the migration does
+    async fn from_before_the_foreign_keys(pool: &PgPool, statements: &str) {
+        pool.execute(
+            r"ALTER TABLE graduation_records DROP CONSTRAINT graduation_records_mathematician_fkey;
+            ALTER TABLE dissertations DROP CONSTRAINT dissertations_author_fkey;
+            ALTER TABLE dissertation_advisors
+                DROP CONSTRAINT dissertation_advisors_dissertation_fkey;",
+        )
+        .await
+        .unwrap();
+        pool.execute(statements).await.unwrap();
+        pool.execute(include_str!("../migrations/0029_foreign_keys.sql"))
+            .await
+            .unwrap();
+    }
+
+    async fn seed(pool: &PgPool) {
+        from_before_the_foreign_keys(
+            pool,
+            r"INSERT INTO mathematicians(id, name) VALUES (1, 'Rescraped'), (2, 'Clean');
+            INSERT INTO schools(name) VALUES
+                ('University of Toronto'), ('Toronto University'), ('McGill University');
+            INSERT INTO countries(name) VALUES ('Canada'), ('Nowhere');
+            INSERT INTO school_locations(school, country) VALUES (1, 1);
+            INSERT INTO graduation_records(mathematician, school, year) VALUES
+                (1, 2, 2004), (1, 1, 2003), (2, 3, 1990), (3, 3, 1991);",
+        )
+        .await;
+    }
+
+    async fn records(pool: &PgPool) -> Vec<(i32, i32, i16)> {
+        sqlx::query_as(
+            "SELECT mathematician, school, year FROM graduation_records ORDER BY mathematician;",
+        )
+        .fetch_all(pool)
+        .await
+        .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn keeps_one_record_per_mathematician(pool: PgPool) {
+        seed(&pool).await;
+
+        let repair = rebuild_graduation_records(&pool, false).await.unwrap();
+        assert_eq!(
+            repair,
+            GraduationRepair {
+                superseded_records: 1,
+                orphaned_records: 1,
+                unused_schools: 1,
+                unused_countries: 1,
+            }
+        );
+        assert_eq!(records(&pool).await, [(1, 1, 2003), (2, 3, 1990)]);
+
+        // a second pass has nothing left to do
+        let again = rebuild_graduation_records(&pool, false).await.unwrap();
+        assert_eq!(again, GraduationRepair::default());
+    }
+
+    #[sqlx::test]
+    async fn keeps_a_record_per_degree_line(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name) VALUES (1, 'Rajesh Pereira');
+            INSERT INTO schools(name) VALUES
+                ('University of Toronto'), ('McGill University'), ('Old Spelling');
+            INSERT INTO graduation_records(mathematician, school, year, degree, position) VALUES
+                (1, 1, 2003, 'Ph.D.', 1), (1, 3, 2004, 'Ph.D.', 1), (1, 2, 1999, 'M.Sc.', 2);",
+        )
+        .await
+        .unwrap();
+
+        let repair = rebuild_graduation_records(&pool, false).await.unwrap();
+        assert_eq!(repair.superseded_records, 1);
+        let mut kept = records(&pool).await;
+        kept.sort();
+        assert_eq!(kept, [(1, 1, 2003), (1, 2, 1999)]);
+    }
+
+    #[sqlx::test]
+    async fn records_stored_before_their_place_share_the_place_of_their_degree(pool: PgPool) {
+        pool.execute(
+            r"ALTER TABLE graduation_records DROP COLUMN position;
+            INSERT INTO mathematicians(id, name) VALUES (1, 'Rajesh Pereira');
+            INSERT INTO schools(name) VALUES
+                ('University of Toronto'), ('McGill University'), ('Old Spelling');
+            INSERT INTO graduation_records(mathematician, school, year, degree) VALUES
+                (1, 1, 2003, 'Ph.D.'), (1, 3, 2004, 'Ph.D.'), (1, 2, 1999, 'M.Sc.');",
+        )
+        .await
+        .unwrap();
+        pool.execute(include_str!("../migrations/0031_graduation_position.sql"))
+            .await
+            .unwrap();
+
+        let places: Vec<(i32, i16)> =
+            sqlx::query_as("SELECT school, position FROM graduation_records ORDER BY school;")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(places, [(1, 2), (2, 1), (3, 2)]);
+
+        let repair = rebuild_graduation_records(&pool, false).await.unwrap();
+        assert_eq!(repair.superseded_records, 1);
+    }
+
+    async fn seed_dangling(pool: &PgPool) {
+        from_before_the_foreign_keys(
+            pool,
+            r"INSERT INTO mathematicians(id, name) VALUES (1, 'Advisor'), (2, 'Student');
+            INSERT INTO schools(name) VALUES ('University of Toronto');
+            INSERT INTO advisor_relations(advisor, advisee) VALUES (1, 2), (3, 2), (1, 4), (5, 6);
+            INSERT INTO graduation_records(mathematician, school, year) VALUES
+                (2, 1, 2003), (5, 1, 1990);
+            INSERT INTO dissertations(title, author) VALUES ('Kept', 2), ('Merged away', 5);
+            INSERT INTO dissertation_advisors(title, author, advisor, rank) VALUES
+                ('Kept', 2, 1, 1), ('Kept', 2, 3, 2), ('Merged away', 5, 6, 1);
+            INSERT INTO canonical_id_aliases(alias, canonical) VALUES (8, 1);
+            INSERT INTO fetched_students(advisor, student) VALUES (1, 4), (8, 4), (9, 10);",
+        )
+        .await;
+    }
+
+    async fn count(pool: &PgPool, table: &str) -> i64 {
+        sqlx::query_scalar(&format!("SELECT count(*) FROM {table};"))
+            .fetch_one(pool)
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn prunes_the_rows_of_deleted_mathematicians(pool: PgPool) {
+        seed_dangling(&pool).await;
+
+        let dry_run = prune(&pool, true).await.unwrap();
+        assert_eq!(count(&pool, "advisor_relations").await, 4);
+
+        let pruned = prune(&pool, false).await.unwrap();
+        assert_eq!(
+            pruned,
+            Prune {
+                relations: 1,
+                graduation_records: 1,
+                dissertations: 1,
+                dissertation_advisors: 1,
+                fetched_students: 2,
+                foreign_keys: vec![
+                    "dissertation_advisors_dissertation_fkey".to_string(),
+                    "dissertations_author_fkey".to_string(),
+                    "graduation_records_mathematician_fkey".to_string(),
+                ],
+            }
+        );
+        assert_eq!(dry_run, pruned);
+        // the advisor not scraped yet and the student only stored as a relation keep their edges
+        let relations: Vec<(i32, i32)> =
+            sqlx::query_as("SELECT advisor, advisee FROM advisor_relations ORDER BY 1, 2;")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(relations, [(1, 2), (1, 4), (3, 2)]);
+        assert_eq!(count(&pool, "dissertation_advisors").await, 2);
+        // only the interrupted scrape of a record not stored yet is still resumed
+        let fetched: Vec<(i32, i32)> =
+            sqlx::query_as("SELECT advisor, student FROM fetched_students;")
+                .fetch_all(&pool)
+                .await
+                .unwrap();
+        assert_eq!(fetched, [(9, 10)]);
+    }
+
+    #[sqlx::test]
+    async fn foreign_keys_are_validated_once_and_delete_along(pool: PgPool) {
+        seed_dangling(&pool).await;
+
+        let pruned = prune(&pool, false).await.unwrap();
+        assert_eq!(pruned.foreign_keys.len(), 3);
+        assert_eq!(prune(&pool, false).await.unwrap(), Prune::default());
+
+        // a record can still be stored before the mathematician it belongs to
+        pool.execute(
+            r"BEGIN;
+            INSERT INTO dissertations(title, author) VALUES ('Early', 7);
+            INSERT INTO mathematicians(id, name) VALUES (7, 'Late');
+            COMMIT;",
+        )
+        .await
+        .unwrap();
+        let dangling = pool
+            .execute(
+                "INSERT INTO graduation_records(mathematician, school, year) VALUES (8, 1, 2000);",
+            )
+            .await;
+        assert!(dangling.is_err());
+
+        pool.execute("DELETE FROM mathematicians WHERE id = 2;")
+            .await
+            .unwrap();
+        assert_eq!(count(&pool, "graduation_records").await, 0);
+        assert_eq!(count(&pool, "dissertation_advisors").await, 0);
+        assert_eq!(count(&pool, "dissertations").await, 1);
+    }
+
+    #[sqlx::test]
+    async fn a_dry_run_only_counts(pool: PgPool) {
+        seed(&pool).await;
+        let before = records(&pool).await;
+
+        let repair = rebuild_graduation_records(&pool, true).await.unwrap();
+        assert_eq!(repair.superseded_records, 1);
+        assert_eq!(records(&pool).await, before);
+    }
+}
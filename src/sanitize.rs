@@ -0,0 +1,180 @@
+use std::num::NonZeroUsize;
+
+use crate::parser::ScrapeRecord;
+
+/// The most characters the text fields of a stored record may have, `None` leaving a field as long
+/// as the page has it. Names cover the record's, its students' and its advisors'
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TextLimits {
+    pub name: Option<NonZeroUsize>,
+    pub title: Option<NonZeroUsize>,
+    pub school: Option<NonZeroUsize>,
+}
+
+/// A text field [`TextLimits::apply`] cut short
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct Truncation {
+    pub field: &'static str,
+    /// How many characters it had
+    pub length: usize,
+    pub limit: usize,
+}
+
+/// `text` without control characters and with every run of whitespace, line breaks and tabs
+/// included, made a single space, trimmed
+pub fn clean_text(text: &str) -> String {
+    let text: String = text
+        .chars()
+        .filter(|c| !c.is_control() || c.is_whitespace())
+        .collect();
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Cuts `text` to `limit` characters, returning how many it had when that was more
+fn truncate(text: &mut String, limit: Option<NonZeroUsize>) -> Option<usize> {
+    let (end, _) = text.char_indices().nth(limit?.get())?;
+    let length = text.chars().count();
+    text.truncate(end);
+    // the cut may fall right after a space
+    text.truncate(text.trim_end().len());
+    Some(length)
+}
+
+impl TextLimits {
+    /// Cleans every text field of `record` with [`clean_text`] and cuts the ones longer than their
+    /// limit, returning what was cut
+    pub fn apply(&self, record: &mut ScrapeRecord) -> Vec<Truncation> {
+        let mut cut = vec![];
+        let mut clean = |field, text: &mut String, limit: Option<NonZeroUsize>| {
+            *text = clean_text(text);
+            if let Some(length) = truncate(text, limit) {
+                cut.push(Truncation {
+                    field,
+                    length,
+                    limit: limit.map_or(0, NonZeroUsize::get),
+                });
+            }
+        };
+
+        clean("name", &mut record.name, self.name);
+        for title in &mut record.dissertations {
+            clean("dissertation", title, self.title);
+        }
+        if let Some(school) = &mut record.school {
+            clean("school", school, self.school);
+        }
+        for text in [
+            &mut record.country,
+            &mut record.degree,
+            &mut record.contributor,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            *text = clean_text(text);
+        }
+        for degree in &mut record.further_degrees {
+            if let Some(school) = &mut degree.school {
+                clean("school", school, self.school);
+            }
+            for text in [&mut degree.country, &mut degree.degree]
+                .into_iter()
+                .flatten()
+            {
+                *text = clean_text(text);
+            }
+        }
+        for advisor in &mut record.advisors {
+            clean("advisor's name", &mut advisor.name, self.name);
+        }
+        for student in &mut record.students {
+            clean("student's name", &mut student.name, self.name);
+            if let Some(school) = &mut student.school {
+                clean("student's school", school, self.school);
+            }
+        }
+
+        cut
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::parser::Student;
+    use crate::parser::StudentsSection;
+
+    fn record(name: &str, title: &str) -> ScrapeRecord {
+        ScrapeRecord {
+            name: name.to_string(),
+            students: vec![Student {
+                name: "Jeremy\tLevick".to_string(),
+                id: None,
+                school: Some("University of\r\nGuelph".to_string()),
+                year: None,
+            }],
+            students_section: StudentsSection::Listed,
+            dissertations: vec![title.to_string()],
+            dissertation_year: None,
+            advisors: vec![],
+            school: Some(" University of Toronto ".to_string()),
+            country: Some("Canada\u{0}".to_string()),
+            year: None,
+            degree: None,
+            further_degrees: vec![],
+            contributor: None,
+            source_updated: None,
+        }
+    }
+
+    #[test]
+    fn control_characters_and_line_breaks_are_cleaned_up() {
+        assert_eq!(
+            clean_text("  Trace Vectors\nin\r\n  Matrix\u{7}\tAnalysis "),
+            "Trace Vectors in Matrix Analysis"
+        );
+        assert_eq!(clean_text("東京\u{3000}大学\u{a0}"), "東京 大学");
+        assert_eq!(clean_text("\n\u{1b}\n"), "");
+
+        let mut cleaned = record("Rajesh\nPereira", "Trace Vectors\nin Matrix Analysis");
+        assert!(TextLimits::default().apply(&mut cleaned).is_empty());
+        assert_eq!(cleaned.name, "Rajesh Pereira");
+        assert_eq!(cleaned.dissertations, ["Trace Vectors in Matrix Analysis"]);
+        assert_eq!(cleaned.school.as_deref(), Some("University of Toronto"));
+        assert_eq!(cleaned.country.as_deref(), Some("Canada"));
+        assert_eq!(cleaned.students[0].name, "Jeremy Levick");
+        assert_eq!(
+            cleaned.students[0].school.as_deref(),
+            Some("University of Guelph")
+        );
+    }
+
+    #[test]
+    fn overlong_fields_are_cut_to_their_limit() {
+        let limits = TextLimits {
+            name: NonZeroUsize::new(14),
+            title: NonZeroUsize::new(20),
+            school: None,
+        };
+        let title = format!(
+            "Trace Vectors\nin Matrix Analysis{}",
+            " and more".repeat(500)
+        );
+        let mut cut = record("Rajesh Pereira", &title);
+
+        assert_eq!(
+            limits.apply(&mut cut),
+            [Truncation {
+                field: "dissertation",
+                length: title.chars().count(),
+                limit: 20,
+            }]
+        );
+        assert_eq!(cut.dissertations, ["Trace Vectors in Mat"]);
+        assert_eq!(cut.name, "Rajesh Pereira");
+
+        let mut title = "Überlagerungen ".repeat(2);
+        assert_eq!(truncate(&mut title, NonZeroUsize::new(15)), Some(30));
+        assert_eq!(title, "Überlagerungen");
+    }
+}
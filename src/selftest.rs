@@ -0,0 +1,200 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use rand_distr::Distribution;
+use rand_distr::Uniform;
+use scraper::Html;
+
+use crate::error::ScraperError;
+use crate::parser::Id;
+use crate::parser::ScrapeFields;
+use crate::scrape::Scraper;
+use crate::scrape::MAX_ID;
+
+/// What a sample of live pages showed about the parser
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
+pub struct Summary {
+    pub sampled: usize,
+    pub parsed: usize,
+    /// Ids the site has no record for, which says nothing about the parser
+    pub not_found: usize,
+    /// Pages that couldn't be fetched, with the error
+    pub unreachable: Vec<(Id, String)>,
+    /// Pages that were fetched but didn't parse into a plausible record, with what was wrong
+    pub broken: Vec<(Id, String)>,
+}
+
+impl Summary {
+    /// The share of the fetched records that parsed, `None` when none were fetched
+    pub fn success_rate(&self) -> Option<f64> {
+        let fetched = self.parsed + self.broken.len();
+        (fetched > 0).then(|| self.parsed as f64 / fetched as f64)
+    }
+}
+
+impl fmt::Display for Summary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "sampled {} pages: {} parsed, {} broken, {} not found, {} unreachable",
+            self.sampled,
+            self.parsed,
+            self.broken.len(),
+            self.not_found,
+            self.unreachable.len()
+        )?;
+        match self.success_rate() {
+            Some(rate) => writeln!(f, "parse success rate {:.1}%", rate * 100.)?,
+            None => writeln!(f, "parse success rate n/a, no record was fetched")?,
+        }
+        for (id, reason) in &self.broken {
+            writeln!(f, "broken      {id}: {reason}")?;
+        }
+        for (id, error) in &self.unreachable {
+            writeln!(f, "unreachable {id}: {error}")?;
+        }
+        Ok(())
+    }
+}
+
+/// `n` distinct ids picked at random from the whole range
+pub fn random_ids(n: usize) -> Vec<Id> {
+    let n = n.min(MAX_ID as usize);
+    let dist = Uniform::new_inclusive(1, MAX_ID);
+    let mut rng = rand::thread_rng();
+
+    let mut ids = HashSet::with_capacity(n);
+    while ids.len() < n {
+        ids.insert(dist.sample(&mut rng));
+    }
+    let mut ids: Vec<_> = ids.into_iter().map(Id).collect();
+    ids.sort_by_key(|id| id.0);
+    ids
+}
+
+/// Fetches the first page of each of `ids`, as far apart as the scrapes of a run, and checks each
+/// parses into a record with a name and at least `min_fields` of the optional fields. Nothing is
+/// written anywhere
+pub async fn run(scraper: &Scraper, ids: &[Id]) -> Summary {
+    let mut summary = Summary {
+        sampled: ids.len(),
+        ..Summary::default()
+    };
+
+    let mut mark = scraper.pacer.mark();
+    for &id in ids {
+        scraper.pacer.wait(mark, || true).await;
+        mark = scraper.pacer.mark();
+
+        let body = match scraper.fetch_first_page(id).await {
+            Ok((body, _)) => body,
+            Err(e) if matches!(e.downcast_ref(), Some(ScraperError::NotFound(_))) => {
+                summary.not_found += 1;
+                continue;
+            }
+            Err(e) => {
+                summary.unreachable.push((id, e.to_string()));
+                continue;
+            }
+        };
+
+        match check_page(scraper, &body) {
+            Ok(()) => summary.parsed += 1,
+            Err(reason) => summary.broken.push((id, reason)),
+        }
+    }
+
+    summary
+}
+
+/// What's wrong with the record parsed from `body`, if anything
+fn check_page(scraper: &Scraper, body: &str) -> Result<(), String> {
+    let page = Html::parse_document(body);
+    let (record, _) = scraper
+        .source
+        .parse(&page, ScrapeFields::ALL)
+        .map_err(|e| e.to_string())?;
+
+    if record.name.trim().is_empty() {
+        return Err("the name is empty".to_string());
+    }
+    let populated = record.populated_fields();
+    if populated < scraper.config.min_fields {
+        return Err(format!(
+            "only {populated} of school, year, country, dissertation and students parsed"
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::scrape::test::fixture;
+    use crate::scrape::test::scraper;
+    use crate::scrape::Databases;
+    use wiremock::matchers::path;
+    use wiremock::matchers::query_param;
+    use wiremock::Mock;
+    use wiremock::MockServer;
+    use wiremock::ResponseTemplate;
+
+    #[test]
+    fn random_ids_are_distinct_and_in_range() {
+        let ids = random_ids(50);
+        assert_eq!(ids.len(), 50);
+        assert_eq!(ids.iter().collect::<HashSet<_>>().len(), 50);
+        assert!(ids.iter().all(|id| (1..=MAX_ID).contains(&id.0)));
+    }
+
+    #[tokio::test]
+    async fn sorts_pages_into_parsed_broken_and_not_found() {
+        let server = MockServer::start().await;
+        for (id, page) in [
+            ("1", fixture("rajesh.html")),
+            ("2", fixture("not-found.html")),
+            (
+                "3",
+                ResponseTemplate::new(200).set_body_string("<html></html>"),
+            ),
+        ] {
+            Mock::given(path("/id.php"))
+                .and(query_param("id", id))
+                .respond_with(page)
+                .mount(&server)
+                .await;
+        }
+        let scraper = scraper(Databases::unconnected().write, &server);
+
+        let summary = run(&scraper, &[Id(1), Id(2), Id(3)]).await;
+        assert_eq!(summary.sampled, 3);
+        assert_eq!(summary.parsed, 1);
+        assert_eq!(summary.not_found, 1);
+        assert!(summary.unreachable.is_empty());
+        assert_eq!(summary.broken.len(), 1);
+        assert_eq!(summary.broken[0].0, Id(3));
+        assert_eq!(summary.success_rate(), Some(0.5));
+
+        // nothing was connected to, let alone written
+        assert_eq!(scraper.db.write.size(), 0);
+    }
+
+    #[test]
+    fn summary_lists_the_failed_ids() {
+        let summary = Summary {
+            sampled: 4,
+            parsed: 3,
+            not_found: 0,
+            unreachable: vec![],
+            broken: vec![(Id(42), "the name is empty".to_string())],
+        };
+
+        assert_eq!(
+            summary.to_string(),
+            "sampled 4 pages: 3 parsed, 1 broken, 0 not found, 0 unreachable\n\
+             parse success rate 75.0%\n\
+             broken      42: the name is empty\n"
+        );
+    }
+}
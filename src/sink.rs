@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+
+use crate::parser::Id;
+use crate::parser::ScrapeRecord;
+
+/// A destination every successfully scraped record is handed to, in addition to the database
+#[async_trait]
+pub trait RecordSink: Send + Sync + std::fmt::Debug {
+    async fn write(&self, id: Id, record: &ScrapeRecord) -> color_eyre::Result<()>;
+
+    /// Sends on what the sink buffered, once no more records are coming
+    async fn flush(&self) -> color_eyre::Result<()> {
+        Ok(())
+    }
+}
+
+/// Publishes each record as a JSON message to NATS, on `<subject>.<id>`
+#[cfg(feature = "nats")]
+#[derive(Debug)]
+pub struct NatsSink {
+    client: async_nats::Client,
+    subject: String,
+}
+
+#[cfg(feature = "nats")]
+impl NatsSink {
+    pub async fn connect(url: &str, subject: impl Into<String>) -> color_eyre::Result<Self> {
+        let client = async_nats::connect(url).await?;
+        Ok(Self {
+            client,
+            subject: subject.into(),
+        })
+    }
+}
+
+#[cfg(feature = "nats")]
+#[async_trait]
+impl RecordSink for NatsSink {
+    async fn write(&self, id: Id, record: &ScrapeRecord) -> color_eyre::Result<()> {
+        let payload = serde_json::to_vec(record)?;
+
+        // the id doubles as the JetStream deduplication key
+        let mut headers = async_nats::HeaderMap::new();
+        headers.insert("Nats-Msg-Id", id.to_string().as_str());
+
+        self.client
+            .publish_with_headers(format!("{}.{id}", self.subject), headers, payload.into())
+            .await?;
+
+        Ok(())
+    }
+
+    async fn flush(&self) -> color_eyre::Result<()> {
+        // publishing only queues a message for the connection to write out
+        self.client.flush().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use std::fmt::Debug;
+
+use scraper::Html;
+
+use crate::parser;
+use crate::parser::FieldWarning;
+use crate::parser::Id;
+use crate::parser::ScrapeFields;
+use crate::parser::ScrapeRecord;
+use crate::parser::Student;
+
+/// A genealogy site the scraper can be pointed at: where its records live and how to read them.
+/// The orchestration (retries, pagination, storage) only talks to a site through this
+pub trait GenealogySource: Send + Sync + Debug {
+    /// The url of the record with `id`
+    fn url_for_id(&self, id: Id) -> String;
+
+    /// The id of the record a url points at, used to follow redirects to a canonical record
+    fn id_for_url(&self, url: &str) -> Option<Id>;
+
+    /// The id the first page of a record gives as its own, when it gives one
+    fn page_id(&self, page: &Html) -> Option<Id>;
+
+    /// Parses `fields` of the first page of a record, with warnings about those that are on the
+    /// page but couldn't be parsed
+    fn parse(
+        &self,
+        page: &Html,
+        fields: ScrapeFields,
+    ) -> color_eyre::Result<(ScrapeRecord, Vec<FieldWarning>)>;
+
+    /// Parses the students on a later page of a record's students table
+    fn parse_students(&self, page: &Html) -> color_eyre::Result<Vec<Student>>;
+
+    /// The link to the next page of the students table, relative to the page
+    fn next_students_page(&self, page: &Html) -> Option<String>;
+
+    /// The link to the page listing everyone descended from the record's mathematician, relative
+    /// to the page, when the site has one
+    fn descendants_page(&self, page: &Html) -> Option<String>;
+
+    /// Parses the ids listed on a descendants page
+    fn parse_descendants(&self, page: &Html) -> Vec<Id>;
+
+    /// Whether the site served a rate limit or CAPTCHA page instead of a record
+    fn is_block_page(&self, body: &str) -> bool;
+
+    /// Whether the site served its page for an id that has no record
+    fn is_not_found(&self, body: &str) -> bool;
+}
+
+/// The Mathematics Genealogy Project
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MathGenealogy {
+    base_url: String,
+}
+
+impl MathGenealogy {
+    pub const BASE_URL: &'static str = "https://www.mathgenealogy.org/";
+
+    /// The site served from somewhere else, e.g. a mirror or a local test server. `base_url` ends
+    /// with a `/`
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+        }
+    }
+}
+
+impl Default for MathGenealogy {
+    fn default() -> Self {
+        Self::new(Self::BASE_URL)
+    }
+}
+
+impl GenealogySource for MathGenealogy {
+    fn url_for_id(&self, id: Id) -> String {
+        format!("{}id.php?id={id}", self.base_url)
+    }
+
+    fn id_for_url(&self, url: &str) -> Option<Id> {
+        parser::parse_id(url)
+    }
+
+    fn page_id(&self, page: &Html) -> Option<Id> {
+        parser::parse_page_id(parser::main_content(page))
+    }
+
+    fn parse(
+        &self,
+        page: &Html,
+        fields: ScrapeFields,
+    ) -> color_eyre::Result<(ScrapeRecord, Vec<FieldWarning>)> {
+        parser::scrape_fields(page, fields)
+    }
+
+    fn parse_students(&self, page: &Html) -> color_eyre::Result<Vec<Student>> {
+        parser::scrape_students(parser::main_content(page))
+    }
+
+    fn next_students_page(&self, page: &Html) -> Option<String> {
+        parser::parse_next_students_page(parser::main_content(page)).map(str::to_string)
+    }
+
+    fn descendants_page(&self, page: &Html) -> Option<String> {
+        parser::parse_descendants_link(parser::main_content(page))
+    }
+
+    fn parse_descendants(&self, page: &Html) -> Vec<Id> {
+        parser::scrape_descendants(parser::main_content(page))
+    }
+
+    fn is_block_page(&self, body: &str) -> bool {
+        parser::is_block_page(body)
+    }
+
+    fn is_not_found(&self, body: &str) -> bool {
+        parser::is_not_found_page(body)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::read_to_string;
+
+    #[test]
+    fn math_genealogy_urls_round_trip() {
+        let source = MathGenealogy::default();
+        let url = source.url_for_id(Id(10416));
+        assert_eq!(url, "https://www.mathgenealogy.org/id.php?id=10416");
+        assert_eq!(source.id_for_url(&url), Some(Id(10416)));
+
+        let local = MathGenealogy::new("http://127.0.0.1:8080/");
+        assert_eq!(local.url_for_id(Id(1)), "http://127.0.0.1:8080/id.php?id=1");
+    }
+
+    #[test]
+    fn math_genealogy_classifies_pages() {
+        let source = MathGenealogy::default();
+        let knuth = read_to_string("knuth.html").unwrap();
+        let not_found = read_to_string("not-found.html").unwrap();
+        let blocked = read_to_string("blocked.html").unwrap();
+
+        assert!(!source.is_block_page(&knuth) && !source.is_not_found(&knuth));
+        assert!(source.is_not_found(&not_found) && !source.is_block_page(&not_found));
+        assert!(source.is_block_page(&blocked));
+
+        let page = Html::parse_document(&knuth);
+        assert_eq!(
+            source.parse(&page, ScrapeFields::ALL).unwrap().0.name,
+            "Donald Ervin Knuth"
+        );
+        assert_eq!(source.page_id(&page), Some(Id(10416)));
+        assert_eq!(source.next_students_page(&page), None);
+    }
+}
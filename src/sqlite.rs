@@ -0,0 +1,287 @@
+use std::path::Path;
+
+use chrono::DateTime;
+use chrono::NaiveDate;
+use chrono::Utc;
+use color_eyre::eyre::eyre;
+use futures::TryStreamExt;
+use sqlx::postgres::PgRow;
+use sqlx::sqlite::SqliteConnectOptions;
+use sqlx::Connection;
+use sqlx::PgPool;
+use sqlx::Row;
+use sqlx::SqliteConnection;
+use tracing::info;
+
+/// A column of a Postgres table, as `information_schema` describes it
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Column {
+    name: String,
+    pg_type: String,
+    not_null: bool,
+}
+
+impl Column {
+    /// The SQLite type the column is stored as. Timestamps become RFC 3339 text and dates ISO 8601
+    /// text, which sort and compare the same way
+    fn sqlite_type(&self) -> color_eyre::Result<&'static str> {
+        Ok(match self.pg_type.as_str() {
+            "smallint" | "integer" | "bigint" | "boolean" => "INTEGER",
+            "real" | "double precision" => "REAL",
+            "text" | "timestamp with time zone" | "date" => "TEXT",
+            "bytea" => "BLOB",
+            other => {
+                return Err(eyre!(
+                    "{} is a {other}, which has no SQLite counterpart yet",
+                    self.name
+                ))
+            }
+        })
+    }
+}
+
+/// A value read from Postgres in the shape SQLite stores it
+#[derive(Debug, PartialEq, Clone)]
+enum Value {
+    Integer(Option<i64>),
+    Real(Option<f64>),
+    Text(Option<String>),
+    Blob(Option<Vec<u8>>),
+}
+
+impl Value {
+    fn read(row: &PgRow, column: &Column) -> color_eyre::Result<Self> {
+        let name = column.name.as_str();
+        Ok(match column.pg_type.as_str() {
+            "smallint" => Value::Integer(row.try_get::<Option<i16>, _>(name)?.map(i64::from)),
+            "integer" => Value::Integer(row.try_get::<Option<i32>, _>(name)?.map(i64::from)),
+            "bigint" => Value::Integer(row.try_get(name)?),
+            "boolean" => Value::Integer(row.try_get::<Option<bool>, _>(name)?.map(i64::from)),
+            "real" => Value::Real(row.try_get::<Option<f32>, _>(name)?.map(f64::from)),
+            "double precision" => Value::Real(row.try_get(name)?),
+            "text" => Value::Text(row.try_get(name)?),
+            "timestamp with time zone" => Value::Text(
+                row.try_get::<Option<DateTime<Utc>>, _>(name)?
+                    .map(|at| at.to_rfc3339()),
+            ),
+            "date" => Value::Text(
+                row.try_get::<Option<NaiveDate>, _>(name)?
+                    .map(|date| date.to_string()),
+            ),
+            "bytea" => Value::Blob(row.try_get(name)?),
+            _ => return Err(eyre!("can't read {name}, a {}", column.pg_type)),
+        })
+    }
+}
+
+/// The columns of `table` in their order
+async fn columns(pool: &PgPool, table: &str) -> color_eyre::Result<Vec<Column>> {
+    let columns = sqlx::query_as::<_, (String, String, bool)>(
+        r"SELECT column_name::text, data_type::text, is_nullable = 'NO'
+        FROM information_schema.columns
+        WHERE table_schema = current_schema() AND table_name = $1
+        ORDER BY ordinal_position;",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await?;
+
+    Ok(columns
+        .into_iter()
+        .map(|(name, pg_type, not_null)| Column {
+            name,
+            pg_type,
+            not_null,
+        })
+        .collect())
+}
+
+/// The columns of the primary key of `table` in key order
+async fn primary_key(pool: &PgPool, table: &str) -> color_eyre::Result<Vec<String>> {
+    Ok(sqlx::query_scalar(
+        r"SELECT a.attname::text FROM pg_index i
+        JOIN pg_attribute a ON a.attrelid = i.indrelid AND a.attnum = ANY(i.indkey)
+        WHERE i.indrelid = $1::regclass AND i.indisprimary
+        ORDER BY array_position(i.indkey::smallint[], a.attnum);",
+    )
+    .bind(table)
+    .fetch_all(pool)
+    .await?)
+}
+
+/// The SQLite DDL of a table with `columns` and `primary_key`
+fn create_table(
+    table: &str,
+    columns: &[Column],
+    primary_key: &[String],
+) -> color_eyre::Result<String> {
+    let mut definitions = columns
+        .iter()
+        .map(|column| {
+            let not_null = if column.not_null { " NOT NULL" } else { "" };
+            Ok(format!(
+                "{} {}{not_null}",
+                column.name,
+                column.sqlite_type()?
+            ))
+        })
+        .collect::<color_eyre::Result<Vec<_>>>()?;
+    if !primary_key.is_empty() {
+        definitions.push(format!("PRIMARY KEY ({})", primary_key.join(", ")));
+    }
+
+    Ok(format!(
+        "CREATE TABLE {table} ({});",
+        definitions.join(", ")
+    ))
+}
+
+/// Copies every row of `table` into the same table of `sqlite`, returning how many were copied
+async fn copy_table(
+    pool: &PgPool,
+    sqlite: &mut SqliteConnection,
+    table: &str,
+) -> color_eyre::Result<u64> {
+    let columns = columns(pool, table).await?;
+    let primary_key = primary_key(pool, table).await?;
+    sqlx::query(&create_table(table, &columns, &primary_key)?)
+        .execute(&mut *sqlite)
+        .await?;
+
+    let names: Vec<_> = columns.iter().map(|c| c.name.as_str()).collect();
+    let insert = format!(
+        "INSERT INTO {table} ({}) VALUES ({});",
+        names.join(", "),
+        vec!["?"; names.len()].join(", ")
+    );
+
+    let select = format!("SELECT {} FROM {table};", names.join(", "));
+    let mut rows = sqlx::query(&select).fetch(pool);
+    let mut count = 0;
+    while let Some(row) = rows.try_next().await? {
+        let mut query = sqlx::query(&insert);
+        for column in &columns {
+            query = match Value::read(&row, column)? {
+                Value::Integer(value) => query.bind(value),
+                Value::Real(value) => query.bind(value),
+                Value::Text(value) => query.bind(value),
+                Value::Blob(value) => query.bind(value),
+            };
+        }
+        query.execute(&mut *sqlite).await?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+/// Writes the `tables` to a new SQLite database at `path`, with the same columns and primary keys
+/// but none of the foreign keys, indexes or triggers. Refuses to touch an existing file
+pub async fn export(pool: &PgPool, tables: &[&str], path: &Path) -> color_eyre::Result<()> {
+    if path.exists() {
+        return Err(eyre!("{} already exists", path.display()));
+    }
+
+    let options = SqliteConnectOptions::new()
+        .filename(path)
+        .create_if_missing(true);
+    let mut sqlite = SqliteConnection::connect_with(&options).await?;
+
+    // one transaction, committing per row would take hours for the whole site
+    let mut transaction = sqlite.begin().await?;
+    for &table in tables {
+        let count = copy_table(pool, &mut transaction, table).await?;
+        info!("Exported {count} rows of {table}");
+    }
+    transaction.commit().await?;
+    sqlite.close().await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::info::TABLES;
+    use sqlx::Executor;
+
+    #[test]
+    fn tables_keep_their_columns_and_primary_key() {
+        let column = |name: &str, pg_type: &str, not_null| Column {
+            name: name.to_string(),
+            pg_type: pg_type.to_string(),
+            not_null,
+        };
+        let columns = [
+            column("id", "integer", true),
+            column("page_hash", "bytea", false),
+            column("updated_at", "timestamp with time zone", true),
+        ];
+
+        assert_eq!(
+            create_table("mathematicians", &columns, &["id".to_string()]).unwrap(),
+            "CREATE TABLE mathematicians (id INTEGER NOT NULL, page_hash BLOB, \
+             updated_at TEXT NOT NULL, PRIMARY KEY (id));"
+        );
+        assert!(create_table("points", &[column("at", "point", false)], &[]).is_err());
+    }
+
+    #[sqlx::test]
+    async fn copies_every_table(pool: PgPool) {
+        pool.execute(
+            r"INSERT INTO mathematicians(id, name, page_hash, updated_at, source_updated) VALUES
+                (1, 'Advisor', '\x0102', '2024-06-01T00:00:00Z', '2019-03-14'),
+                (2, 'Student', NULL, now(), NULL);
+            INSERT INTO advisor_relations(advisor, advisee, rank) VALUES (1, 2, 1);
+            INSERT INTO schools(name) VALUES ('University of Toronto');
+            INSERT INTO graduation_records(mathematician, school, year) VALUES (1, 1, 2003);",
+        )
+        .await
+        .unwrap();
+        let path = std::env::temp_dir().join(format!("combi-export-{}.db", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        export(&pool, TABLES, &path).await.unwrap();
+        assert!(
+            export(&pool, TABLES, &path).await.is_err(),
+            "overwrote the export"
+        );
+
+        let options = SqliteConnectOptions::new().filename(&path);
+        let mut sqlite = SqliteConnection::connect_with(&options).await.unwrap();
+        let tables: Vec<String> =
+            sqlx::query_scalar("SELECT name FROM sqlite_master WHERE type = 'table';")
+                .fetch_all(&mut sqlite)
+                .await
+                .unwrap();
+        assert_eq!(tables.len(), TABLES.len());
+
+        let (name, hash, updated_at, source_updated): (String, Vec<u8>, String, String) =
+            sqlx::query_as(
+                r"SELECT name, page_hash, updated_at, source_updated
+                FROM mathematicians WHERE id = 1;",
+            )
+            .fetch_one(&mut sqlite)
+            .await
+            .unwrap();
+        assert_eq!(name, "Advisor");
+        assert_eq!(hash, [1, 2]);
+        assert_eq!(updated_at, "2024-06-01T00:00:00+00:00");
+        assert_eq!(source_updated, "2019-03-14");
+
+        let year: i64 = sqlx::query_scalar("SELECT year FROM graduation_records;")
+            .fetch_one(&mut sqlite)
+            .await
+            .unwrap();
+        assert_eq!(year, 2003);
+        let relations: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM advisor_relations;")
+            .fetch_one(&mut sqlite)
+            .await
+            .unwrap();
+        assert_eq!(relations, 1);
+
+        sqlite.close().await.unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
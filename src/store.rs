@@ -0,0 +1,33 @@
+use crate::parser::ScrapeFields;
+
+/// How a record is stored
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InsertOptions {
+    /// Store the fields that changed in `field_updates` when the mathematician was already stored
+    pub record_updates: bool,
+    /// What storing an advisor relation that's already stored does
+    pub on_conflict: ConflictStrategy,
+    /// The fields stored, the others are left as they are
+    pub fields: ScrapeFields,
+}
+
+impl Default for InsertOptions {
+    fn default() -> Self {
+        Self {
+            record_updates: false,
+            on_conflict: ConflictStrategy::Update,
+            fields: ScrapeFields::ALL,
+        }
+    }
+}
+
+/// What storing an advisor relation that's already stored does
+#[derive(Debug, PartialEq, Eq, Clone, Copy, clap::ValueEnum)]
+pub enum ConflictStrategy {
+    /// Keep the stored relation as it is
+    Skip,
+    /// Take the rank the page gives, when it gives one
+    Update,
+    /// Fail the record, rolling back everything stored about it
+    Error,
+}
@@ -0,0 +1,129 @@
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+
+use color_eyre::eyre::eyre;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::TcpStream;
+use tracing::info;
+use tracing::warn;
+
+/// Asks a local Tor daemon for a fresh circuit every so many requests, through its control port
+#[derive(Debug)]
+pub struct CircuitRotator {
+    control: SocketAddr,
+    password: Option<String>,
+    rotate_every: u64,
+    requests: AtomicU64,
+}
+
+impl CircuitRotator {
+    pub fn new(control: SocketAddr, password: Option<String>, rotate_every: u64) -> Self {
+        Self {
+            control,
+            password,
+            rotate_every,
+            requests: AtomicU64::new(0),
+        }
+    }
+
+    /// Counts a request, rotating the circuit once enough of them went through the current one
+    pub async fn on_request(&self) {
+        let requests = self.requests.fetch_add(1, Ordering::Relaxed) + 1;
+        if self.rotate_every > 0 && requests.is_multiple_of(self.rotate_every) {
+            self.new_circuit().await;
+        }
+    }
+
+    /// Requests a new circuit, failures are only logged since the old circuit still works
+    pub async fn new_circuit(&self) {
+        match self.signal_newnym().await {
+            Ok(()) => info!("Requested a new Tor circuit"),
+            Err(e) => warn!("Failed to request a new Tor circuit: {e}"),
+        }
+    }
+
+    async fn signal_newnym(&self) -> color_eyre::Result<()> {
+        let stream = TcpStream::connect(self.control).await?;
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+
+        let password = self
+            .password
+            .as_deref()
+            .unwrap_or_default()
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"");
+        // Named separately so the password never ends up in an error message
+        let commands = [
+            ("AUTHENTICATE", format!("AUTHENTICATE \"{password}\"")),
+            ("SIGNAL NEWNYM", "SIGNAL NEWNYM".to_string()),
+        ];
+        for (name, command) in commands {
+            write.write_all(format!("{command}\r\n").as_bytes()).await?;
+
+            let reply = lines.next_line().await?.unwrap_or_default();
+            if !reply.starts_with("250") {
+                return Err(eyre!("Tor refused {name}: {reply}"));
+            }
+        }
+        write.write_all(b"QUIT\r\n").await?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    /// A control port that answers every command with `reply`, returning what it was sent
+    async fn fake_control_port(
+        reply: &'static str,
+    ) -> (SocketAddr, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let handle = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let (read, mut write) = stream.into_split();
+            let mut lines = BufReader::new(read).lines();
+            let mut received = String::new();
+            while let Ok(Some(line)) = lines.next_line().await {
+                received.push_str(&line);
+                received.push('\n');
+                if write.write_all(reply.as_bytes()).await.is_err() {
+                    break;
+                }
+            }
+            received
+        });
+        (addr, handle)
+    }
+
+    #[tokio::test]
+    async fn authenticates_then_signals_newnym() {
+        let (addr, handle) = fake_control_port("250 OK\r\n").await;
+        let rotator = CircuitRotator::new(addr, Some("pa\"ss".to_string()), 1);
+
+        rotator.signal_newnym().await.unwrap();
+        drop(rotator);
+
+        assert_eq!(
+            handle.await.unwrap(),
+            "AUTHENTICATE \"pa\\\"ss\"\nSIGNAL NEWNYM\nQUIT\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn refused_authentication_does_not_leak_the_password() {
+        let (addr, _handle) = fake_control_port("515 Authentication failed\r\n").await;
+        let rotator = CircuitRotator::new(addr, Some("hunter2".to_string()), 1);
+
+        let error = rotator.signal_newnym().await.unwrap_err().to_string();
+        assert!(error.contains("515"));
+        assert!(!error.contains("hunter2"));
+    }
+}